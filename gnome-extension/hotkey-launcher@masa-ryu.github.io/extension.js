@@ -0,0 +1,100 @@
+// Exports window control for alacritty-hotkey-launcher (src/gnome_backend.rs)
+// on GNOME Shell's session bus connection, since Wayland clients cannot list
+// or move other windows themselves.
+import Gio from 'gi://Gio';
+import Meta from 'gi://Meta';
+import {Extension} from 'resource:///org/gnome/shell/extensions/extension.js';
+
+const PATH = '/io/github/MasaRyu/HotkeyLauncher';
+const INTERFACE = `
+<node>
+  <interface name="io.github.MasaRyu.HotkeyLauncher.Windows">
+    <method name="ListWindows">
+      <!-- id, wm_class, title, minimized, on_active_workspace, focused, pid -->
+      <arg type="a(tssbbbi)" direction="out" name="windows"/>
+    </method>
+    <method name="Activate">
+      <arg type="t" direction="in" name="id"/>
+      <arg type="b" direction="out" name="found"/>
+    </method>
+    <method name="Minimize">
+      <arg type="t" direction="in" name="id"/>
+      <arg type="b" direction="out" name="found"/>
+    </method>
+    <method name="MoveToWorkspace">
+      <arg type="t" direction="in" name="id"/>
+      <!-- index, or -1 for the active workspace -->
+      <arg type="i" direction="in" name="workspace"/>
+      <arg type="b" direction="out" name="found"/>
+    </method>
+  </interface>
+</node>`;
+
+function normalWindows() {
+    return global.get_window_actors()
+        .map(actor => actor.meta_window)
+        .filter(w => w.get_window_type() === Meta.WindowType.NORMAL);
+}
+
+function findWindow(id) {
+    return normalWindows().find(w => w.get_id() === id) ?? null;
+}
+
+class Windows {
+    ListWindows() {
+        const active = global.workspace_manager.get_active_workspace();
+        const focus = global.display.focus_window;
+        return normalWindows().map(w => [
+            w.get_id(),
+            w.get_wm_class() ?? '',
+            w.get_title() ?? '',
+            w.minimized,
+            w.is_on_all_workspaces() || w.get_workspace() === active,
+            w === focus,
+            w.get_pid(),
+        ]);
+    }
+
+    Activate(id) {
+        const w = findWindow(id);
+        if (w === null)
+            return false;
+        if (w.minimized)
+            w.unminimize();
+        w.activate(global.get_current_time());
+        return true;
+    }
+
+    Minimize(id) {
+        const w = findWindow(id);
+        if (w === null)
+            return false;
+        w.minimize();
+        return true;
+    }
+
+    MoveToWorkspace(id, index) {
+        const w = findWindow(id);
+        if (w === null)
+            return false;
+        const manager = global.workspace_manager;
+        const workspace = index < 0
+            ? manager.get_active_workspace()
+            : manager.get_workspace_by_index(index);
+        if (workspace !== null)
+            w.change_workspace(workspace);
+        return true;
+    }
+}
+
+export default class HotkeyLauncherExtension extends Extension {
+    enable() {
+        this._exported = Gio.DBusExportedObject.wrapJSObject(INTERFACE, new Windows());
+        this._exported.export(Gio.DBus.session, PATH);
+    }
+
+    disable() {
+        this._exported.unexport();
+        this._exported = null;
+    }
+}
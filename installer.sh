@@ -35,6 +35,7 @@ After=graphical-session.target
 [Service]
 Type=simple
 ExecStart=%h/.local/bin/alacritty-hotkey-launcher
+ExecReload=/bin/kill -HUP $MAINPID
 Environment=ALACRITTY_HOTKEY_LAUNCHER_CONFIG=%h/.config/alacritty-hotkey-launcher/config.toml
 Restart=on-failure
 RestartSec=5
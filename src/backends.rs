@@ -0,0 +1,205 @@
+// The window backends by name: how each rates the running session, which one
+// is picked, and how it is created.
+use crate::common_backend::{Probe, WindowBackend};
+use crate::config::Config;
+use crate::gnome_backend::GnomeBackend;
+use crate::hyprland_backend::HyprlandBackend;
+use crate::i3_backend::I3Backend;
+use crate::kwin_backend::KwinBackend;
+use crate::niri_backend::NiriBackend;
+use crate::wayfire_backend::WayfireBackend;
+use crate::wayland_backend::WaylandBackend;
+use crate::wlr_backend::WlrBackend;
+use crate::x11_backend::X11Backend;
+#[cfg(feature = "x11rb")]
+use crate::x11rb_backend::X11rbBackend;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum BackendKind {
+    Sway,
+    Hyprland,
+    Kwin,
+    Gnome,
+    Wayfire,
+    Niri,
+    I3,
+    Wlr,
+    X11,
+    #[cfg(feature = "x11rb")]
+    X11rb,
+    Wayland,
+}
+
+impl BackendKind {
+    // In probe order; on equal scores the earlier one wins.
+    pub const ALL: &'static [BackendKind] = &[
+        BackendKind::Sway,
+        BackendKind::Hyprland,
+        BackendKind::Kwin,
+        BackendKind::Gnome,
+        BackendKind::Wayfire,
+        BackendKind::Niri,
+        BackendKind::I3,
+        BackendKind::Wlr,
+        BackendKind::X11,
+        #[cfg(feature = "x11rb")]
+        BackendKind::X11rb,
+        BackendKind::Wayland,
+    ];
+
+    // As given to --backend and the `backend` setting
+    pub fn name(self) -> &'static str {
+        match self {
+            BackendKind::Sway => "sway",
+            BackendKind::Hyprland => "hyprland",
+            BackendKind::Kwin => "kwin",
+            BackendKind::Gnome => "gnome",
+            BackendKind::Wayfire => "wayfire",
+            BackendKind::Niri => "niri",
+            BackendKind::I3 => "i3",
+            BackendKind::Wlr => "wlr",
+            BackendKind::X11 => "x11",
+            #[cfg(feature = "x11rb")]
+            BackendKind::X11rb => "x11rb",
+            BackendKind::Wayland => "wayland",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        let name = name.to_ascii_lowercase();
+        Self::ALL.iter().copied().find(|kind| kind.name() == name)
+    }
+
+    pub fn probe(self) -> Probe {
+        match self {
+            BackendKind::Sway => I3Backend::probe_sway(),
+            BackendKind::Hyprland => HyprlandBackend::probe(),
+            BackendKind::Kwin => KwinBackend::probe(),
+            BackendKind::Gnome => GnomeBackend::probe(),
+            BackendKind::Wayfire => WayfireBackend::probe(),
+            BackendKind::Niri => NiriBackend::probe(),
+            BackendKind::I3 => I3Backend::probe_i3(),
+            BackendKind::Wlr => WlrBackend::probe(),
+            BackendKind::X11 => X11Backend::probe(),
+            #[cfg(feature = "x11rb")]
+            BackendKind::X11rb => X11rbBackend::probe(),
+            BackendKind::Wayland => WaylandBackend::probe(),
+        }
+    }
+}
+
+// "sway, hyprland, ... or wayland", for error messages.
+pub fn names() -> String {
+    let names: Vec<&str> = BackendKind::ALL.iter().map(|kind| kind.name()).collect();
+    let (last, rest) = names.split_last().unwrap();
+    format!("{} or {last}", rest.join(", "))
+}
+
+pub fn probe_all() -> Vec<(BackendKind, Probe)> {
+    BackendKind::ALL
+        .iter()
+        .map(|&kind| (kind, kind.probe()))
+        .collect()
+}
+
+// The highest score; X11 when nothing scores at all.
+pub fn best(probes: &[(BackendKind, Probe)]) -> BackendKind {
+    let mut best: Option<&(BackendKind, Probe)> = None;
+    for probe in probes {
+        if probe.1.score > best.map_or(0, |b| b.1.score) {
+            best = Some(probe);
+        }
+    }
+    best.map_or(BackendKind::X11, |b| b.0)
+}
+
+// Fails when the backend's compositor cannot be found.
+pub fn create(kind: BackendKind, config: &Config) -> Result<Box<dyn WindowBackend>, String> {
+    let hide_method = config.hide_method;
+    Ok(match kind {
+        BackendKind::X11 => Box::new(X11Backend::new()),
+        #[cfg(feature = "x11rb")]
+        BackendKind::X11rb => Box::new(X11rbBackend::new()),
+        BackendKind::I3 => match I3Backend::i3_socket() {
+            Some(socket) => Box::new(I3Backend::i3(socket)),
+            None => return Err("cannot find the i3 IPC socket; is i3 running?".into()),
+        },
+        BackendKind::Sway => match I3Backend::sway_socket() {
+            Some(socket) => Box::new(I3Backend::sway(socket, hide_method)),
+            None => return Err("SWAYSOCK is not set; is sway running?".into()),
+        },
+        BackendKind::Hyprland => match HyprlandBackend::socket_dir() {
+            Some(dir) => Box::new(HyprlandBackend::new(dir, hide_method)),
+            None => {
+                return Err("HYPRLAND_INSTANCE_SIGNATURE is not set; is Hyprland running?".into())
+            }
+        },
+        BackendKind::Kwin => Box::new(KwinBackend::new(hide_method)),
+        BackendKind::Gnome => Box::new(GnomeBackend::new(hide_method)),
+        BackendKind::Wayfire => match WayfireBackend::socket() {
+            Some(socket) => Box::new(WayfireBackend::new(socket, hide_method)),
+            None => {
+                return Err(
+                    "WAYFIRE_SOCKET is not set; is Wayfire running with the ipc plugin?".into(),
+                )
+            }
+        },
+        BackendKind::Niri => match NiriBackend::socket() {
+            Some(socket) => Box::new(NiriBackend::new(
+                socket,
+                hide_method,
+                config.stash_workspace.clone(),
+            )),
+            None => return Err("NIRI_SOCKET is not set; is niri running?".into()),
+        },
+        BackendKind::Wlr => Box::new(WlrBackend::new(hide_method)),
+        BackendKind::Wayland => Box::new(WaylandBackend::new()),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn names_round_trip() {
+        for &kind in BackendKind::ALL {
+            assert_eq!(BackendKind::from_name(kind.name()), Some(kind));
+        }
+        assert_eq!(BackendKind::from_name("KWin"), Some(BackendKind::Kwin));
+        assert_eq!(BackendKind::from_name("auto"), None);
+        let expected = if cfg!(feature = "x11rb") {
+            "sway, hyprland, kwin, gnome, wayfire, niri, i3, wlr, x11, x11rb or wayland"
+        } else {
+            "sway, hyprland, kwin, gnome, wayfire, niri, i3, wlr, x11 or wayland"
+        };
+        assert_eq!(names(), expected);
+    }
+
+    #[test]
+    fn best_prefers_high_scores_then_probe_order() {
+        let probes = |scores: &[(BackendKind, u8)]| -> Vec<(BackendKind, Probe)> {
+            scores
+                .iter()
+                .map(|&(kind, score)| (kind, Probe::new(score, "")))
+                .collect()
+        };
+        assert_eq!(
+            best(&probes(&[
+                (BackendKind::Sway, 0),
+                (BackendKind::Wlr, 60),
+                (BackendKind::X11, 70),
+                (BackendKind::Wayland, 5),
+            ])),
+            BackendKind::X11
+        );
+        assert_eq!(
+            best(&probes(&[(BackendKind::Sway, 100), (BackendKind::I3, 100)])),
+            BackendKind::Sway
+        );
+        assert_eq!(
+            best(&probes(&[(BackendKind::Wlr, 0), (BackendKind::Wayland, 0)])),
+            BackendKind::X11
+        );
+    }
+}
@@ -0,0 +1,209 @@
+// Command-line parsing: a handful of subcommands and flags, parsed by hand.
+use crate::backends::{self, BackendKind};
+use std::path::PathBuf;
+
+pub const USAGE: &str = "\
+Usage: alacritty-hotkey-launcher [COMMAND] [OPTIONS]
+
+Commands:
+  run                 Listen for hotkeys (default)
+  toggle [BINDING]    Toggle the app of BINDING (default: the first) once and exit
+  check-config        Validate the config and print the effective settings
+  list-windows        Show the windows each binding matches
+  doctor              Show how well each backend fits this session and which is used
+  ctl <REQUEST>       Send a request to the running daemon, e.g. `ctl toggle notes`
+                      (toggle, show, hide, launch [BINDING]; status, reload, quit)
+
+Options:
+  --config <PATH>     Use this config file instead of searching for one
+  --backend <NAME>    Window backend: sway, hyprland, kwin, gnome, wayfire, niri,
+                      i3, wlr, x11 or wayland (default: the config's backend,
+                      else detected)
+  --lenient           Start even if the config has problems
+  -v, --verbose       Log triggers and actions as they fire
+  -h, --help          Show this help
+";
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Command {
+    Run,
+    // Binding name; None means the first binding
+    Toggle(Option<String>),
+    CheckConfig,
+    ListWindows,
+    Doctor,
+    // Request words for the control socket
+    Ctl(Vec<String>),
+    Help,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Options {
+    pub command: Command,
+    pub config: Option<PathBuf>,
+    // None: pick from the environment
+    pub backend: Option<BackendKind>,
+    pub lenient: bool,
+    pub verbose: bool,
+}
+
+// Parses the arguments after the program name. Flags may appear anywhere and
+// take their value either as the next argument or after '='.
+pub fn parse(args: impl IntoIterator<Item = String>) -> Result<Options, String> {
+    let mut options = Options {
+        command: Command::Run,
+        config: None,
+        backend: None,
+        lenient: false,
+        verbose: false,
+    };
+    let mut help = false;
+    let mut positional = Vec::new();
+    let mut args = args.into_iter();
+
+    while let Some(arg) = args.next() {
+        let (flag, inline) = match arg.split_once('=') {
+            Some((flag, value)) if flag.starts_with("--") => (flag, Some(value.to_string())),
+            _ => (arg.as_str(), None),
+        };
+        let takes_value = matches!(flag, "--config" | "--backend");
+        if inline.is_some() && !takes_value {
+            return Err(format!("option '{flag}' does not take a value"));
+        }
+        let value = if takes_value {
+            inline
+                .or_else(|| args.next())
+                .ok_or_else(|| format!("option '{flag}' needs a value"))?
+        } else {
+            String::new()
+        };
+
+        match flag {
+            "--config" => options.config = Some(PathBuf::from(value)),
+            "--backend" => options.backend = Some(parse_backend(&value)?),
+            "--lenient" => options.lenient = true,
+            "-v" | "--verbose" => options.verbose = true,
+            "-h" | "--help" => help = true,
+            _ if flag.starts_with('-') && flag.len() > 1 => {
+                return Err(format!("unknown option '{flag}'"))
+            }
+            _ => positional.push(arg),
+        }
+    }
+
+    if help {
+        options.command = Command::Help;
+        return Ok(options);
+    }
+    let mut positional = positional.into_iter();
+    options.command = match positional.next().as_deref() {
+        None | Some("run") => Command::Run,
+        Some("toggle") => Command::Toggle(positional.next()),
+        Some("check-config") => Command::CheckConfig,
+        Some("list-windows") => Command::ListWindows,
+        Some("doctor") => Command::Doctor,
+        Some("ctl") => {
+            let request: Vec<String> = positional.by_ref().collect();
+            if request.is_empty() {
+                return Err("ctl needs a request, e.g. 'ctl toggle'".to_string());
+            }
+            Command::Ctl(request)
+        }
+        Some(other) => return Err(format!("unknown command '{other}'")),
+    };
+    if let Some(extra) = positional.next() {
+        return Err(format!("unexpected argument '{extra}'"));
+    }
+    Ok(options)
+}
+
+fn parse_backend(s: &str) -> Result<BackendKind, String> {
+    BackendKind::from_name(s)
+        .ok_or_else(|| format!("unknown backend '{s}' (expected {})", backends::names()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse_args(args: &[&str]) -> Result<Options, String> {
+        parse(args.iter().map(|a| a.to_string()))
+    }
+
+    #[test]
+    fn no_arguments_runs_the_daemon() {
+        assert_eq!(
+            parse_args(&[]),
+            Ok(Options {
+                command: Command::Run,
+                config: None,
+                backend: None,
+                lenient: false,
+                verbose: false,
+            })
+        );
+        // Flags alone still mean run
+        assert_eq!(parse_args(&["--lenient"]).unwrap().command, Command::Run);
+    }
+
+    #[test]
+    fn subcommands_and_flags() {
+        let o = parse_args(&["toggle", "notes", "--config", "/tmp/c.toml", "-v"]).unwrap();
+        assert_eq!(o.command, Command::Toggle(Some("notes".to_string())));
+        assert_eq!(o.config, Some(PathBuf::from("/tmp/c.toml")));
+        assert!(o.verbose);
+
+        let o = parse_args(&["--backend=wayland", "list-windows"]).unwrap();
+        assert_eq!(o.command, Command::ListWindows);
+        assert_eq!(o.backend, Some(BackendKind::Wayland));
+
+        assert_eq!(
+            parse_args(&["check-config", "--config=c.toml"])
+                .unwrap()
+                .config,
+            Some(PathBuf::from("c.toml"))
+        );
+        assert_eq!(parse_args(&["doctor"]).unwrap().command, Command::Doctor);
+        assert_eq!(
+            parse_args(&["toggle"]).unwrap().command,
+            Command::Toggle(None)
+        );
+        assert_eq!(
+            parse_args(&["ctl", "show", "notes"]).unwrap().command,
+            Command::Ctl(vec!["show".to_string(), "notes".to_string()])
+        );
+        assert_eq!(
+            parse_args(&["bogus", "--help"]).unwrap().command,
+            Command::Help
+        );
+    }
+
+    #[test]
+    fn rejects_bad_arguments() {
+        assert_eq!(
+            parse_args(&["--backend", "mir"]),
+            Err(format!(
+                "unknown backend 'mir' (expected {})",
+                backends::names()
+            ))
+        );
+        assert_eq!(
+            parse_args(&["--config"]),
+            Err("option '--config' needs a value".to_string())
+        );
+        assert_eq!(
+            parse_args(&["--verbose=yes"]),
+            Err("option '--verbose' does not take a value".to_string())
+        );
+        assert_eq!(parse_args(&["-x"]), Err("unknown option '-x'".to_string()));
+        assert_eq!(
+            parse_args(&["start"]),
+            Err("unknown command 'start'".to_string())
+        );
+        assert!(parse_args(&["ctl"]).is_err());
+        assert_eq!(
+            parse_args(&["check-config", "extra"]),
+            Err("unexpected argument 'extra'".to_string())
+        );
+    }
+}
@@ -1,93 +1,800 @@
+use crate::keys::key_name;
+use crate::matching::{select_preferred_window, Candidate};
 use rdev::Key;
+use std::env;
+use std::fmt;
+use std::io;
+use std::os::unix::net::UnixStream;
+use std::path::Path;
+use std::process::Command;
+use std::ptr;
 use std::time::{Duration, Instant};
 
 // Public config shared by backends and orchestrator
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct AppConfig {
-    pub double_press_interval: Duration,
+    pub name: String,
     pub app_path: String,
     pub app_name: String,
-    pub detect_key: Key,
+    pub trigger: TriggerSpec,
+}
+
+// What a fired trigger does to the binding's app.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Action {
+    // Show/hide/move the window, launching when none exists
+    Toggle,
+    // Always start a fresh instance
+    Launch,
+    // Hide every matching window
+    HideAll,
+}
+
+// Config syntax: "toggle", "launch" or "hide_all".
+impl fmt::Display for Action {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Action::Toggle => "toggle",
+            Action::Launch => "launch",
+            Action::HideAll => "hide_all",
+        })
+    }
+}
+
+// How Wayland backends hide a window (`wayland_hide_method`).
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum HideMethod {
+    // Whatever suits the compositor: the scratchpad on sway
+    #[default]
+    Auto,
+    Scratchpad,
+    // Never hide; hide() fails with Unsupported, so a toggle is not reported as
+    // having hidden anything
+    None,
+}
+
+impl fmt::Display for HideMethod {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            HideMethod::Auto => "auto",
+            HideMethod::Scratchpad => "scratchpad",
+            HideMethod::None => "none",
+        })
+    }
+}
+
+// How a binding is triggered, as parsed from config.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TriggerSpec {
+    Taps(TapSpec),
+    Chord(Vec<ChordKey>),
+}
+
+impl TriggerSpec {
+    pub fn double_tap(key: Key, interval: Duration) -> Self {
+        TriggerSpec::Taps(TapSpec::double_tap(key, interval))
+    }
+
+    pub fn build(&self) -> Box<dyn Trigger> {
+        match self {
+            TriggerSpec::Taps(spec) => Box::new(TapDetector::new(spec.clone())),
+            TriggerSpec::Chord(keys) => Box::new(ChordDetector::new(keys.clone())),
+        }
+    }
+}
+
+// For the startup log, e.g. "ctrl_left: double_tap=toggle, long_press=hide_all
+// (hold 500 ms, strict)" or "chord:super+grave".
+impl fmt::Display for TriggerSpec {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TriggerSpec::Taps(spec) => {
+                let patterns: Vec<String> = spec
+                    .patterns
+                    .iter()
+                    .map(|(pattern, action)| format!("{pattern}={action}"))
+                    .collect();
+                write!(f, "{}: {}", key_name(spec.key), patterns.join(", "))?;
+                let mut notes = Vec::new();
+                if spec.patterns.iter().any(|(pattern, _)| pattern.hold) {
+                    notes.push(format!("hold {} ms", spec.hold.as_millis()));
+                }
+                if spec.strict {
+                    notes.push("strict".to_string());
+                }
+                if !notes.is_empty() {
+                    write!(f, " ({})", notes.join(", "))?;
+                }
+                Ok(())
+            }
+            TriggerSpec::Chord(keys) => {
+                let names: Vec<String> = keys.iter().map(|k| k.to_string()).collect();
+                write!(f, "chord:{}", names.join("+"))
+            }
+        }
+    }
+}
+
+// A matching window as the backend sees it, for `list-windows`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WindowInfo {
+    pub id: u64,
+    pub class: Option<String>,
+    pub title: Option<String>,
+    // Backend-specific workspace name or number, if known
+    pub workspace: Option<String>,
+    pub on_current_ws: bool,
+    pub visible: bool,
+    // Has input focus, where the backend can tell
+    pub focused: bool,
+    // Process that owns the window, where the backend can tell
+    pub pid: Option<u32>,
+}
+
+fn preferred_window(windows: impl IntoIterator<Item = WindowInfo>) -> Option<u64> {
+    let candidates: Vec<Candidate> = windows
+        .into_iter()
+        .map(|w| Candidate {
+            window: w.id,
+            on_current_ws: w.on_current_ws,
+            visible: w.visible,
+        })
+        .collect();
+    select_preferred_window(&candidates)
+}
+
+// Why a backend operation failed.
+#[derive(Debug)]
+pub enum BackendError {
+    // No connection to the X server or compositor
+    DisplayUnavailable,
+    // The window was closed before the operation reached it
+    WindowGone(u64),
+    // The backend cannot do this; names the operation
+    Unsupported(&'static str),
+    SpawnFailed(io::Error),
+    // The display server rejected a request
+    Protocol(String),
+}
+
+impl fmt::Display for BackendError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BackendError::DisplayUnavailable => f.write_str("display unavailable"),
+            BackendError::WindowGone(id) => write!(f, "window 0x{id:08x} no longer exists"),
+            BackendError::Unsupported(what) => write!(f, "{what} is not supported by this backend"),
+            BackendError::SpawnFailed(error) => write!(f, "cannot start the app: {error}"),
+            BackendError::Protocol(error) => write!(f, "protocol error: {error}"),
+        }
+    }
+}
+
+impl std::error::Error for BackendError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            BackendError::SpawnFailed(error) => Some(error),
+            _ => None,
+        }
+    }
+}
+
+pub type BackendResult<T> = Result<T, BackendError>;
+
+// How long to wait for the compositor to answer. A hung compositor must not
+// hang the hotkey loop.
+pub const REPLY_TIMEOUT: Duration = Duration::from_secs(2);
+
+// What an action did, for logs and control socket replies.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Outcome {
+    Hidden(u64),
+    Shown(u64),
+    // Moved from another workspace, then shown
+    Moved(u64),
+    Launched,
+    // hide_all: how many windows were hidden
+    HiddenAll(usize),
+}
+
+impl fmt::Display for Outcome {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Outcome::Hidden(id) => write!(f, "hid window 0x{id:08x}"),
+            Outcome::Shown(id) => write!(f, "showed window 0x{id:08x}"),
+            Outcome::Moved(id) => write!(f, "moved window 0x{id:08x} here and showed it"),
+            Outcome::Launched => f.write_str("launched the app"),
+            Outcome::HiddenAll(n) => write!(f, "hid {n} window(s)"),
+        }
+    }
 }
 
 // Unified backend interface. Uses a portable u64 as WindowId.
 pub trait WindowBackend {
-    fn find_window(&mut self, app_name: &str) -> Option<u64>;
-    fn is_on_current_workspace(&mut self, window: u64) -> bool;
-    fn is_visible(&mut self, window: u64) -> bool;
-    fn move_to_current_workspace(&mut self, window: u64);
-    fn show(&mut self, window: u64);
-    fn hide(&mut self, window: u64);
-    fn launch_app(&mut self, app_path: &str);
+    // Details of every matching window.
+    fn list_windows(&mut self, app_name: &str) -> BackendResult<Vec<WindowInfo>>;
+    // The preferred matching window. Backends with a cheaper lookup than a
+    // full listing may override this.
+    fn find_window(&mut self, app_name: &str) -> BackendResult<Option<u64>> {
+        Ok(preferred_window(self.list_windows(app_name)?))
+    }
+    // All matching windows.
+    fn find_windows(&mut self, app_name: &str) -> BackendResult<Vec<u64>> {
+        Ok(self
+            .list_windows(app_name)?
+            .into_iter()
+            .map(|w| w.id)
+            .collect())
+    }
+    // The preferred matching window owned by one of `pids`. Finds nothing
+    // where list_windows cannot tell the owning process.
+    fn find_owned_window(&mut self, app_name: &str, pids: &[u32]) -> BackendResult<Option<u64>> {
+        if pids.is_empty() {
+            return Ok(None);
+        }
+        Ok(preferred_window(
+            self.list_windows(app_name)?
+                .into_iter()
+                .filter(|w| w.pid.is_some_and(|pid| pids.contains(&pid))),
+        ))
+    }
+    fn is_on_current_workspace(&mut self, window: u64) -> BackendResult<bool>;
+    fn is_visible(&mut self, window: u64) -> BackendResult<bool>;
+    fn move_to_current_workspace(&mut self, window: u64) -> BackendResult<()>;
+    fn show(&mut self, window: u64) -> BackendResult<()>;
+    fn hide(&mut self, window: u64) -> BackendResult<()>;
+    // Returns the PID of the started process.
+    fn launch_app(&mut self, app_path: &str) -> BackendResult<u32>;
 }
 
-// Core orchestration logic, backend-agnostic.
-pub fn toggle_or_launch(backend: &mut dyn WindowBackend, cfg: &AppConfig) {
-    if let Some(id) = backend.find_window(&cfg.app_name) {
-        if backend.is_on_current_workspace(id) {
-            if backend.is_visible(id) {
-                backend.hide(id);
-            } else {
-                backend.show(id);
+// How well a backend fits the running session, from 0 (unusable) to 100 (its
+// compositor answered), and what that is based on. Shown by `doctor`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Probe {
+    pub score: u8,
+    pub reason: String,
+}
+
+impl Probe {
+    pub fn new(score: u8, reason: impl Into<String>) -> Self {
+        Self {
+            score,
+            reason: reason.into(),
+        }
+    }
+
+    pub fn none(reason: impl Into<String>) -> Self {
+        Self::new(0, reason)
+    }
+
+    // For an IPC socket taken from $var: certain if it accepts a connection.
+    // A socket left over from an earlier session scores low.
+    pub fn socket(var: &str, socket: Option<&Path>) -> Self {
+        match socket {
+            None => Self::none(format!("{var} is not set")),
+            Some(path) if UnixStream::connect(path).is_ok() => {
+                Self::new(100, format!("{} accepts connections", path.display()))
             }
-        } else {
-            backend.move_to_current_workspace(id);
-            backend.show(id);
+            Some(path) => Self::new(
+                10,
+                format!("{} does not accept connections", path.display()),
+            ),
         }
+    }
+}
+
+// Whether $XDG_CURRENT_DESKTOP (a colon-separated list) names `desktop`.
+pub fn current_desktop_is(desktop: &str) -> bool {
+    env::var("XDG_CURRENT_DESKTOP").is_ok_and(|desktops| desktops.split(':').any(|d| d == desktop))
+}
+
+// Starts app_path detached from the daemon; the usual launch_app.
+pub fn spawn_app(app_path: &str) -> BackendResult<u32> {
+    Command::new(app_path)
+        .spawn()
+        .map(|child| child.id())
+        .map_err(BackendError::SpawnFailed)
+}
+
+// The processes a binding has launched and that are still running, so its
+// hotkey can prefer their windows over others of the same class.
+#[derive(Debug, Default)]
+pub struct Launched {
+    pids: Vec<u32>,
+}
+
+impl Launched {
+    pub fn add(&mut self, pid: u32) {
+        self.pids.push(pid);
+    }
+
+    // Reaps and forgets the ones that have exited. Until reaped, a PID cannot
+    // be reused by an unrelated process.
+    pub fn pids(&mut self) -> &[u32] {
+        self.pids.retain(|&pid| {
+            let pid = pid as libc::pid_t;
+            unsafe { libc::waitpid(pid, ptr::null_mut(), libc::WNOHANG) == 0 }
+        });
+        &self.pids
+    }
+}
+
+// A window of a process the binding launched, else any window app_name matches.
+pub fn find_app_window(
+    backend: &mut dyn WindowBackend,
+    cfg: &AppConfig,
+    launched: &mut Launched,
+) -> BackendResult<Option<u64>> {
+    match backend.find_owned_window(&cfg.app_name, launched.pids())? {
+        Some(id) => Ok(Some(id)),
+        None => backend.find_window(&cfg.app_name),
+    }
+}
+
+// Starts a new instance, remembered as one of the binding's own.
+pub fn launch(
+    backend: &mut dyn WindowBackend,
+    cfg: &AppConfig,
+    launched: &mut Launched,
+) -> BackendResult<Outcome> {
+    launched.add(backend.launch_app(&cfg.app_path)?);
+    Ok(Outcome::Launched)
+}
+
+// Core orchestration logic, backend-agnostic.
+pub fn toggle_or_launch(
+    backend: &mut dyn WindowBackend,
+    cfg: &AppConfig,
+    launched: &mut Launched,
+) -> BackendResult<Outcome> {
+    let Some(id) = find_app_window(backend, cfg, launched)? else {
+        return launch(backend, cfg, launched);
+    };
+    if !backend.is_on_current_workspace(id)? {
+        backend.move_to_current_workspace(id)?;
+        backend.show(id)?;
+        Ok(Outcome::Moved(id))
+    } else if backend.is_visible(id)? {
+        backend.hide(id)?;
+        Ok(Outcome::Hidden(id))
     } else {
-        backend.launch_app(&cfg.app_path);
+        backend.show(id)?;
+        Ok(Outcome::Shown(id))
     }
 }
 
-// Robust double-press detector that requires a release between presses.
-pub struct DoublePressDetector {
-    interval: Duration,
-    target: Key,
-    last_press: Option<Instant>,
-    saw_release_since_last_press: bool,
+// Like toggle_or_launch, but never hides.
+pub fn show_or_launch(
+    backend: &mut dyn WindowBackend,
+    cfg: &AppConfig,
+    launched: &mut Launched,
+) -> BackendResult<Outcome> {
+    let Some(id) = find_app_window(backend, cfg, launched)? else {
+        return launch(backend, cfg, launched);
+    };
+    if backend.is_on_current_workspace(id)? {
+        backend.show(id)?;
+        Ok(Outcome::Shown(id))
+    } else {
+        backend.move_to_current_workspace(id)?;
+        backend.show(id)?;
+        Ok(Outcome::Moved(id))
+    }
+}
+
+// A window closed while this runs is skipped rather than failing the rest.
+pub fn hide_all(backend: &mut dyn WindowBackend, cfg: &AppConfig) -> BackendResult<Outcome> {
+    let mut hidden = 0;
+    for id in backend.find_windows(&cfg.app_name)? {
+        let result = match backend.is_visible(id) {
+            Ok(true) => backend.hide(id).map(|()| hidden += 1),
+            Ok(false) => Ok(()),
+            Err(error) => Err(error),
+        };
+        match result {
+            Ok(()) | Err(BackendError::WindowGone(_)) => {}
+            Err(error) => return Err(error),
+        }
+    }
+    Ok(Outcome::HiddenAll(hidden))
+}
+
+pub fn run_action(
+    backend: &mut dyn WindowBackend,
+    cfg: &AppConfig,
+    launched: &mut Launched,
+    action: Action,
+) -> BackendResult<Outcome> {
+    match action {
+        Action::Toggle => toggle_or_launch(backend, cfg, launched),
+        Action::Launch => launch(backend, cfg, launched),
+        Action::HideAll => hide_all(backend, cfg),
+    }
+}
+
+// Common interface for hotkey detectors. Every key event is fed to every
+// trigger; a returned Action means the hotkey fired.
+pub trait Trigger {
+    fn on_key_press(&mut self, key: Key, now: Instant) -> Option<Action>;
+    fn on_key_release(&mut self, key: Key, now: Instant) -> Option<Action>;
+    // Resolves sequences that only complete once time has passed.
+    fn poll(&mut self, _now: Instant) -> Option<Action> {
+        None
+    }
+    // When poll next needs to be called, if at all.
+    fn next_deadline(&self) -> Option<Instant> {
+        None
+    }
+    fn on_button_press(&mut self, _now: Instant) {}
 }
 
-impl DoublePressDetector {
-    pub fn new(interval: Duration, target: Key) -> Self {
+// A hotkey binding: its own detector paired with the app it toggles.
+pub struct Binding {
+    pub config: AppConfig,
+    pub detector: Box<dyn Trigger>,
+    pub launched: Launched,
+}
+
+impl Binding {
+    pub fn new(config: AppConfig) -> Self {
+        let detector = config.trigger.build();
         Self {
+            config,
+            detector,
+            launched: Launched::default(),
+        }
+    }
+}
+
+pub const DEFAULT_HOLD: Duration = Duration::from_millis(500);
+
+// A sequence of short taps, optionally ending in a long press.
+// Double-tap is {taps: 2, hold: false}, long-press {0, true}, tap-then-hold {1, true}.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TapPattern {
+    pub taps: u32,
+    pub hold: bool,
+}
+
+// Config syntax, using the named form where one exists.
+impl fmt::Display for TapPattern {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match (self.taps, self.hold) {
+            (2, false) => f.write_str("double_tap"),
+            (3, false) => f.write_str("triple_tap"),
+            (0, true) => f.write_str("long_press"),
+            (1, true) => f.write_str("tap_hold"),
+            (n, false) => write!(f, "tap:{n}"),
+            (n, true) => write!(f, "tap:{n}+hold"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TapSpec {
+    pub key: Key,
+    // Maximum time between consecutive presses
+    pub interval: Duration,
+    // Minimum press duration that counts as a hold
+    pub hold: Duration,
+    pub patterns: Vec<(TapPattern, Action)>,
+    // Any other key press or mouse button cancels a pending sequence
+    pub strict: bool,
+    // A press held longer than this (and not a configured hold) cancels the sequence
+    pub max_hold: Option<Duration>,
+}
+
+impl TapSpec {
+    pub fn double_tap(key: Key, interval: Duration) -> Self {
+        Self {
+            key,
             interval,
-            target,
+            hold: DEFAULT_HOLD,
+            patterns: vec![(
+                TapPattern {
+                    taps: 2,
+                    hold: false,
+                },
+                Action::Toggle,
+            )],
+            strict: false,
+            max_hold: None,
+        }
+    }
+}
+
+// Tap-pattern detector. Requires a release between presses, so key auto-repeat
+// never counts as a tap. A pattern fires as soon as no other configured pattern
+// could still extend the sequence; otherwise it waits for the interval to pass.
+pub struct TapDetector {
+    spec: TapSpec,
+    taps: u32,
+    pressed_at: Option<Instant>,
+    last_press: Option<Instant>,
+    // The current press already fired or was cancelled; swallow its release.
+    consumed: bool,
+}
+
+impl TapDetector {
+    pub fn new(spec: TapSpec) -> Self {
+        Self {
+            spec,
+            taps: 0,
+            pressed_at: None,
             last_press: None,
-            saw_release_since_last_press: false,
+            consumed: false,
         }
     }
 
-    pub fn on_key_press(&mut self, key: Key, now: Instant) -> bool {
-        if key != self.target {
-            return false;
+    fn action_for(&self, taps: u32, hold: bool) -> Option<Action> {
+        self.spec
+            .patterns
+            .iter()
+            .find(|(p, _)| p.taps == taps && p.hold == hold)
+            .map(|(_, a)| *a)
+    }
+
+    fn has_hold_patterns(&self) -> bool {
+        self.spec.patterns.iter().any(|(p, _)| p.hold)
+    }
+
+    // Whether `taps` short taps could still grow into another pattern, given
+    // `held_taps` taps completed before the press that may turn into a hold.
+    fn extendable(&self, taps: u32, held_taps: u32) -> bool {
+        self.spec
+            .patterns
+            .iter()
+            .any(|(p, _)| p.taps > taps || (p.hold && p.taps >= held_taps))
+    }
+
+    fn reset(&mut self) {
+        self.taps = 0;
+        self.last_press = None;
+    }
+
+    // Strict mode: drop the pending sequence, including a press still held.
+    fn cancel(&mut self) {
+        self.reset();
+        if self.pressed_at.is_some() {
+            self.consumed = true;
         }
+    }
+}
 
-        let triggered = if let Some(prev) = self.last_press {
-            self.saw_release_since_last_press && now.duration_since(prev) <= self.interval
-        } else {
-            false
-        };
+impl Trigger for TapDetector {
+    fn on_key_press(&mut self, key: Key, now: Instant) -> Option<Action> {
+        if key != self.spec.key {
+            if self.spec.strict {
+                self.cancel();
+            }
+            return None;
+        }
+        if self.pressed_at.is_some() {
+            // Auto-repeat while held
+            return None;
+        }
+        if let Some(prev) = self.last_press {
+            if now.duration_since(prev) > self.spec.interval {
+                self.reset();
+            }
+        }
+        self.pressed_at = Some(now);
+        self.last_press = Some(now);
 
-        if triggered {
-            // Reset to avoid triple chaining
-            self.last_press = None;
-            self.saw_release_since_last_press = false;
-            true
-        } else {
-            self.last_press = Some(now);
-            self.saw_release_since_last_press = false;
-            false
+        let taps = self.taps + 1;
+        let action = self.action_for(taps, false)?;
+        if self.extendable(taps, self.taps) {
+            return None;
         }
+        self.reset();
+        self.consumed = true;
+        Some(action)
     }
 
-    pub fn on_key_release(&mut self, key: Key, _now: Instant) {
-        if key == self.target {
-            self.saw_release_since_last_press = true;
+    fn on_key_release(&mut self, key: Key, now: Instant) -> Option<Action> {
+        if key != self.spec.key {
+            return None;
+        }
+        let start = self.pressed_at.take()?;
+        if std::mem::take(&mut self.consumed) {
+            return None;
+        }
+
+        let held = now.duration_since(start);
+        if self.has_hold_patterns() && held >= self.spec.hold {
+            let action = self.action_for(self.taps, true);
+            self.reset();
+            return action;
+        }
+        if self.spec.max_hold.is_some_and(|max| held > max) {
+            // Used as a modifier rather than tapped
+            self.reset();
+            return None;
         }
+
+        self.taps += 1;
+        let action = self.action_for(self.taps, false)?;
+        if self.extendable(self.taps, self.taps) {
+            return None;
+        }
+        self.reset();
+        Some(action)
+    }
+
+    fn poll(&mut self, now: Instant) -> Option<Action> {
+        let deadline = self.next_deadline()?;
+        if now < deadline {
+            return None;
+        }
+        let action = self.action_for(self.taps, false);
+        self.reset();
+        action
+    }
+
+    fn next_deadline(&self) -> Option<Instant> {
+        if self.pressed_at.is_some() || self.taps == 0 {
+            return None;
+        }
+        self.last_press.map(|t| t + self.spec.interval)
+    }
+
+    fn on_button_press(&mut self, _now: Instant) {
+        if self.spec.strict {
+            self.cancel();
+        }
+    }
+}
+
+// One element of a key chord. Generic modifiers match either side.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChordKey {
+    Ctrl,
+    Alt,
+    Shift,
+    Super,
+    Key(Key),
+}
+
+impl ChordKey {
+    pub fn matches(self, key: Key) -> bool {
+        match self {
+            ChordKey::Ctrl => matches!(key, Key::ControlLeft | Key::ControlRight),
+            ChordKey::Alt => matches!(key, Key::Alt | Key::AltGr),
+            ChordKey::Shift => matches!(key, Key::ShiftLeft | Key::ShiftRight),
+            ChordKey::Super => matches!(key, Key::MetaLeft | Key::MetaRight),
+            ChordKey::Key(k) => k == key,
+        }
+    }
+}
+
+impl fmt::Display for ChordKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ChordKey::Ctrl => f.write_str("ctrl"),
+            ChordKey::Alt => f.write_str("alt"),
+            ChordKey::Shift => f.write_str("shift"),
+            ChordKey::Super => f.write_str("super"),
+            ChordKey::Key(k) => f.write_str(&key_name(*k)),
+        }
+    }
+}
+
+fn is_modifier(key: Key) -> bool {
+    [
+        ChordKey::Ctrl,
+        ChordKey::Alt,
+        ChordKey::Shift,
+        ChordKey::Super,
+    ]
+    .iter()
+    .any(|m| m.matches(key))
+}
+
+// Fires when the last key of a combination (e.g. super+grave) is pressed while
+// all the other keys are held and no unrelated modifier is down.
+pub struct ChordDetector {
+    keys: Vec<ChordKey>,
+    held: Vec<Key>,
+}
+
+impl ChordDetector {
+    pub fn new(keys: Vec<ChordKey>) -> Self {
+        Self {
+            keys,
+            held: Vec::new(),
+        }
+    }
+}
+
+impl Trigger for ChordDetector {
+    fn on_key_press(&mut self, key: Key, _now: Instant) -> Option<Action> {
+        if self.held.contains(&key) {
+            // Auto-repeat while held
+            return None;
+        }
+        self.held.push(key);
+
+        let (last, rest) = self.keys.split_last()?;
+        if !last.matches(key) {
+            return None;
+        }
+        let others: Vec<Key> = self.held.iter().copied().filter(|k| *k != key).collect();
+        let all_held = rest.iter().all(|c| others.iter().any(|k| c.matches(*k)));
+        let no_extra_modifiers = others
+            .iter()
+            .filter(|k| is_modifier(**k))
+            .all(|k| rest.iter().any(|c| c.matches(*k)));
+        (all_held && no_extra_modifiers).then_some(Action::Toggle)
+    }
+
+    fn on_key_release(&mut self, key: Key, _now: Instant) -> Option<Action> {
+        self.held.retain(|k| *k != key);
+        None
+    }
+}
+
+// Fixtures shared by the backend tests.
+#[cfg(test)]
+pub mod test_support {
+    use super::*;
+    use std::fs;
+    use std::ops::Deref;
+    use std::path::PathBuf;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    // A binding toggling `app_name`, launching "test".
+    pub fn app(app_name: &str) -> AppConfig {
+        AppConfig {
+            name: "test".into(),
+            app_path: "test".into(),
+            app_name: app_name.into(),
+            trigger: TriggerSpec::double_tap(Key::ControlLeft, Duration::from_millis(300)),
+        }
+    }
+
+    // An empty directory of its own for a test's sockets and files, removed
+    // when dropped.
+    pub struct TempDir(PathBuf);
+
+    impl TempDir {
+        pub fn new(name: &str) -> Self {
+            static NEXT: AtomicUsize = AtomicUsize::new(0);
+            let dir = env::temp_dir().join(format!(
+                "ahl-{name}-{}-{}",
+                std::process::id(),
+                NEXT.fetch_add(1, Ordering::Relaxed)
+            ));
+            let _ = fs::remove_dir_all(&dir);
+            fs::create_dir_all(&dir).unwrap();
+            Self(dir)
+        }
+    }
+
+    impl Deref for TempDir {
+        type Target = Path;
+
+        fn deref(&self) -> &Path {
+            &self.0
+        }
+    }
+
+    impl Drop for TempDir {
+        fn drop(&mut self) {
+            let _ = fs::remove_dir_all(&self.0);
+        }
+    }
+
+    // How every backend reports a compositor it cannot reach.
+    pub fn assert_display_unavailable(backend: &mut dyn WindowBackend) {
+        assert!(matches!(
+            backend.find_window("Alacritty"),
+            Err(BackendError::DisplayUnavailable)
+        ));
     }
 }
 
 #[cfg(test)]
 mod tests {
+    use super::test_support::{app, TempDir};
     use super::*;
 
     // Simple in-memory mock backend for testing orchestrator
@@ -99,6 +806,8 @@ mod tests {
         shown: bool,
         hidden: bool,
         launched: bool,
+        // The window closes before hide reaches it
+        gone: bool,
     }
 
     impl MockBackend {
@@ -111,52 +820,97 @@ mod tests {
                 shown: false,
                 hidden: false,
                 launched: false,
+                gone: false,
             }
         }
     }
 
     impl WindowBackend for MockBackend {
-        fn find_window(&mut self, _app_name: &str) -> Option<u64> {
-            if self.has_window {
-                Some(1)
-            } else {
-                None
-            }
+        fn list_windows(&mut self, _app_name: &str) -> BackendResult<Vec<WindowInfo>> {
+            Ok(self
+                .has_window
+                .then_some(WindowInfo {
+                    id: 1,
+                    class: None,
+                    title: None,
+                    workspace: None,
+                    on_current_ws: self.on_ws,
+                    visible: self.visible,
+                    focused: false,
+                    pid: None,
+                })
+                .into_iter()
+                .collect())
         }
-        fn is_on_current_workspace(&mut self, _window: u64) -> bool {
-            self.on_ws
+        fn is_on_current_workspace(&mut self, _window: u64) -> BackendResult<bool> {
+            Ok(self.on_ws)
         }
-        fn is_visible(&mut self, _window: u64) -> bool {
-            self.visible
+        fn is_visible(&mut self, _window: u64) -> BackendResult<bool> {
+            Ok(self.visible)
         }
-        fn move_to_current_workspace(&mut self, _window: u64) {
+        fn move_to_current_workspace(&mut self, _window: u64) -> BackendResult<()> {
             self.moved = true;
             self.on_ws = true;
+            Ok(())
         }
-        fn show(&mut self, _window: u64) {
+        fn show(&mut self, _window: u64) -> BackendResult<()> {
             self.shown = true;
             self.visible = true;
+            Ok(())
         }
-        fn hide(&mut self, _window: u64) {
+        fn hide(&mut self, window: u64) -> BackendResult<()> {
+            if self.gone {
+                return Err(BackendError::WindowGone(window));
+            }
             self.hidden = true;
             self.visible = false;
+            Ok(())
         }
-        fn launch_app(&mut self, _app_path: &str) {
+        fn launch_app(&mut self, app_path: &str) -> BackendResult<u32> {
+            if app_path.is_empty() {
+                return Err(BackendError::SpawnFailed(io::ErrorKind::NotFound.into()));
+            }
             self.launched = true;
             self.has_window = true;
+            Ok(1)
+        }
+    }
+
+    // Fixed windows, all on the current workspace.
+    struct ListBackend(Vec<WindowInfo>);
+
+    impl WindowBackend for ListBackend {
+        fn list_windows(&mut self, _app_name: &str) -> BackendResult<Vec<WindowInfo>> {
+            Ok(self.0.clone())
+        }
+        fn is_on_current_workspace(&mut self, _window: u64) -> BackendResult<bool> {
+            Ok(true)
+        }
+        fn is_visible(&mut self, window: u64) -> BackendResult<bool> {
+            Ok(self.0.iter().any(|w| w.id == window && w.visible))
+        }
+        fn move_to_current_workspace(&mut self, _window: u64) -> BackendResult<()> {
+            Ok(())
+        }
+        fn show(&mut self, _window: u64) -> BackendResult<()> {
+            Ok(())
+        }
+        fn hide(&mut self, _window: u64) -> BackendResult<()> {
+            Ok(())
+        }
+        fn launch_app(&mut self, _app_path: &str) -> BackendResult<u32> {
+            Err(BackendError::Unsupported("launch"))
         }
     }
 
     #[test]
     fn orchestrator_hides_when_visible_on_ws() {
         let mut be = MockBackend::new(true, true, true);
-        let cfg = AppConfig {
-            double_press_interval: Duration::from_millis(300),
-            app_path: "test".into(),
-            app_name: "Alacritty".into(),
-            detect_key: Key::ControlLeft,
-        };
-        toggle_or_launch(&mut be, &cfg);
+        let cfg = app("Alacritty");
+        assert_eq!(
+            toggle_or_launch(&mut be, &cfg, &mut Launched::default()).unwrap(),
+            Outcome::Hidden(1)
+        );
         assert!(be.hidden);
         assert!(!be.shown);
         assert!(!be.launched);
@@ -165,13 +919,11 @@ mod tests {
     #[test]
     fn orchestrator_shows_when_hidden_on_ws() {
         let mut be = MockBackend::new(true, true, false);
-        let cfg = AppConfig {
-            double_press_interval: Duration::from_millis(300),
-            app_path: "test".into(),
-            app_name: "Alacritty".into(),
-            detect_key: Key::ControlLeft,
-        };
-        toggle_or_launch(&mut be, &cfg);
+        let cfg = app("Alacritty");
+        assert_eq!(
+            toggle_or_launch(&mut be, &cfg, &mut Launched::default()).unwrap(),
+            Outcome::Shown(1)
+        );
         assert!(be.shown);
         assert!(!be.hidden);
         assert!(!be.launched);
@@ -180,13 +932,11 @@ mod tests {
     #[test]
     fn orchestrator_moves_and_shows_when_on_other_ws() {
         let mut be = MockBackend::new(true, false, false);
-        let cfg = AppConfig {
-            double_press_interval: Duration::from_millis(300),
-            app_path: "test".into(),
-            app_name: "Alacritty".into(),
-            detect_key: Key::ControlLeft,
-        };
-        toggle_or_launch(&mut be, &cfg);
+        let cfg = app("Alacritty");
+        assert_eq!(
+            toggle_or_launch(&mut be, &cfg, &mut Launched::default()).unwrap(),
+            Outcome::Moved(1)
+        );
         assert!(be.moved);
         assert!(be.shown);
         assert!(!be.hidden);
@@ -196,31 +946,404 @@ mod tests {
     #[test]
     fn orchestrator_launches_when_not_found() {
         let mut be = MockBackend::new(false, false, false);
+        let cfg = app("Alacritty");
+        assert_eq!(
+            toggle_or_launch(&mut be, &cfg, &mut Launched::default()).unwrap(),
+            Outcome::Launched
+        );
+        assert!(be.launched);
+    }
+
+    #[test]
+    fn show_never_hides() {
+        let cfg = app("Alacritty");
+        let mut be = MockBackend::new(true, true, true);
+        assert_eq!(
+            show_or_launch(&mut be, &cfg, &mut Launched::default()).unwrap(),
+            Outcome::Shown(1)
+        );
+        assert!(be.shown);
+        assert!(!be.hidden);
+
+        let mut be = MockBackend::new(true, false, false);
+        assert_eq!(
+            show_or_launch(&mut be, &cfg, &mut Launched::default()).unwrap(),
+            Outcome::Moved(1)
+        );
+        assert!(be.moved && be.shown);
+
+        let mut be = MockBackend::new(false, false, false);
+        assert_eq!(
+            show_or_launch(&mut be, &cfg, &mut Launched::default()).unwrap(),
+            Outcome::Launched
+        );
+        assert!(be.launched);
+    }
+
+    #[test]
+    fn windows_of_launched_processes_come_first() {
+        let cfg = app("Alacritty");
+        let mut child = Command::new("sleep").arg("30").spawn().unwrap();
+        let window = |id, visible, pid| WindowInfo {
+            id,
+            class: Some("Alacritty".into()),
+            title: None,
+            workspace: None,
+            on_current_ws: true,
+            visible,
+            focused: false,
+            pid,
+        };
+        let mut be = ListBackend(vec![
+            window(1, true, Some(1)),
+            window(2, false, Some(child.id())),
+            window(3, true, None),
+        ]);
+        let mut launched = Launched::default();
+        assert_eq!(
+            find_app_window(&mut be, &cfg, &mut launched).unwrap(),
+            Some(1)
+        );
+
+        // Owned windows win even when another one would rank higher
+        launched.add(child.id());
+        assert_eq!(
+            find_app_window(&mut be, &cfg, &mut launched).unwrap(),
+            Some(2)
+        );
+        assert_eq!(
+            toggle_or_launch(&mut be, &cfg, &mut launched).unwrap(),
+            Outcome::Shown(2)
+        );
+
+        // Once the process is gone, any matching window will do again
+        child.kill().unwrap();
+        child.wait().unwrap();
+        assert_eq!(
+            find_app_window(&mut be, &cfg, &mut launched).unwrap(),
+            Some(1)
+        );
+        assert!(launched.pids().is_empty());
+    }
+
+    #[test]
+    fn errors_are_reported() {
         let cfg = AppConfig {
-            double_press_interval: Duration::from_millis(300),
-            app_path: "test".into(),
-            app_name: "Alacritty".into(),
-            detect_key: Key::ControlLeft,
+            app_path: String::new(),
+            ..app("Alacritty")
         };
-        toggle_or_launch(&mut be, &cfg);
-        assert!(be.launched);
+        let mut be = MockBackend::new(false, false, false);
+        let error = toggle_or_launch(&mut be, &cfg, &mut Launched::default()).unwrap_err();
+        assert!(matches!(error, BackendError::SpawnFailed(_)));
+        assert!(error.to_string().starts_with("cannot start the app: "));
+
+        let mut be = MockBackend::new(true, true, true);
+        be.gone = true;
+        assert_eq!(
+            toggle_or_launch(&mut be, &cfg, &mut Launched::default())
+                .unwrap_err()
+                .to_string(),
+            "window 0x00000001 no longer exists"
+        );
+        // hide_all carries on past windows that have closed
+        assert_eq!(hide_all(&mut be, &cfg).unwrap(), Outcome::HiddenAll(0));
     }
 
     #[test]
     fn double_press_requires_release_and_interval() {
         let target = Key::ControlLeft;
-        let mut dp = DoublePressDetector::new(Duration::from_millis(250), target);
+        let mut dp = TapDetector::new(TapSpec::double_tap(target, Duration::from_millis(250)));
         let t0 = Instant::now();
-        assert!(!dp.on_key_press(target, t0));
+        assert_eq!(dp.on_key_press(target, t0), None);
         // Without release, second press should not trigger
-        assert!(!dp.on_key_press(target, t0 + Duration::from_millis(100)));
+        assert_eq!(
+            dp.on_key_press(target, t0 + Duration::from_millis(100)),
+            None
+        );
         // Now release, then press quickly should trigger
         dp.on_key_release(target, t0 + Duration::from_millis(110));
-        assert!(dp.on_key_press(target, t0 + Duration::from_millis(200)));
+        assert_eq!(
+            dp.on_key_press(target, t0 + Duration::from_millis(200)),
+            Some(Action::Toggle)
+        );
         // After trigger, next press starts new sequence
-        assert!(!dp.on_key_press(target, t0 + Duration::from_millis(300)));
+        assert_eq!(
+            dp.on_key_press(target, t0 + Duration::from_millis(300)),
+            None
+        );
         dp.on_key_release(target, t0 + Duration::from_millis(310));
         // Too late
-        assert!(!dp.on_key_press(target, t0 + Duration::from_millis(700)));
+        assert_eq!(
+            dp.on_key_press(target, t0 + Duration::from_millis(700)),
+            None
+        );
+    }
+
+    #[test]
+    fn chord_fires_when_combination_completes() {
+        let mut ch = ChordDetector::new(vec![ChordKey::Super, ChordKey::Key(Key::BackQuote)]);
+        let t0 = Instant::now();
+        // Key alone does nothing
+        assert_eq!(ch.on_key_press(Key::BackQuote, t0), None);
+        ch.on_key_release(Key::BackQuote, t0);
+        // Modifier then key fires, either side of the modifier
+        assert_eq!(ch.on_key_press(Key::MetaRight, t0), None);
+        assert_eq!(ch.on_key_press(Key::BackQuote, t0), Some(Action::Toggle));
+        // Auto-repeat of the held key does not fire again
+        assert_eq!(ch.on_key_press(Key::BackQuote, t0), None);
+        ch.on_key_release(Key::BackQuote, t0);
+        // Key pressed again while modifier still held fires again
+        assert_eq!(ch.on_key_press(Key::BackQuote, t0), Some(Action::Toggle));
+        ch.on_key_release(Key::BackQuote, t0);
+        ch.on_key_release(Key::MetaRight, t0);
+        assert_eq!(ch.on_key_press(Key::BackQuote, t0), None);
+    }
+
+    #[test]
+    fn chord_rejects_extra_modifiers() {
+        let mut ch = ChordDetector::new(vec![
+            ChordKey::Ctrl,
+            ChordKey::Alt,
+            ChordKey::Key(Key::KeyT),
+        ]);
+        let t0 = Instant::now();
+        assert_eq!(ch.on_key_press(Key::ControlLeft, t0), None);
+        // Missing alt
+        assert_eq!(ch.on_key_press(Key::KeyT, t0), None);
+        ch.on_key_release(Key::KeyT, t0);
+        assert_eq!(ch.on_key_press(Key::Alt, t0), None);
+        assert_eq!(ch.on_key_press(Key::ShiftLeft, t0), None);
+        // Shift is not part of the chord
+        assert_eq!(ch.on_key_press(Key::KeyT, t0), None);
+        ch.on_key_release(Key::KeyT, t0);
+        ch.on_key_release(Key::ShiftLeft, t0);
+        assert_eq!(ch.on_key_press(Key::KeyT, t0), Some(Action::Toggle));
+    }
+
+    fn ms(t0: Instant, ms: u64) -> Instant {
+        t0 + Duration::from_millis(ms)
+    }
+
+    fn tap_spec(patterns: &[(u32, bool, Action)]) -> TapSpec {
+        TapSpec {
+            key: Key::ControlLeft,
+            interval: Duration::from_millis(250),
+            hold: Duration::from_millis(500),
+            patterns: patterns
+                .iter()
+                .map(|&(taps, hold, action)| (TapPattern { taps, hold }, action))
+                .collect(),
+            strict: false,
+            max_hold: None,
+        }
+    }
+
+    #[test]
+    fn trigger_display_lists_the_patterns() {
+        let double = TriggerSpec::Taps(tap_spec(&[(2, false, Action::Toggle)]));
+        assert_eq!(double.to_string(), "ctrl_left: double_tap=toggle");
+
+        let mut spec = tap_spec(&[
+            (2, false, Action::Toggle),
+            (3, false, Action::Launch),
+            (0, true, Action::HideAll),
+        ]);
+        spec.strict = true;
+        assert_eq!(
+            TriggerSpec::Taps(spec).to_string(),
+            "ctrl_left: double_tap=toggle, triple_tap=launch, long_press=hide_all \
+             (hold 500 ms, strict)"
+        );
+    }
+
+    #[test]
+    fn double_and_triple_tap_wait_for_interval() {
+        let k = Key::ControlLeft;
+        let mut td = TapDetector::new(tap_spec(&[
+            (2, false, Action::Toggle),
+            (3, false, Action::Launch),
+        ]));
+        let t0 = Instant::now();
+        // Double tap: ambiguous with triple, so it resolves after the interval
+        assert_eq!(td.on_key_press(k, t0), None);
+        assert_eq!(td.on_key_release(k, ms(t0, 50)), None);
+        assert_eq!(td.on_key_press(k, ms(t0, 150)), None);
+        assert_eq!(td.on_key_release(k, ms(t0, 200)), None);
+        assert_eq!(td.next_deadline(), Some(ms(t0, 400)));
+        assert_eq!(td.poll(ms(t0, 399)), None);
+        assert_eq!(td.poll(ms(t0, 400)), Some(Action::Toggle));
+        assert_eq!(td.next_deadline(), None);
+
+        // Triple tap fires immediately on the third press
+        let t1 = ms(t0, 1000);
+        assert_eq!(td.on_key_press(k, t1), None);
+        assert_eq!(td.on_key_release(k, ms(t1, 50)), None);
+        assert_eq!(td.on_key_press(k, ms(t1, 150)), None);
+        assert_eq!(td.on_key_release(k, ms(t1, 200)), None);
+        assert_eq!(td.on_key_press(k, ms(t1, 300)), Some(Action::Launch));
+        assert_eq!(td.on_key_release(k, ms(t1, 350)), None);
+        assert_eq!(td.poll(ms(t1, 1000)), None);
+
+        // A single tap matches nothing
+        let t2 = ms(t0, 3000);
+        assert_eq!(td.on_key_press(k, t2), None);
+        assert_eq!(td.on_key_release(k, ms(t2, 50)), None);
+        assert_eq!(td.poll(ms(t2, 1000)), None);
+    }
+
+    #[test]
+    fn long_press_fires_on_release() {
+        let k = Key::ControlLeft;
+        let mut td = TapDetector::new(tap_spec(&[
+            (2, false, Action::Toggle),
+            (0, true, Action::HideAll),
+        ]));
+        let t0 = Instant::now();
+        assert_eq!(td.on_key_press(k, t0), None);
+        // Auto-repeat while held
+        assert_eq!(td.on_key_press(k, ms(t0, 300)), None);
+        assert_eq!(td.on_key_release(k, ms(t0, 600)), Some(Action::HideAll));
+
+        // Too short to be a hold: counts as a tap, and the double tap still
+        // fires on the second press since no hold can follow it.
+        let t1 = ms(t0, 2000);
+        assert_eq!(td.on_key_press(k, t1), None);
+        assert_eq!(td.on_key_release(k, ms(t1, 100)), None);
+        assert_eq!(td.on_key_press(k, ms(t1, 200)), Some(Action::Toggle));
+        assert_eq!(td.on_key_release(k, ms(t1, 250)), None);
+    }
+
+    #[test]
+    fn tap_then_hold_is_distinguished_from_double_tap() {
+        let k = Key::ControlLeft;
+        let mut td = TapDetector::new(tap_spec(&[
+            (2, false, Action::Toggle),
+            (1, true, Action::HideAll),
+        ]));
+        let t0 = Instant::now();
+        assert_eq!(td.on_key_press(k, t0), None);
+        assert_eq!(td.on_key_release(k, ms(t0, 50)), None);
+        // Second press could still become a hold, so it does not fire yet
+        assert_eq!(td.on_key_press(k, ms(t0, 150)), None);
+        assert_eq!(td.on_key_release(k, ms(t0, 700)), Some(Action::HideAll));
+
+        let t1 = ms(t0, 2000);
+        assert_eq!(td.on_key_press(k, t1), None);
+        assert_eq!(td.on_key_release(k, ms(t1, 50)), None);
+        assert_eq!(td.on_key_press(k, ms(t1, 150)), None);
+        // Short second press completes the double tap on release
+        assert_eq!(td.on_key_release(k, ms(t1, 200)), Some(Action::Toggle));
+    }
+
+    #[test]
+    fn run_action_hide_all_and_launch() {
+        let cfg = app("Alacritty");
+        let mut be = MockBackend::new(true, true, true);
+        assert_eq!(
+            run_action(&mut be, &cfg, &mut Launched::default(), Action::HideAll).unwrap(),
+            Outcome::HiddenAll(1)
+        );
+        assert!(be.hidden);
+        assert!(!be.launched);
+
+        let mut be = MockBackend::new(true, true, true);
+        assert_eq!(
+            run_action(&mut be, &cfg, &mut Launched::default(), Action::Launch).unwrap(),
+            Outcome::Launched
+        );
+        assert!(be.launched);
+        assert!(!be.hidden);
+    }
+
+    // Ctrl, Ctrl+C, Ctrl: press Ctrl, release, press Ctrl, press C, release both, press Ctrl
+    fn copy_sequence(td: &mut TapDetector, t0: Instant) -> Option<Action> {
+        let k = Key::ControlLeft;
+        td.on_key_press(k, t0);
+        td.on_key_release(k, ms(t0, 40));
+        td.on_key_press(k, ms(t0, 80));
+        td.on_key_press(Key::KeyC, ms(t0, 100));
+        td.on_key_release(Key::KeyC, ms(t0, 120));
+        td.on_key_release(k, ms(t0, 130))
+            .or(td.on_key_press(k, ms(t0, 200)))
+    }
+
+    #[test]
+    fn strict_mode_cancels_on_other_keys() {
+        let mut spec = tap_spec(&[(2, false, Action::Toggle), (3, false, Action::Launch)]);
+        let mut lax = TapDetector::new(spec.clone());
+        let t0 = Instant::now();
+        // Without strict mode the copy shortcut completes a triple tap
+        assert_eq!(copy_sequence(&mut lax, t0), Some(Action::Launch));
+
+        spec.strict = true;
+        let mut td = TapDetector::new(spec);
+        assert_eq!(copy_sequence(&mut td, t0), None);
+        assert_eq!(td.poll(ms(t0, 1000)), None);
+    }
+
+    #[test]
+    fn strict_mode_cancels_a_lone_double_tap() {
+        let k = Key::ControlLeft;
+        // Ctrl, C, Ctrl
+        let ctrl_c_ctrl = |td: &mut TapDetector, t0: Instant| {
+            td.on_key_press(k, t0);
+            td.on_key_release(k, ms(t0, 40));
+            td.on_key_press(Key::KeyC, ms(t0, 60));
+            td.on_key_release(Key::KeyC, ms(t0, 80));
+            td.on_key_press(k, ms(t0, 120))
+        };
+        let mut spec = tap_spec(&[(2, false, Action::Toggle)]);
+        let t0 = Instant::now();
+        let mut lax = TapDetector::new(spec.clone());
+        assert_eq!(ctrl_c_ctrl(&mut lax, t0), Some(Action::Toggle));
+
+        spec.strict = true;
+        let mut td = TapDetector::new(spec);
+        assert_eq!(ctrl_c_ctrl(&mut td, t0), None);
+        assert_eq!(td.on_key_release(k, ms(t0, 160)), None);
+        assert_eq!(td.poll(ms(t0, 1000)), None);
+    }
+
+    #[test]
+    fn strict_mode_cancels_on_mouse_button() {
+        let k = Key::ControlLeft;
+        let mut spec = tap_spec(&[(2, false, Action::Toggle)]);
+        spec.strict = true;
+        let mut td = TapDetector::new(spec);
+        let t0 = Instant::now();
+        assert_eq!(td.on_key_press(k, t0), None);
+        assert_eq!(td.on_key_release(k, ms(t0, 40)), None);
+        td.on_button_press(ms(t0, 60));
+        assert_eq!(td.on_key_press(k, ms(t0, 100)), None);
+        assert_eq!(td.on_key_release(k, ms(t0, 140)), None);
+        // The sequence restarted at the press after the click
+        assert_eq!(td.on_key_press(k, ms(t0, 200)), Some(Action::Toggle));
+    }
+
+    #[test]
+    fn max_hold_cancels_long_presses() {
+        let k = Key::ControlLeft;
+        let mut spec = tap_spec(&[(2, false, Action::Toggle)]);
+        spec.max_hold = Some(Duration::from_millis(150));
+        let mut td = TapDetector::new(spec);
+        let t0 = Instant::now();
+        // Held like a modifier: does not count as the first tap
+        assert_eq!(td.on_key_press(k, t0), None);
+        assert_eq!(td.on_key_release(k, ms(t0, 200)), None);
+        assert_eq!(td.on_key_press(k, ms(t0, 240)), None);
+        assert_eq!(td.on_key_release(k, ms(t0, 280)), None);
+        assert_eq!(td.on_key_press(k, ms(t0, 320)), Some(Action::Toggle));
+    }
+
+    #[test]
+    fn socket_probe_needs_a_listener() {
+        let dir = TempDir::new("probe");
+        let path = dir.join("ipc.sock");
+
+        assert_eq!(Probe::socket("SOCK", None).score, 0);
+        assert_eq!(Probe::socket("SOCK", Some(&path)).score, 10);
+        let listener = std::os::unix::net::UnixListener::bind(&path).unwrap();
+        assert_eq!(Probe::socket("SOCK", Some(&path)).score, 100);
+        drop(listener);
     }
 }
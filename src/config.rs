@@ -1,14 +1,124 @@
-use crate::common_backend::AppConfig;
+use crate::backends::{self, BackendKind};
+use crate::common_backend::{
+    Action, AppConfig, ChordKey, HideMethod, TapPattern, TapSpec, TriggerSpec, DEFAULT_HOLD,
+};
+use crate::keys::{key_name, parse_key};
 use rdev::Key;
 use serde::Deserialize;
+use std::collections::{BTreeMap, HashSet};
+use std::env;
+use std::fmt;
 use std::fs;
+use std::ops::{Range, RangeInclusive};
+use std::os::unix::fs::PermissionsExt;
 use std::path::Path;
 use std::time::Duration;
+use toml_edit::{ImDocument, Item, TableLike};
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Default, Deserialize)]
 struct FileConfig {
     #[serde(default)]
     settings: Settings,
+    #[serde(default)]
+    binding: Vec<BindingSettings>,
+}
+
+// A [[binding]] entry. Unset fields inherit from [settings].
+#[derive(Debug, Deserialize)]
+struct BindingSettings {
+    #[serde(default)]
+    name: Option<String>,
+    #[serde(default)]
+    interval: Option<u64>,
+    #[serde(default)]
+    app_path: Option<String>,
+    #[serde(default)]
+    app_name: Option<String>,
+    #[serde(default)]
+    detected_key: Option<String>,
+    #[serde(default)]
+    trigger: Option<String>,
+    #[serde(default)]
+    hold: Option<u64>,
+    #[serde(default)]
+    actions: Option<BTreeMap<String, String>>,
+    #[serde(default)]
+    strict: Option<bool>,
+    #[serde(default)]
+    max_hold: Option<u64>,
+}
+
+// Fully resolved configuration: one entry per hotkey binding, plus settings
+// that apply to the daemon as a whole.
+#[derive(Debug, PartialEq, Eq)]
+pub struct Config {
+    pub bindings: Vec<AppConfig>,
+    // None: pick the backend from the environment
+    pub backend: Option<BackendKind>,
+    pub hide_method: HideMethod,
+    // Named workspace niri hides windows on
+    pub stash_workspace: String,
+}
+
+// Built-in defaults used when no config file exists anywhere.
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            bindings: vec![AppConfig {
+                name: "default".to_string(),
+                app_path: default_app_path(),
+                app_name: "class=Alacritty".to_string(),
+                trigger: TriggerSpec::double_tap(
+                    rdev::Key::ControlLeft,
+                    Duration::from_millis(default_interval()),
+                ),
+            }],
+            backend: None,
+            hide_method: HideMethod::default(),
+            stash_workspace: default_stash_workspace(),
+        }
+    }
+}
+
+// The effective config in file syntax, every binding spelled out in full.
+impl fmt::Display for Config {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let quote = |s: &str| toml_edit::Value::from(s).to_string();
+        writeln!(f, "[settings]")?;
+        writeln!(
+            f,
+            "backend = {}",
+            quote(self.backend.map_or("auto", BackendKind::name))
+        )?;
+        writeln!(
+            f,
+            "wayland_hide_method = {}",
+            quote(&self.hide_method.to_string())
+        )?;
+        writeln!(f, "niri_stash_workspace = {}", quote(&self.stash_workspace))?;
+        for b in &self.bindings {
+            writeln!(f, "\n[[binding]]")?;
+            writeln!(f, "name = {}", quote(&b.name))?;
+            writeln!(f, "app_path = {}", quote(&b.app_path))?;
+            writeln!(f, "app_name = {}", quote(&b.app_name))?;
+            writeln!(f, "trigger = {}", quote(&trigger_value(&b.trigger)))?;
+            let TriggerSpec::Taps(tap) = &b.trigger else {
+                continue;
+            };
+            writeln!(f, "interval = {}", tap.interval.as_millis())?;
+            writeln!(f, "hold = {}", tap.hold.as_millis())?;
+            writeln!(f, "strict = {}", tap.strict)?;
+            if let Some(max_hold) = tap.max_hold {
+                writeln!(f, "max_hold = {}", max_hold.as_millis())?;
+            }
+            writeln!(f, "\n[binding.actions]")?;
+            for (pattern, action) in &tap.patterns {
+                let key = toml_edit::Key::new(pattern.to_string());
+                writeln!(f, "{} = {}", key.display_repr(), quote(&action.to_string()))?;
+            }
+        }
+        Ok(())
+    }
 }
 
 #[derive(Debug, Deserialize)]
@@ -24,6 +134,30 @@ struct Settings {
     detected_key: Option<String>,
     #[serde(default)]
     detected_keys: Option<Vec<String>>,
+    // "double_tap[:key]" (default) or "chord:super+grave"
+    #[serde(default)]
+    trigger: Option<String>,
+    // Long-press threshold (ms)
+    #[serde(default)]
+    hold: Option<u64>,
+    // Tap pattern -> action, e.g. triple_tap = "launch". Defaults to double_tap = "toggle".
+    #[serde(default)]
+    actions: Option<BTreeMap<String, String>>,
+    // Cancel a pending tap sequence on any other key press or mouse button
+    #[serde(default)]
+    strict: bool,
+    // Cancel when the key is held longer than this (ms), e.g. used as a modifier
+    #[serde(default)]
+    max_hold: Option<u64>,
+    // "auto" or a backend name, as for --backend
+    #[serde(default)]
+    backend: Option<String>,
+    // "auto", "scratchpad" or "none"
+    #[serde(default)]
+    wayland_hide_method: Option<String>,
+    // Named workspace (declared in the niri config) that niri hides windows on
+    #[serde(default = "default_stash_workspace")]
+    niri_stash_workspace: String,
 }
 
 fn default_interval() -> u64 {
@@ -35,6 +169,9 @@ fn default_app_path() -> String {
 fn default_app_name() -> String {
     "Alacritty".to_string()
 }
+fn default_stash_workspace() -> String {
+    "stash".to_string()
+}
 
 impl Default for Settings {
     fn default() -> Self {
@@ -44,59 +181,680 @@ impl Default for Settings {
             app_name: default_app_name(),
             detected_key: None,
             detected_keys: None,
+            trigger: None,
+            hold: None,
+            actions: None,
+            strict: false,
+            max_hold: None,
+            backend: None,
+            wayland_hide_method: None,
+            niri_stash_workspace: default_stash_workspace(),
+        }
+    }
+}
+
+// Accepted keys per table; anything else is reported as unknown.
+const ROOT_KEYS: &[&str] = &["settings", "binding"];
+const SETTINGS_KEYS: &[&str] = &[
+    "interval",
+    "app_path",
+    "app_name",
+    "detected_key",
+    "detected_keys",
+    "trigger",
+    "hold",
+    "actions",
+    "strict",
+    "max_hold",
+    "backend",
+    "wayland_hide_method",
+    "niri_stash_workspace",
+];
+const BINDING_KEYS: &[&str] = &[
+    "name",
+    "interval",
+    "app_path",
+    "app_name",
+    "detected_key",
+    "trigger",
+    "hold",
+    "actions",
+    "strict",
+    "max_hold",
+];
+
+// Accepted ranges, in milliseconds.
+const INTERVAL_RANGE: RangeInclusive<u64> = 50..=2_000;
+const HOLD_RANGE: RangeInclusive<u64> = 100..=10_000;
+const MAX_HOLD_RANGE: RangeInclusive<u64> = 50..=10_000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConfigErrorKind {
+    Io,
+    Syntax,
+    UnknownKey,
+    OutOfRange,
+    InvalidValue,
+    AppPath,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConfigError {
+    pub kind: ConfigErrorKind,
+    pub message: String,
+    // Byte range in the source and the 1-based (line, column) where it starts
+    pub span: Option<Range<usize>>,
+    pub location: Option<(usize, usize)>,
+}
+
+impl ConfigError {
+    fn new(kind: ConfigErrorKind, message: String, span: Option<Range<usize>>, src: &str) -> Self {
+        let location = span.as_ref().map(|span| line_column(src, span.start));
+        Self {
+            kind,
+            message,
+            span,
+            location,
+        }
+    }
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.location {
+            Some((line, column)) => write!(f, "line {line}, column {column}: {}", self.message),
+            None => f.write_str(&self.message),
+        }
+    }
+}
+
+fn line_column(src: &str, offset: usize) -> (usize, usize) {
+    let before = src.get(..offset).unwrap_or(src);
+    let line = before.matches('\n').count() + 1;
+    let column = before.rsplit('\n').next().unwrap_or("").chars().count() + 1;
+    (line, column)
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Section {
+    Settings,
+    Binding(usize),
+}
+
+// Collects every problem found while loading, located via the parsed document.
+struct Diagnostics<'a> {
+    src: &'a str,
+    doc: ImDocument<&'a str>,
+    errors: Vec<ConfigError>,
+}
+
+impl<'a> Diagnostics<'a> {
+    fn table(&self, section: Section) -> Option<&dyn TableLike> {
+        match section {
+            Section::Settings => self.doc.get("settings")?.as_table_like(),
+            Section::Binding(i) => match self.doc.get("binding")? {
+                Item::ArrayOfTables(tables) => tables.get(i).map(|t| t as &dyn TableLike),
+                item => item
+                    .as_array()?
+                    .get(i)?
+                    .as_inline_table()
+                    .map(|t| t as &dyn TableLike),
+            },
+        }
+    }
+
+    // Walks to the table holding the last element of `path`, e.g. ["actions", "triple_tap"].
+    fn parent(&self, section: Section, path: &[&str]) -> Option<(&dyn TableLike, String)> {
+        let (last, parents) = path.split_last()?;
+        let mut table = self.table(section)?;
+        for p in parents {
+            table = table.get(p)?.as_table_like()?;
+        }
+        Some((table, last.to_string()))
+    }
+
+    fn value_span(&self, section: Section, path: &[&str]) -> Option<Range<usize>> {
+        let (table, last) = self.parent(section, path)?;
+        table.get(&last)?.span()
+    }
+
+    fn key_span(&self, section: Section, path: &[&str]) -> Option<Range<usize>> {
+        let (table, last) = self.parent(section, path)?;
+        table.key(&last)?.span()
+    }
+
+    fn report(&mut self, kind: ConfigErrorKind, message: String, span: Option<Range<usize>>) {
+        self.errors
+            .push(ConfigError::new(kind, message, span, self.src));
+    }
+
+    fn report_value(
+        &mut self,
+        kind: ConfigErrorKind,
+        section: Section,
+        key: &str,
+        message: String,
+    ) {
+        let span = self.value_span(section, &[key]);
+        self.report(kind, message, span);
+    }
+
+    fn check_unknown_keys(&mut self, bindings: usize) {
+        let mut unknown = unknown_keys(self.doc.as_table(), ROOT_KEYS, "top level");
+        if let Some(table) = self.table(Section::Settings) {
+            unknown.extend(unknown_keys(table, SETTINGS_KEYS, "[settings]"));
+        }
+        for i in 0..bindings {
+            if let Some(table) = self.table(Section::Binding(i)) {
+                unknown.extend(unknown_keys(table, BINDING_KEYS, "[[binding]]"));
+            }
+        }
+        for (message, span) in unknown {
+            self.report(ConfigErrorKind::UnknownKey, message, span);
+        }
+    }
+
+    // Out-of-range values are reported and replaced by None.
+    fn millis(
+        &mut self,
+        section: Section,
+        key: &str,
+        value: Option<u64>,
+        range: RangeInclusive<u64>,
+    ) -> Option<Duration> {
+        let value = value?;
+        if range.contains(&value) {
+            return Some(Duration::from_millis(value));
+        }
+        self.report_value(
+            ConfigErrorKind::OutOfRange,
+            section,
+            key,
+            format!(
+                "{key} = {value} ms is out of range ({}..={} ms)",
+                range.start(),
+                range.end()
+            ),
+        );
+        None
+    }
+
+    fn key(&mut self, section: Section, field: &str, name: &str) -> Option<Key> {
+        match parse_key(name) {
+            Ok(key) => Some(key),
+            Err(e) => {
+                self.report_value(
+                    ConfigErrorKind::InvalidValue,
+                    section,
+                    field,
+                    format!("{field}: {e}"),
+                );
+                None
+            }
         }
     }
+
+    fn trigger(&mut self, section: Section, trigger: Option<&str>, tap: &TapSpec) -> TriggerSpec {
+        match resolve_trigger(trigger, tap) {
+            Ok(trigger) => trigger,
+            Err(e) => {
+                self.report_value(
+                    ConfigErrorKind::InvalidValue,
+                    section,
+                    "trigger",
+                    format!("trigger: {e}"),
+                );
+                TriggerSpec::Taps(tap.clone())
+            }
+        }
+    }
+
+    fn backend(&mut self, value: Option<&str>) -> Option<BackendKind> {
+        let name = value.filter(|v| !v.eq_ignore_ascii_case("auto"))?;
+        let kind = BackendKind::from_name(name);
+        if kind.is_none() {
+            self.report_value(
+                ConfigErrorKind::InvalidValue,
+                Section::Settings,
+                "backend",
+                format!(
+                    "unknown backend '{name}' (expected auto, {})",
+                    backends::names()
+                ),
+            );
+        }
+        kind
+    }
+
+    fn hide_method(&mut self, value: Option<&str>) -> HideMethod {
+        match value.map(str::to_ascii_lowercase).as_deref() {
+            None | Some("auto") => HideMethod::Auto,
+            Some("scratchpad") => HideMethod::Scratchpad,
+            Some("none") => HideMethod::None,
+            Some(_) => {
+                self.report_value(
+                    ConfigErrorKind::InvalidValue,
+                    Section::Settings,
+                    "wayland_hide_method",
+                    format!(
+                        "unknown wayland_hide_method '{}' (expected auto, scratchpad or none)",
+                        value.unwrap_or_default()
+                    ),
+                );
+                HideMethod::Auto
+            }
+        }
+    }
+
+    fn stash_workspace(&mut self, name: String) -> String {
+        if !name.trim().is_empty() {
+            return name;
+        }
+        self.report_value(
+            ConfigErrorKind::InvalidValue,
+            Section::Settings,
+            "niri_stash_workspace",
+            "niri_stash_workspace must not be empty".to_string(),
+        );
+        default_stash_workspace()
+    }
+
+    // Invalid entries are reported and skipped; an empty table falls back to
+    // double_tap = "toggle".
+    fn actions(
+        &mut self,
+        section: Section,
+        actions: Option<&BTreeMap<String, String>>,
+    ) -> Vec<(TapPattern, Action)> {
+        let mut patterns = Vec::new();
+        for (pattern, action) in actions.into_iter().flatten() {
+            let path = ["actions", pattern.as_str()];
+            let Some(p) = parse_pattern(pattern) else {
+                let span = self.key_span(section, &path);
+                self.report(
+                    ConfigErrorKind::InvalidValue,
+                    format!("unknown tap pattern '{pattern}'"),
+                    span,
+                );
+                continue;
+            };
+            let Some(a) = parse_action(action) else {
+                let span = self.value_span(section, &path);
+                self.report(
+                    ConfigErrorKind::InvalidValue,
+                    format!("unknown action '{action}' (expected toggle, launch or hide_all)"),
+                    span,
+                );
+                continue;
+            };
+            patterns.push((p, a));
+        }
+        if patterns.is_empty() {
+            vec![(
+                TapPattern {
+                    taps: 2,
+                    hold: false,
+                },
+                Action::Toggle,
+            )]
+        } else {
+            patterns
+        }
+    }
+
+    fn app_path(&mut self, section: Section, path: &str) {
+        if let Err(message) = check_app_path(path) {
+            self.report_value(ConfigErrorKind::AppPath, section, "app_path", message);
+        }
+    }
+}
+
+fn unknown_keys(
+    table: &dyn TableLike,
+    allowed: &[&str],
+    context: &str,
+) -> Vec<(String, Option<Range<usize>>)> {
+    table
+        .iter()
+        .filter(|(key, _)| !allowed.contains(key))
+        .map(|(key, _)| {
+            (
+                format!("unknown key '{key}' in {context}"),
+                table.key(key).and_then(|k| k.span()),
+            )
+        })
+        .collect()
 }
 
-pub fn load_from_str(s: &str) -> AppConfig {
+// The app must be an executable file, either by path or found in $PATH.
+fn check_app_path(path: &str) -> Result<(), String> {
+    fn is_executable(p: &Path) -> bool {
+        fs::metadata(p)
+            .map(|m| m.is_file() && m.permissions().mode() & 0o111 != 0)
+            .unwrap_or(false)
+    }
+
+    if path.contains('/') {
+        if !Path::new(path).exists() {
+            return Err(format!("app_path '{path}' does not exist"));
+        }
+        if !is_executable(Path::new(path)) {
+            return Err(format!("app_path '{path}' is not executable"));
+        }
+        return Ok(());
+    }
+    let found = env::var_os("PATH")
+        .map(|dirs| env::split_paths(&dirs).any(|dir| is_executable(&dir.join(path))))
+        .unwrap_or(false);
+    if found {
+        Ok(())
+    } else {
+        Err(format!("app_path '{path}' was not found in PATH"))
+    }
+}
+
+// Validates syntax and values only; file loads also check app paths.
+#[cfg(test)]
+pub fn load_from_str(s: &str) -> Result<Config, Vec<ConfigError>> {
+    into_result(load(s, false))
+}
+
+pub fn load_from_file(path: impl AsRef<Path>) -> Result<Config, Vec<ConfigError>> {
+    into_result(load_from_file_lenient(path))
+}
+
+// Returns every problem found alongside a best-effort config in which invalid
+// values fall back to their defaults.
+pub fn load_from_file_lenient(path: impl AsRef<Path>) -> (Config, Vec<ConfigError>) {
+    let p = path.as_ref();
+    match fs::read_to_string(p) {
+        Ok(content) => load(&content, true),
+        Err(e) => (
+            Config::default(),
+            vec![ConfigError::new(
+                ConfigErrorKind::Io,
+                format!("cannot read {}: {e}", p.display()),
+                None,
+                "",
+            )],
+        ),
+    }
+}
+
+fn into_result((config, errors): (Config, Vec<ConfigError>)) -> Result<Config, Vec<ConfigError>> {
+    if errors.is_empty() {
+        Ok(config)
+    } else {
+        Err(errors)
+    }
+}
+
+fn load(s: &str, check_paths: bool) -> (Config, Vec<ConfigError>) {
     // Allow both [settings] and legacy [settigs]. If legacy header exists and no
     // proper [settings] header, prefer the legacy-rewritten version.
-    let parsed: Option<FileConfig> = if s.contains("[settigs]") && !s.contains("[settings]") {
-        let fixed = s.replace("[settigs]", "[settings]");
-        toml::from_str::<FileConfig>(&fixed).ok()
-    } else {
-        toml::from_str::<FileConfig>(s).ok()
+    if !s.contains("[settigs]") || s.contains("[settings]") {
+        return parse(s, check_paths);
+    }
+    let fixed = s.replace("[settigs]", "[settings]");
+    let (config, mut errors) = parse(&fixed, check_paths);
+    // Each rewritten header is a byte longer; point errors at the original
+    let header_ends: Vec<usize> = fixed
+        .match_indices("[settings]")
+        .map(|(start, header)| start + header.len())
+        .collect();
+    let original =
+        |offset: usize| offset - header_ends.iter().filter(|&&end| end <= offset).count();
+    for error in &mut errors {
+        if let Some(span) = &mut error.span {
+            *span = original(span.start)..original(span.end);
+            error.location = Some(line_column(s, span.start));
+        }
+    }
+    (config, errors)
+}
+
+fn parse(src: &str, check_paths: bool) -> (Config, Vec<ConfigError>) {
+    let doc = match ImDocument::parse(src) {
+        Ok(doc) => doc,
+        Err(e) => {
+            let error = ConfigError::new(
+                ConfigErrorKind::Syntax,
+                e.message().trim().to_string(),
+                e.span(),
+                src,
+            );
+            return (Config::default(), vec![error]);
+        }
+    };
+    let mut diag = Diagnostics {
+        src,
+        doc,
+        errors: Vec::new(),
     };
 
-    let settings = parsed.map(|f| f.settings).unwrap_or_default();
-    let (interval, app_path, app_name) = (settings.interval, settings.app_path, settings.app_name);
+    let file = match toml::from_str::<FileConfig>(src) {
+        Ok(file) => file,
+        Err(e) => {
+            // Wrong value types: nothing reliable to build from
+            diag.report(
+                ConfigErrorKind::InvalidValue,
+                e.message().trim().to_string(),
+                e.span(),
+            );
+            FileConfig::default()
+        }
+    };
+    diag.check_unknown_keys(file.binding.len());
+
+    let config = build(file, &mut diag, check_paths);
+    (config, diag.errors)
+}
+
+fn build(file: FileConfig, diag: &mut Diagnostics, check_paths: bool) -> Config {
+    let FileConfig {
+        settings,
+        binding: bindings,
+    } = file;
+    let section = Section::Settings;
+    let backend = diag.backend(settings.backend.as_deref());
+    let hide_method = diag.hide_method(settings.wayland_hide_method.as_deref());
+    let stash_workspace = diag.stash_workspace(settings.niri_stash_workspace);
 
     // Determine key: prefer detected_key, else first of detected_keys
-    let key_str = settings
-        .detected_key
-        .or_else(|| settings.detected_keys.and_then(|v| v.into_iter().next()))
-        .unwrap_or_else(|| "ctrl_left".to_string());
+    let (key_field, key_str) = match (&settings.detected_key, &settings.detected_keys) {
+        (Some(k), _) => ("detected_key", Some(k.as_str())),
+        (None, Some(keys)) => ("detected_keys", keys.first().map(String::as_str)),
+        (None, None) => ("detected_key", None),
+    };
+    let key = key_str.and_then(|k| diag.key(section, key_field, k));
+
+    let base_tap = TapSpec {
+        key: key.unwrap_or(Key::ControlLeft),
+        interval: diag
+            .millis(section, "interval", Some(settings.interval), INTERVAL_RANGE)
+            .unwrap_or(Duration::from_millis(default_interval())),
+        hold: diag
+            .millis(section, "hold", settings.hold, HOLD_RANGE)
+            .unwrap_or(DEFAULT_HOLD),
+        patterns: diag.actions(section, settings.actions.as_ref()),
+        strict: settings.strict,
+        max_hold: diag.millis(section, "max_hold", settings.max_hold, MAX_HOLD_RANGE),
+    };
+    let base = AppConfig {
+        name: "default".to_string(),
+        app_path: settings.app_path,
+        app_name: settings.app_name,
+        trigger: diag.trigger(section, settings.trigger.as_deref(), &base_tap),
+    };
+
+    // Only check the [settings] app_path if some binding actually uses it
+    if check_paths && (bindings.is_empty() || bindings.iter().any(|b| b.app_path.is_none())) {
+        diag.app_path(section, &base.app_path);
+    }
+
+    if bindings.is_empty() {
+        return Config {
+            bindings: vec![base],
+            backend,
+            hide_method,
+            stash_workspace,
+        };
+    }
 
-    let detect_key = parse_key(&key_str).unwrap_or(Key::ControlLeft);
+    // Names pick a binding in the control socket and on reload, so they must
+    // be unique; a repeated one falls back to the default name
+    let mut names = HashSet::new();
+    let bindings = bindings
+        .into_iter()
+        .enumerate()
+        .map(|(i, b)| {
+            let section = Section::Binding(i);
+            let default_name = || format!("binding{}", i + 1);
+            let mut name = b.name.unwrap_or_else(default_name);
+            if !names.insert(name.clone()) {
+                diag.report_value(
+                    ConfigErrorKind::InvalidValue,
+                    section,
+                    "name",
+                    format!("binding name '{name}' is already used"),
+                );
+                name = default_name();
+                names.insert(name.clone());
+            }
+            // Tap settings inherit field by field from [settings]
+            let tap = TapSpec {
+                key: b
+                    .detected_key
+                    .as_deref()
+                    .and_then(|k| diag.key(section, "detected_key", k))
+                    .unwrap_or(base_tap.key),
+                interval: diag
+                    .millis(section, "interval", b.interval, INTERVAL_RANGE)
+                    .unwrap_or(base_tap.interval),
+                hold: diag
+                    .millis(section, "hold", b.hold, HOLD_RANGE)
+                    .unwrap_or(base_tap.hold),
+                patterns: match b.actions.as_ref() {
+                    Some(actions) => diag.actions(section, Some(actions)),
+                    None => base_tap.patterns.clone(),
+                },
+                strict: b.strict.unwrap_or(base_tap.strict),
+                max_hold: diag
+                    .millis(section, "max_hold", b.max_hold, MAX_HOLD_RANGE)
+                    .or(base_tap.max_hold),
+            };
+            // A binding naming its own key does not inherit a chord from [settings]
+            let trigger = match (&b.trigger, &b.detected_key) {
+                (Some(t), _) => Some(t.as_str()),
+                (None, None) => settings.trigger.as_deref(),
+                (None, Some(_)) => None,
+            };
+            if check_paths {
+                if let Some(path) = &b.app_path {
+                    diag.app_path(section, path);
+                }
+            }
+            AppConfig {
+                name,
+                app_path: b.app_path.unwrap_or_else(|| base.app_path.clone()),
+                app_name: b.app_name.unwrap_or_else(|| base.app_name.clone()),
+                // An inherited trigger was already reported with [settings]
+                trigger: if b.trigger.is_some() {
+                    diag.trigger(section, trigger, &tap)
+                } else {
+                    resolve_trigger(trigger, &tap).unwrap_or(TriggerSpec::Taps(tap))
+                },
+            }
+        })
+        .collect();
 
-    AppConfig {
-        double_press_interval: Duration::from_millis(interval),
-        app_path,
-        app_name,
-        detect_key,
+    Config {
+        bindings,
+        backend,
+        hide_method,
+        stash_workspace,
     }
 }
 
-pub fn load_from_file(path: impl AsRef<Path>) -> Option<AppConfig> {
-    let p = path.as_ref();
-    let content = fs::read_to_string(p).ok()?;
-    Some(load_from_str(&content))
+fn resolve_trigger(trigger: Option<&str>, tap: &TapSpec) -> Result<TriggerSpec, String> {
+    match trigger {
+        Some(t) => parse_trigger(t, tap),
+        None => Ok(TriggerSpec::Taps(tap.clone())),
+    }
+}
+
+// The `trigger` value parse_trigger reads back. A tap trigger's patterns are
+// written under [binding.actions].
+fn trigger_value(trigger: &TriggerSpec) -> String {
+    match trigger {
+        TriggerSpec::Taps(spec) => format!("double_tap:{}", key_name(spec.key)),
+        TriggerSpec::Chord(_) => trigger.to_string(),
+    }
 }
 
-// Minimal parser for common key names. Case-insensitive.
-fn parse_key(s: &str) -> Option<Key> {
-    let k = s.to_ascii_lowercase();
-    match k.as_str() {
-        "ctrl" | "control" | "ctrl_left" | "control_left" | "left_ctrl" | "left_control"
-        | "controlleft" => Some(Key::ControlLeft),
-        "ctrl_right" | "control_right" | "right_ctrl" | "right_control" | "controlright" => {
-            Some(Key::ControlRight)
+// Parses "double_tap", "double_tap:<key>" or "chord:<key>+<key>+...".
+fn parse_trigger(s: &str, tap: &TapSpec) -> Result<TriggerSpec, String> {
+    let t = s.trim().to_ascii_lowercase();
+    let (kind, arg) = match t.split_once(':') {
+        Some((kind, arg)) => (kind, Some(arg)),
+        None => (t.as_str(), None),
+    };
+    match (kind, arg) {
+        ("double_tap" | "tap", None) => Ok(TriggerSpec::Taps(tap.clone())),
+        ("double_tap" | "tap", Some(k)) => Ok(TriggerSpec::Taps(TapSpec {
+            key: parse_key(k).map_err(|e| e.to_string())?,
+            ..tap.clone()
+        })),
+        ("chord", Some(combo)) => {
+            let keys = combo
+                .split('+')
+                .map(|k| parse_chord_key(k.trim()))
+                .collect::<Result<Vec<_>, String>>()?;
+            Ok(TriggerSpec::Chord(keys))
         }
-        // Extend as needed
+        _ => Err(format!("invalid trigger '{s}'")),
+    }
+}
+
+// "double_tap", "triple_tap", "long_press", "tap_hold", or "tap:N" / "tap:N+hold".
+fn parse_pattern(s: &str) -> Option<TapPattern> {
+    let p = s.trim().to_ascii_lowercase();
+    let (taps, hold) = match p.as_str() {
+        "double_tap" => (2, false),
+        "triple_tap" => (3, false),
+        "long_press" | "hold" => (0, true),
+        "tap_hold" => (1, true),
+        _ => {
+            let n = p.strip_prefix("tap:")?;
+            match n.strip_suffix("+hold") {
+                Some(n) => (n.parse().ok()?, true),
+                None => (n.parse().ok().filter(|n| *n > 0)?, false),
+            }
+        }
+    };
+    Some(TapPattern { taps, hold })
+}
+
+fn parse_action(s: &str) -> Option<Action> {
+    match s.trim().to_ascii_lowercase().as_str() {
+        "toggle" => Some(Action::Toggle),
+        "launch" => Some(Action::Launch),
+        "hide_all" => Some(Action::HideAll),
         _ => None,
     }
 }
 
+// Within a chord, bare modifier names match either side.
+fn parse_chord_key(s: &str) -> Result<ChordKey, String> {
+    match s {
+        "ctrl" | "control" => Ok(ChordKey::Ctrl),
+        "alt" => Ok(ChordKey::Alt),
+        "shift" => Ok(ChordKey::Shift),
+        "super" | "meta" | "win" | "logo" => Ok(ChordKey::Super),
+        _ => parse_key(s).map(ChordKey::Key).map_err(|e| e.to_string()),
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -110,11 +868,13 @@ mod tests {
             app_name = "Echo"
             detected_key = "ctrl_left"
         "#;
-        let cfg = load_from_str(s);
-        assert_eq!(cfg.double_press_interval, Duration::from_millis(450));
+        let cfg = &load_from_str(s).unwrap().bindings[0];
         assert_eq!(cfg.app_path, "/bin/echo");
         assert_eq!(cfg.app_name, "Echo");
-        assert!(matches!(cfg.detect_key, Key::ControlLeft));
+        assert_eq!(
+            cfg.trigger,
+            TriggerSpec::double_tap(Key::ControlLeft, Duration::from_millis(450))
+        );
     }
 
     #[test]
@@ -126,24 +886,471 @@ mod tests {
             app_name = "alacritty"
             detected_keys = ["CTRL_LEFT", "CTRL_RIGHT"]
         "#;
-        let cfg = load_from_str(s);
-        assert_eq!(cfg.double_press_interval, Duration::from_millis(300));
+        let cfg = &load_from_str(s).unwrap().bindings[0];
         assert_eq!(cfg.app_name, "alacritty");
-        assert!(matches!(cfg.detect_key, Key::ControlLeft));
+        assert_eq!(
+            cfg.trigger,
+            TriggerSpec::double_tap(Key::ControlLeft, Duration::from_millis(300))
+        );
     }
 
     #[test]
-    fn parse_defaults_on_missing_or_invalid_key() {
+    fn legacy_table_errors_point_at_the_original() {
+        let s = "[settigs]\ninterval = 300\n\n[[binding]]\nbogus = 1\n";
+        let errors = load_from_str(s).unwrap_err();
+        let span = errors[0].span.clone().unwrap();
+        assert_eq!(&s[span], "bogus");
+        assert_eq!(errors[0].location, Some((5, 1)));
+    }
+
+    #[test]
+    fn parse_defaults_on_missing_key() {
         let s = r#"
             [settings]
             app_path = "/bin/echo"
             app_name = "Echo"
+        "#;
+        let cfg = &load_from_str(s).unwrap().bindings[0];
+        // interval default 300, missing key -> default ControlLeft
+        assert_eq!(
+            cfg.trigger,
+            TriggerSpec::double_tap(Key::ControlLeft, Duration::from_millis(300))
+        );
+    }
+
+    #[test]
+    fn parse_multiple_bindings_inherit_settings() {
+        let s = r#"
+            [settings]
+            interval = 400
+            app_path = "/usr/local/bin/alacritty"
+            app_name = "class=Alacritty"
+
+            [[binding]]
+            name = "terminal"
+            detected_key = "ctrl_left"
+
+            [[binding]]
+            detected_key = "ctrl_right"
+            app_path = "/usr/bin/gedit"
+            app_name = "class=Gedit"
+            interval = 250
+        "#;
+        let cfg = load_from_str(s).unwrap();
+        assert_eq!(cfg.bindings.len(), 2);
+
+        let term = &cfg.bindings[0];
+        assert_eq!(term.name, "terminal");
+        assert_eq!(term.app_path, "/usr/local/bin/alacritty");
+        assert_eq!(term.app_name, "class=Alacritty");
+        assert_eq!(
+            term.trigger,
+            TriggerSpec::double_tap(Key::ControlLeft, Duration::from_millis(400))
+        );
+
+        let notes = &cfg.bindings[1];
+        assert_eq!(notes.name, "binding2");
+        assert_eq!(notes.app_path, "/usr/bin/gedit");
+        assert_eq!(notes.app_name, "class=Gedit");
+        assert_eq!(
+            notes.trigger,
+            TriggerSpec::double_tap(Key::ControlRight, Duration::from_millis(250))
+        );
+    }
+
+    #[test]
+    fn rejects_duplicate_binding_names() {
+        let s = r#"
+[[binding]]
+name = "terminal"
+
+[[binding]]
+name = "terminal"
+detected_key = "ctrl_right"
+"#;
+        let errors = load_from_str(s).unwrap_err();
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].kind, ConfigErrorKind::InvalidValue);
+        assert_eq!(errors[0].message, "binding name 'terminal' is already used");
+        assert_eq!(errors[0].location, Some((6, 8)));
+
+        // Leniently loaded, the second binding gets its default name
+        let (cfg, _) = load(s, false);
+        let names: Vec<&str> = cfg.bindings.iter().map(|b| b.name.as_str()).collect();
+        assert_eq!(names, ["terminal", "binding2"]);
+    }
+
+    #[test]
+    fn parse_chord_trigger() {
+        let s = r#"
+            [settings]
+            trigger = "chord:super+grave"
+
+            [[binding]]
+            name = "shell"
+            trigger = "chord:Ctrl+Alt+T"
+
+            [[binding]]
+            name = "inherit"
+        "#;
+        let cfg = load_from_str(s).unwrap();
+        assert_eq!(
+            cfg.bindings[0].trigger,
+            TriggerSpec::Chord(vec![
+                ChordKey::Ctrl,
+                ChordKey::Alt,
+                ChordKey::Key(Key::KeyT)
+            ])
+        );
+        assert_eq!(
+            cfg.bindings[1].trigger,
+            TriggerSpec::Chord(vec![ChordKey::Super, ChordKey::Key(Key::BackQuote)])
+        );
+    }
+
+    #[test]
+    fn parse_trigger_strings() {
+        let tap = TapSpec::double_tap(Key::ControlLeft, Duration::from_millis(300));
+        assert_eq!(
+            parse_trigger("double_tap:ctrl_right", &tap),
+            Ok(TriggerSpec::double_tap(
+                Key::ControlRight,
+                Duration::from_millis(300)
+            ))
+        );
+        assert_eq!(
+            parse_trigger("chord:shift_right+f5", &tap),
+            Ok(TriggerSpec::Chord(vec![
+                ChordKey::Key(Key::ShiftRight),
+                ChordKey::Key(Key::F5)
+            ]))
+        );
+        assert!(parse_trigger("chord:super+", &tap).is_err());
+        assert_eq!(
+            parse_trigger("chord:hyper+x", &tap),
+            Err("unknown key name 'hyper'".to_string())
+        );
+        assert_eq!(
+            parse_trigger("triple", &tap),
+            Err("invalid trigger 'triple'".to_string())
+        );
+    }
+
+    #[test]
+    fn parse_tap_pattern_actions() {
+        let s = r#"
+            [settings]
+            detected_key = "ctrl_left"
+            interval = 250
+            hold = 600
+
+            [settings.actions]
+            double_tap = "toggle"
+            triple_tap = "launch"
+            long_press = "hide_all"
+            "tap:4+hold" = "toggle"
+            bogus = "launch"
+        "#;
+        // The invalid entry is reported and skipped
+        let (cfg, errors) = load(s, false);
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].message, "unknown tap pattern 'bogus'");
+        assert_eq!(errors[0].location, Some((12, 13)));
+        let TriggerSpec::Taps(tap) = &cfg.bindings[0].trigger else {
+            panic!("expected a tap trigger");
+        };
+        assert_eq!(tap.interval, Duration::from_millis(250));
+        assert_eq!(tap.hold, Duration::from_millis(600));
+        let p = |taps, hold| TapPattern { taps, hold };
+        assert_eq!(tap.patterns.len(), 4);
+        assert!(tap.patterns.contains(&(p(2, false), Action::Toggle)));
+        assert!(tap.patterns.contains(&(p(3, false), Action::Launch)));
+        assert!(tap.patterns.contains(&(p(0, true), Action::HideAll)));
+        assert!(tap.patterns.contains(&(p(4, true), Action::Toggle)));
+    }
+
+    #[test]
+    fn parse_pattern_names() {
+        let p = |taps, hold| Some(TapPattern { taps, hold });
+        assert_eq!(parse_pattern("double_tap"), p(2, false));
+        assert_eq!(parse_pattern("tap_hold"), p(1, true));
+        assert_eq!(parse_pattern("tap:5"), p(5, false));
+        assert_eq!(parse_pattern("tap:0+hold"), p(0, true));
+        assert_eq!(parse_pattern("tap:0"), None);
+        assert_eq!(parse_pattern("tap:x"), None);
+    }
+
+    #[test]
+    fn parse_strict_and_max_hold() {
+        let s = r#"
+            [settings]
+            strict = true
+            max_hold = 180
+
+            [[binding]]
+            name = "inherits"
+
+            [[binding]]
+            name = "lax"
+            strict = false
+            max_hold = 400
+        "#;
+        let cfg = load_from_str(s).unwrap();
+        let tap = |i: usize| match &cfg.bindings[i].trigger {
+            TriggerSpec::Taps(tap) => tap.clone(),
+            other => panic!("unexpected trigger {other:?}"),
+        };
+        assert!(tap(0).strict);
+        assert_eq!(tap(0).max_hold, Some(Duration::from_millis(180)));
+        assert!(!tap(1).strict);
+        assert_eq!(tap(1).max_hold, Some(Duration::from_millis(400)));
+    }
+
+    #[test]
+    fn parse_rejects_unknown_key() {
+        let s = r#"
+            [settings]
             detected_key = "unknown_key"
         "#;
-        let cfg = load_from_str(s);
-        // interval default 300
-        assert_eq!(cfg.double_press_interval, Duration::from_millis(300));
-        // invalid key -> default ControlLeft
-        assert!(matches!(cfg.detect_key, Key::ControlLeft));
+        let errors = load_from_str(s).err().unwrap();
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].kind, ConfigErrorKind::InvalidValue);
+        assert_eq!(
+            errors[0].to_string(),
+            "line 3, column 28: detected_key: unknown key name 'unknown_key'"
+        );
+
+        let s = r#"
+            [[binding]]
+            detected_key = "ctrl_left"
+
+            [[binding]]
+            trigger = "chord:super+nope"
+        "#;
+        let errors = load_from_str(s).err().unwrap();
+        assert_eq!(
+            errors[0].to_string(),
+            "line 6, column 23: trigger: unknown key name 'nope'"
+        );
+    }
+
+    #[test]
+    fn reports_every_unknown_key() {
+        let s = r#"
+            colour = "red"
+            [settings]
+            intervall = 300
+            app_path = "/bin/echo"
+
+            [[binding]]
+            name = "a"
+            detected_kye = "ctrl_left"
+        "#;
+        let errors = load_from_str(s).err().unwrap();
+        let found: Vec<_> = errors
+            .into_iter()
+            .map(|e| (e.kind, e.message, e.location))
+            .collect();
+        assert_eq!(
+            found,
+            vec![
+                (
+                    ConfigErrorKind::UnknownKey,
+                    "unknown key 'colour' in top level".to_string(),
+                    Some((2, 13))
+                ),
+                (
+                    ConfigErrorKind::UnknownKey,
+                    "unknown key 'intervall' in [settings]".to_string(),
+                    Some((4, 13))
+                ),
+                (
+                    ConfigErrorKind::UnknownKey,
+                    "unknown key 'detected_kye' in [[binding]]".to_string(),
+                    Some((9, 13))
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn rejects_out_of_range_durations() {
+        for (interval, line) in [("0", "interval = 0 ms"), ("600000", "interval = 600000 ms")] {
+            let s = format!("[settings]\ninterval = {interval}\n");
+            let errors = load_from_str(&s).err().unwrap();
+            assert_eq!(errors.len(), 1);
+            assert_eq!(errors[0].kind, ConfigErrorKind::OutOfRange);
+            assert_eq!(errors[0].location, Some((2, 12)));
+            assert!(errors[0].message.starts_with(line), "{}", errors[0]);
+        }
+
+        let s = r#"
+            [settings]
+            hold = 5
+
+            [[binding]]
+            max_hold = 20000
+        "#;
+        let (cfg, errors) = load(s, false);
+        let kinds: Vec<_> = errors.iter().map(|e| e.kind).collect();
+        assert_eq!(kinds, [ConfigErrorKind::OutOfRange; 2]);
+        // Lenient loading falls back to the defaults
+        let TriggerSpec::Taps(tap) = &cfg.bindings[0].trigger else {
+            panic!("expected a tap trigger");
+        };
+        assert_eq!(tap.hold, DEFAULT_HOLD);
+        assert_eq!(tap.max_hold, None);
+    }
+
+    #[test]
+    fn reports_syntax_and_type_errors() {
+        let errors = load_from_str("[settings]\ninterval = = 3\n").err().unwrap();
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].kind, ConfigErrorKind::Syntax);
+        assert_eq!(errors[0].location.map(|(line, _)| line), Some(2));
+
+        let errors = load_from_str("[settings]\ninterval = \"fast\"\n")
+            .err()
+            .unwrap();
+        assert_eq!(errors[0].kind, ConfigErrorKind::InvalidValue);
+        assert_eq!(errors[0].location, Some((2, 12)));
+
+        let errors = load_from_str("[settings]\nwayland_hide_method = \"minimize\"\n")
+            .err()
+            .unwrap();
+        assert_eq!(
+            errors[0].to_string(),
+            "line 2, column 23: unknown wayland_hide_method 'minimize' (expected auto, scratchpad or none)"
+        );
+
+        let errors = load_from_str("[settings]\nniri_stash_workspace = \" \"\n")
+            .err()
+            .unwrap();
+        assert_eq!(
+            errors[0].to_string(),
+            "line 2, column 24: niri_stash_workspace must not be empty"
+        );
+
+        let errors = load_from_str("[settings]\nbackend = \"mir\"\n")
+            .err()
+            .unwrap();
+        assert_eq!(
+            errors[0].to_string(),
+            format!(
+                "line 2, column 11: unknown backend 'mir' (expected auto, {})",
+                backends::names()
+            )
+        );
+    }
+
+    #[test]
+    fn checks_app_path_on_file_load() {
+        let dir = std::env::temp_dir().join(format!("ahl-config-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let not_exec = dir.join("not-exec");
+        fs::write(&not_exec, "").unwrap();
+        let config = dir.join("config.toml");
+        let s = format!(
+            "[settings]\napp_path = \"/bin/sh\"\n\n[[binding]]\napp_path = \"{}\"\n\n[[binding]]\napp_path = \"{}/missing\"\n\n[[binding]]\napp_path = \"sh\"\n",
+            not_exec.display(),
+            dir.display()
+        );
+        fs::write(&config, &s).unwrap();
+
+        // Only file loads look at the filesystem
+        assert!(load_from_str(&s).is_ok());
+        let errors = load_from_file(&config).err().unwrap();
+        let found: Vec<_> = errors.iter().map(|e| (e.kind, e.location)).collect();
+        assert_eq!(
+            found,
+            vec![
+                (ConfigErrorKind::AppPath, Some((5, 12))),
+                (ConfigErrorKind::AppPath, Some((8, 12))),
+            ]
+        );
+        assert!(errors[0].message.ends_with("is not executable"));
+        assert!(errors[1].message.ends_with("does not exist"));
+
+        let (cfg, errors) = load_from_file_lenient(dir.join("nope.toml"));
+        assert_eq!(errors[0].kind, ConfigErrorKind::Io);
+        assert_eq!(cfg.bindings.len(), 1);
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn parse_full_key_names() {
+        let s = r#"
+            [settings]
+            detected_key = "unknown:191"
+
+            [[binding]]
+            name = "inherits"
+
+            [[binding]]
+            detected_key = "KP_ENTER"
+        "#;
+        let cfg = load_from_str(s).unwrap();
+        let key = |i: usize| match &cfg.bindings[i].trigger {
+            TriggerSpec::Taps(tap) => tap.key,
+            other => panic!("unexpected trigger {other:?}"),
+        };
+        assert_eq!(key(0), Key::Unknown(191));
+        assert_eq!(key(1), Key::KpReturn);
+    }
+
+    #[test]
+    fn effective_config_round_trips() {
+        let s = r#"
+            [settings]
+            app_path = "/usr/bin/my \"term\""
+            interval = 350
+            max_hold = 200
+            backend = "Sway"
+            wayland_hide_method = "none"
+            niri_stash_workspace = "hidden"
+
+            [settings.actions]
+            double_tap = "toggle"
+            triple_tap = "launch"
+            "tap:4+hold" = "hide_all"
+
+            [[binding]]
+            name = "terminal"
+            detected_key = "ctrl_right"
+            strict = true
+
+            [[binding]]
+            name = "shell"
+            trigger = "chord:ctrl+alt+t"
+        "#;
+        let cfg = load_from_str(s).unwrap();
+        let printed = cfg.to_string();
+        assert_eq!(cfg.backend, Some(BackendKind::Sway));
+        assert!(
+            printed.contains("trigger = \"double_tap:ctrl_right\""),
+            "{printed}"
+        );
+        assert!(
+            printed.contains("\"tap:4+hold\" = \"hide_all\""),
+            "{printed}"
+        );
+        assert_eq!(load_from_str(&printed).unwrap(), cfg);
+        assert_eq!(
+            load_from_str(&Config::default().to_string()).unwrap(),
+            Config::default()
+        );
+    }
+
+    #[test]
+    fn trigger_value_round_trips() {
+        let tap = TapSpec::double_tap(Key::ControlLeft, Duration::from_millis(300));
+        for t in [
+            "double_tap:ctrl_right",
+            "chord:super+grave",
+            "chord:ctrl+alt+kp_0",
+        ] {
+            let spec = parse_trigger(t, &tap).unwrap();
+            assert_eq!(trigger_value(&spec), t);
+        }
     }
 }
@@ -0,0 +1,279 @@
+// Config file resolution.
+// Order: --config, $ALACRITTY_HOTKEY_LAUNCHER_CONFIG, $XDG_CONFIG_HOME (or ~/.config),
+// each of $XDG_CONFIG_DIRS (or /etc/xdg), then the repo default.
+use std::ffi::OsString;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+pub const ENV_VAR: &str = "ALACRITTY_HOTKEY_LAUNCHER_CONFIG";
+const APP_DIR: &str = "alacritty-hotkey-launcher";
+const FILE_NAME: &str = "config.toml";
+const REPO_DEFAULT: &str = "src/config.toml";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConfigSource {
+    CommandLine,
+    EnvVar,
+    UserConfig,
+    SystemConfig,
+    RepoDefault,
+}
+
+impl fmt::Display for ConfigSource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            ConfigSource::CommandLine => "--config",
+            ConfigSource::EnvVar => ENV_VAR,
+            ConfigSource::UserConfig => "user config",
+            ConfigSource::SystemConfig => "system config",
+            ConfigSource::RepoDefault => "repo default",
+        })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ResolvedConfig {
+    pub path: PathBuf,
+    pub source: ConfigSource,
+}
+
+impl fmt::Display for ResolvedConfig {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} (from {})", self.path.display(), self.source)
+    }
+}
+
+#[derive(Debug)]
+pub enum ResolveError {
+    // A path given explicitly (--config or the env var) does not exist
+    NotFound(PathBuf, ConfigSource),
+    // A file exists but cannot be read; searching stops here rather than
+    // silently picking a lower-priority file
+    Unreadable { path: PathBuf, error: io::Error },
+}
+
+impl fmt::Display for ResolveError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ResolveError::NotFound(path, source) => {
+                write!(f, "config file {} ({source}) not found", path.display())
+            }
+            ResolveError::Unreadable { path, error } => {
+                write!(f, "config file {} is not readable: {error}", path.display())
+            }
+        }
+    }
+}
+
+// Resolves against the process environment. Ok(None) means no config file
+// exists anywhere and built-in defaults apply.
+pub fn resolve() -> Result<Option<ResolvedConfig>, ResolveError> {
+    resolve_with(|name| std::env::var_os(name))
+}
+
+pub fn resolve_with(
+    env: impl Fn(&str) -> Option<OsString>,
+) -> Result<Option<ResolvedConfig>, ResolveError> {
+    if let Some(path) = env(ENV_VAR).filter(|p| !p.is_empty()) {
+        return explicit(PathBuf::from(path), ConfigSource::EnvVar).map(Some);
+    }
+
+    for (path, source) in candidates(&env) {
+        match check(&path) {
+            Ok(true) => return Ok(Some(ResolvedConfig { path, source })),
+            Ok(false) => continue,
+            Err(error) => return Err(ResolveError::Unreadable { path, error }),
+        }
+    }
+    Ok(None)
+}
+
+// A path named by the user must exist; there is no fallback.
+pub fn explicit(path: PathBuf, source: ConfigSource) -> Result<ResolvedConfig, ResolveError> {
+    match check(&path) {
+        Ok(true) => Ok(ResolvedConfig { path, source }),
+        Ok(false) => Err(ResolveError::NotFound(path, source)),
+        Err(error) => Err(ResolveError::Unreadable { path, error }),
+    }
+}
+
+// Search locations below the env var, in priority order.
+pub fn candidates(env: impl Fn(&str) -> Option<OsString>) -> Vec<(PathBuf, ConfigSource)> {
+    let mut out = Vec::new();
+
+    // Per the XDG spec, relative paths in these variables are ignored
+    let config_home = env("XDG_CONFIG_HOME")
+        .map(PathBuf::from)
+        .filter(|p| p.is_absolute())
+        .or_else(|| env("HOME").map(|home| PathBuf::from(home).join(".config")));
+    if let Some(dir) = config_home {
+        out.push((dir.join(APP_DIR).join(FILE_NAME), ConfigSource::UserConfig));
+    }
+
+    let config_dirs: Vec<PathBuf> = env("XDG_CONFIG_DIRS")
+        .map(|dirs| std::env::split_paths(&dirs).collect::<Vec<_>>())
+        .unwrap_or_default()
+        .into_iter()
+        .filter(|p| p.is_absolute())
+        .collect();
+    let config_dirs = if config_dirs.is_empty() {
+        vec![PathBuf::from("/etc/xdg")]
+    } else {
+        config_dirs
+    };
+    for dir in config_dirs {
+        out.push((
+            dir.join(APP_DIR).join(FILE_NAME),
+            ConfigSource::SystemConfig,
+        ));
+    }
+
+    out.push((PathBuf::from(REPO_DEFAULT), ConfigSource::RepoDefault));
+    out
+}
+
+// Ok(false) if missing, Err if present but unreadable.
+fn check(path: &Path) -> io::Result<bool> {
+    let meta = match fs::metadata(path) {
+        Ok(meta) => meta,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(false),
+        Err(e) => return Err(e),
+    };
+    if meta.is_dir() {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "is a directory",
+        ));
+    }
+    fs::File::open(path)?;
+    Ok(true)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    fn scratch_dir(name: &str) -> PathBuf {
+        let dir =
+            std::env::temp_dir().join(format!("ahl-config-path-{}-{}", std::process::id(), name));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn env_of(vars: &[(&str, &Path)]) -> impl Fn(&str) -> Option<OsString> {
+        let map: HashMap<String, OsString> = vars
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.as_os_str().to_owned()))
+            .collect();
+        move |name| map.get(name).cloned()
+    }
+
+    fn write_config(dir: &Path) -> PathBuf {
+        let path = dir.join(APP_DIR).join(FILE_NAME);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(&path, "[settings]\n").unwrap();
+        path
+    }
+
+    #[test]
+    fn candidate_order_follows_xdg() {
+        let env = env_of(&[
+            ("HOME", Path::new("/home/u")),
+            ("XDG_CONFIG_DIRS", Path::new("/opt/xdg:relative:/etc/xdg")),
+        ]);
+        let paths: Vec<(PathBuf, ConfigSource)> = candidates(env);
+        assert_eq!(
+            paths,
+            vec![
+                (
+                    PathBuf::from("/home/u/.config/alacritty-hotkey-launcher/config.toml"),
+                    ConfigSource::UserConfig
+                ),
+                (
+                    PathBuf::from("/opt/xdg/alacritty-hotkey-launcher/config.toml"),
+                    ConfigSource::SystemConfig
+                ),
+                (
+                    PathBuf::from("/etc/xdg/alacritty-hotkey-launcher/config.toml"),
+                    ConfigSource::SystemConfig
+                ),
+                (PathBuf::from(REPO_DEFAULT), ConfigSource::RepoDefault),
+            ]
+        );
+
+        // XDG_CONFIG_HOME wins over HOME; unset XDG_CONFIG_DIRS means /etc/xdg
+        let env = env_of(&[
+            ("HOME", Path::new("/home/u")),
+            ("XDG_CONFIG_HOME", Path::new("/cfg")),
+        ]);
+        let paths = candidates(env);
+        assert_eq!(
+            paths[0].0,
+            PathBuf::from("/cfg/alacritty-hotkey-launcher/config.toml")
+        );
+        assert_eq!(
+            paths[1].0,
+            PathBuf::from("/etc/xdg/alacritty-hotkey-launcher/config.toml")
+        );
+    }
+
+    #[test]
+    fn resolves_first_existing_file() {
+        let root = scratch_dir("first");
+        let home = root.join("home");
+        let sys = root.join("sys");
+        let sys_cfg = write_config(&sys);
+        let env = env_of(&[("XDG_CONFIG_HOME", &home), ("XDG_CONFIG_DIRS", &sys)]);
+        assert_eq!(
+            resolve_with(&env).unwrap(),
+            Some(ResolvedConfig {
+                path: sys_cfg,
+                source: ConfigSource::SystemConfig
+            })
+        );
+
+        let home_cfg = write_config(&home);
+        assert_eq!(resolve_with(&env).unwrap().unwrap().path, home_cfg);
+        let _ = fs::remove_dir_all(&root);
+    }
+
+    #[test]
+    fn env_var_is_explicit() {
+        let root = scratch_dir("env");
+        let home_cfg = write_config(&root);
+        let missing = root.join("missing.toml");
+        let env = env_of(&[(ENV_VAR, &missing), ("XDG_CONFIG_HOME", &root)]);
+        // A missing explicit path is an error, not a fallback
+        assert!(
+            matches!(resolve_with(&env), Err(ResolveError::NotFound(p, ConfigSource::EnvVar)) if p == missing)
+        );
+
+        let env = env_of(&[(ENV_VAR, &home_cfg)]);
+        assert_eq!(
+            resolve_with(&env).unwrap().unwrap().source,
+            ConfigSource::EnvVar
+        );
+        let _ = fs::remove_dir_all(&root);
+    }
+
+    #[test]
+    fn unreadable_file_stops_the_search() {
+        let root = scratch_dir("unreadable");
+        let home = root.join("home");
+        let sys = root.join("sys");
+        write_config(&sys);
+        // A directory where the file should be cannot be read
+        let bad = home.join(APP_DIR).join(FILE_NAME);
+        fs::create_dir_all(&bad).unwrap();
+        let env = env_of(&[("XDG_CONFIG_HOME", &home), ("XDG_CONFIG_DIRS", &sys)]);
+        match resolve_with(&env) {
+            Err(ResolveError::Unreadable { path, .. }) => assert_eq!(path, bad),
+            other => panic!("expected unreadable error, got {other:?}"),
+        }
+        let _ = fs::remove_dir_all(&root);
+    }
+}
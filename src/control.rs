@@ -0,0 +1,479 @@
+// Control socket, so scripts and WM keybindings can drive the daemon.
+// One request per line, either plain text ("toggle notes") or JSON
+// ({"command": "toggle", "binding": "notes"}). Each gets one response line in
+// the same form: "ok: ..." / "error: ..." or {"ok": true, "message": ...}.
+use crate::common_backend::{
+    find_app_window, hide_all, launch, show_or_launch, toggle_or_launch, AppConfig, BackendResult,
+    Binding, Outcome, WindowBackend,
+};
+use serde::{Deserialize, Serialize};
+use std::env;
+use std::fs;
+use std::io::{self, BufRead, BufReader, Write};
+use std::net::Shutdown;
+use std::os::unix::fs::PermissionsExt;
+use std::os::unix::net::{UnixListener, UnixStream};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::thread;
+
+const SOCKET_NAME: &str = "alacritty-hotkey-launcher.sock";
+
+// $XDG_RUNTIME_DIR/alacritty-hotkey-launcher.sock; None if the variable is unset.
+pub fn socket_path() -> Option<PathBuf> {
+    env::var_os("XDG_RUNTIME_DIR")
+        .filter(|dir| !dir.is_empty())
+        .map(|dir| PathBuf::from(dir).join(SOCKET_NAME))
+}
+
+// Commands taking a binding name act on the first binding when none is given.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Request {
+    Toggle(Option<String>),
+    Show(Option<String>),
+    Hide(Option<String>),
+    Launch(Option<String>),
+    Status,
+    Reload,
+    Quit,
+}
+
+impl Request {
+    fn new(command: &str, binding: Option<String>) -> Result<Self, String> {
+        match (command.to_ascii_lowercase().as_str(), binding) {
+            ("toggle", b) => Ok(Request::Toggle(b)),
+            ("show", b) => Ok(Request::Show(b)),
+            ("hide", b) => Ok(Request::Hide(b)),
+            ("launch", b) => Ok(Request::Launch(b)),
+            ("status", None) => Ok(Request::Status),
+            ("reload", None) => Ok(Request::Reload),
+            ("quit", None) => Ok(Request::Quit),
+            ("status" | "reload" | "quit", Some(_)) => {
+                Err(format!("'{command}' does not take a binding"))
+            }
+            _ => Err(format!("unknown command '{command}'")),
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct JsonRequest {
+    command: String,
+    #[serde(default)]
+    binding: Option<String>,
+}
+
+// Parses one request line. The flag tells whether it was JSON.
+fn parse_request(line: &str) -> (Result<Request, String>, bool) {
+    let line = line.trim();
+    if line.starts_with('{') {
+        let request = serde_json::from_str::<JsonRequest>(line)
+            .map_err(|e| format!("invalid JSON request: {e}"))
+            .and_then(|r| Request::new(&r.command, r.binding));
+        return (request, true);
+    }
+    let words: Vec<&str> = line.split_whitespace().collect();
+    let request = match words.as_slice() {
+        [] => Err("empty request".to_string()),
+        [command] => Request::new(command, None),
+        [command, binding] => Request::new(command, Some(binding.to_string())),
+        [_, _, extra, ..] => Err(format!("unexpected argument '{extra}'")),
+    };
+    (request, false)
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct AppStatus {
+    pub binding: String,
+    pub app_name: String,
+    // The window a toggle would act on, if any
+    pub window: Option<u64>,
+    pub on_current_workspace: bool,
+    pub visible: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Response {
+    pub ok: bool,
+    pub message: String,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub status: Vec<AppStatus>,
+}
+
+impl Response {
+    pub fn ok(message: impl Into<String>) -> Self {
+        Self {
+            ok: true,
+            message: message.into(),
+            status: Vec::new(),
+        }
+    }
+
+    pub fn error(message: impl Into<String>) -> Self {
+        Self {
+            ok: false,
+            message: message.into(),
+            status: Vec::new(),
+        }
+    }
+
+    fn to_line(&self, json: bool) -> String {
+        if json {
+            serde_json::to_string(self).unwrap_or_default()
+        } else if self.ok {
+            format!("ok: {}", self.message)
+        } else {
+            format!("error: {}", self.message)
+        }
+    }
+}
+
+// Runs the requests that only need the backend. Reload and Quit change the
+// daemon itself, so it handles those.
+pub fn execute(
+    request: &Request,
+    bindings: &mut [Binding],
+    backend: &mut dyn WindowBackend,
+) -> Response {
+    // "toggled 'term': hid window 0x…" or "cannot toggle 'term': …"
+    let reply =
+        |verb: &str, done: &str, app: &AppConfig, result: BackendResult<Outcome>| match result {
+            Ok(outcome) => Response::ok(format!("{done} '{}': {outcome}", app.name)),
+            Err(error) => Response::error(format!("cannot {verb} '{}': {error}", app.name)),
+        };
+    let result = match request {
+        Request::Toggle(name) => find(bindings, name).map(|b| {
+            let result = toggle_or_launch(backend, &b.config, &mut b.launched);
+            reply("toggle", "toggled", &b.config, result)
+        }),
+        Request::Show(name) => find(bindings, name).map(|b| {
+            let result = show_or_launch(backend, &b.config, &mut b.launched);
+            reply("show", "shown", &b.config, result)
+        }),
+        Request::Hide(name) => find(bindings, name)
+            .map(|b| reply("hide", "hidden", &b.config, hide_all(backend, &b.config))),
+        Request::Launch(name) => find(bindings, name).map(|b| {
+            let result = launch(backend, &b.config, &mut b.launched);
+            reply("launch", "launched", &b.config, result)
+        }),
+        Request::Status => Ok(
+            status(bindings, backend).unwrap_or_else(|error| Response::error(error.to_string()))
+        ),
+        Request::Reload | Request::Quit => Err(Response::error("handled by the daemon")),
+    };
+    result.unwrap_or_else(|error| error)
+}
+
+fn find<'a>(
+    bindings: &'a mut [Binding],
+    name: &Option<String>,
+) -> Result<&'a mut Binding, Response> {
+    match name {
+        Some(name) => bindings
+            .iter_mut()
+            .find(|b| b.config.name == *name)
+            .ok_or_else(|| Response::error(format!("no binding named '{name}'"))),
+        None => bindings
+            .first_mut()
+            .ok_or_else(|| Response::error("no bindings configured")),
+    }
+}
+
+fn status(bindings: &mut [Binding], backend: &mut dyn WindowBackend) -> BackendResult<Response> {
+    let mut status = Vec::new();
+    for binding in bindings {
+        let app = &binding.config;
+        let window = find_app_window(backend, app, &mut binding.launched)?;
+        let (on_current_workspace, visible) = match window {
+            Some(w) => (backend.is_on_current_workspace(w)?, backend.is_visible(w)?),
+            None => (false, false),
+        };
+        status.push(AppStatus {
+            binding: app.name.clone(),
+            app_name: app.app_name.clone(),
+            window,
+            on_current_workspace,
+            visible,
+        });
+    }
+    let summary: Vec<String> = status
+        .iter()
+        .map(|s| {
+            let state = match (s.window, s.on_current_workspace, s.visible) {
+                (None, _, _) => "not running",
+                (Some(_), true, true) => "visible",
+                (Some(_), true, false) => "hidden",
+                (Some(_), false, _) => "on another workspace",
+            };
+            format!("{}: {}", s.binding, state)
+        })
+        .collect();
+    Ok(Response {
+        status,
+        ..Response::ok(summary.join("; "))
+    })
+}
+
+// Listening socket; the file is removed when this is dropped.
+pub struct Server {
+    path: PathBuf,
+}
+
+impl Drop for Server {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&self.path);
+    }
+}
+
+// Binds the socket and answers each connection on its own thread. `handle` runs
+// on those threads, so it should hand the request over to the daemon's thread.
+pub fn serve(
+    path: &Path,
+    handle: impl Fn(Request) -> Response + Send + Sync + 'static,
+) -> io::Result<Server> {
+    if path.exists() {
+        // A socket that still answers belongs to a running instance; otherwise
+        // it was left behind and is replaced
+        if UnixStream::connect(path).is_ok() {
+            return Err(io::Error::new(
+                io::ErrorKind::AddrInUse,
+                "another instance is listening",
+            ));
+        }
+        fs::remove_file(path)?;
+    }
+    let listener = UnixListener::bind(path)?;
+    fs::set_permissions(path, fs::Permissions::from_mode(0o600))?;
+
+    let handle = Arc::new(handle);
+    thread::spawn(move || {
+        for stream in listener.incoming() {
+            match stream {
+                Ok(stream) => {
+                    let handle = Arc::clone(&handle);
+                    thread::spawn(move || {
+                        let _ = serve_client(stream, &*handle);
+                    });
+                }
+                Err(error) => eprintln!("Control socket: {}", error),
+            }
+        }
+    });
+    Ok(Server {
+        path: path.to_owned(),
+    })
+}
+
+fn serve_client(stream: UnixStream, handle: &dyn Fn(Request) -> Response) -> io::Result<()> {
+    let mut writer = stream.try_clone()?;
+    for line in BufReader::new(stream).lines() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        match parse_request(&line) {
+            // Acknowledged first: once handled, the daemon is gone and cannot answer
+            (Ok(Request::Quit), json) => {
+                writeln!(writer, "{}", Response::ok("quitting").to_line(json))?;
+                writer.flush()?;
+                handle(Request::Quit);
+                return Ok(());
+            }
+            (Ok(request), json) => writeln!(writer, "{}", handle(request).to_line(json))?,
+            (Err(error), json) => writeln!(writer, "{}", Response::error(error).to_line(json))?,
+        }
+    }
+    Ok(())
+}
+
+// Client side: sends one request line and returns the response line.
+pub fn send(path: &Path, request: &str) -> io::Result<String> {
+    let mut stream = UnixStream::connect(path)?;
+    writeln!(stream, "{}", request.trim())?;
+    stream.shutdown(Shutdown::Write)?;
+    let mut response = String::new();
+    BufReader::new(stream).read_line(&mut response)?;
+    Ok(response.trim_end().to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::common_backend::{BackendError, TriggerSpec, WindowInfo};
+    use rdev::Key;
+    use std::collections::HashMap;
+    use std::sync::mpsc;
+    use std::time::Duration;
+
+    // Windows by app_name as (id, on current workspace, visible); records calls.
+    struct FakeBackend {
+        windows: HashMap<String, (u64, bool, bool)>,
+        calls: Vec<String>,
+    }
+
+    impl WindowBackend for FakeBackend {
+        fn list_windows(&mut self, app_name: &str) -> BackendResult<Vec<WindowInfo>> {
+            Ok(self
+                .windows
+                .get(app_name)
+                .map(|&(id, on_current_ws, visible)| WindowInfo {
+                    id,
+                    class: None,
+                    title: None,
+                    workspace: None,
+                    on_current_ws,
+                    visible,
+                    focused: false,
+                    pid: None,
+                })
+                .into_iter()
+                .collect())
+        }
+        fn is_on_current_workspace(&mut self, window: u64) -> BackendResult<bool> {
+            Ok(self.windows.values().any(|w| w.0 == window && w.1))
+        }
+        fn is_visible(&mut self, window: u64) -> BackendResult<bool> {
+            Ok(self.windows.values().any(|w| w.0 == window && w.2))
+        }
+        fn move_to_current_workspace(&mut self, window: u64) -> BackendResult<()> {
+            self.calls.push(format!("move {window}"));
+            Ok(())
+        }
+        fn show(&mut self, window: u64) -> BackendResult<()> {
+            self.calls.push(format!("show {window}"));
+            Ok(())
+        }
+        fn hide(&mut self, window: u64) -> BackendResult<()> {
+            self.calls.push(format!("hide {window}"));
+            Ok(())
+        }
+        fn launch_app(&mut self, app_path: &str) -> BackendResult<u32> {
+            if app_path.ends_with("missing") {
+                return Err(BackendError::SpawnFailed(io::ErrorKind::NotFound.into()));
+            }
+            self.calls.push(format!("launch {app_path}"));
+            Ok(1)
+        }
+    }
+
+    fn app(name: &str, app_name: &str) -> AppConfig {
+        AppConfig {
+            name: name.to_string(),
+            app_path: format!("/usr/bin/{name}"),
+            app_name: app_name.to_string(),
+            trigger: TriggerSpec::double_tap(Key::ControlLeft, Duration::from_millis(300)),
+        }
+    }
+
+    #[test]
+    fn parses_text_and_json_requests() {
+        let parse = |line: &str| parse_request(line).0;
+        assert_eq!(parse("toggle"), Ok(Request::Toggle(None)));
+        assert_eq!(
+            parse("  SHOW notes \n"),
+            Ok(Request::Show(Some("notes".to_string())))
+        );
+        assert_eq!(parse("status"), Ok(Request::Status));
+        assert_eq!(
+            parse(r#"{"command": "hide", "binding": "term"}"#),
+            Ok(Request::Hide(Some("term".to_string())))
+        );
+        assert_eq!(parse(r#"{"command": "quit"}"#), Ok(Request::Quit));
+        assert!(parse_request(r#"{"command": "quit"}"#).1);
+
+        assert_eq!(parse(""), Err("empty request".to_string()));
+        assert_eq!(parse("fly"), Err("unknown command 'fly'".to_string()));
+        assert_eq!(
+            parse("reload now"),
+            Err("'reload' does not take a binding".to_string())
+        );
+        assert_eq!(
+            parse("toggle a b"),
+            Err("unexpected argument 'b'".to_string())
+        );
+        assert!(parse(r#"{"binding": "x"}"#).is_err());
+    }
+
+    #[test]
+    fn daemon_round_trip_over_socket() {
+        let dir = std::env::temp_dir().join(format!("ahl-control-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join(SOCKET_NAME);
+
+        // Stand-in for the daemon loop: requests are executed on this thread
+        let (tx, rx) = mpsc::channel::<(Request, mpsc::Sender<Response>)>();
+        let daemon = thread::spawn(move || {
+            let mut broken = app("broken", "class=Broken");
+            broken.app_path = "/usr/bin/missing".to_string();
+            let apps = [
+                app("term", "class=Term"),
+                app("notes", "class=Notes"),
+                broken,
+            ];
+            let mut bindings: Vec<Binding> = apps.into_iter().map(Binding::new).collect();
+            let mut backend = FakeBackend {
+                windows: HashMap::from([("class=Term".to_string(), (7, true, true))]),
+                calls: Vec::new(),
+            };
+            for (request, reply) in rx {
+                let response = match request {
+                    Request::Reload => Response::ok("reloaded"),
+                    Request::Quit => break,
+                    request => execute(&request, &mut bindings, &mut backend),
+                };
+                let _ = reply.send(response);
+            }
+            backend.calls
+        });
+        let server = serve(&path, move |request| {
+            let (reply_tx, reply_rx) = mpsc::channel();
+            let _ = tx.send((request, reply_tx));
+            reply_rx
+                .recv()
+                .unwrap_or_else(|_| Response::error("daemon stopped"))
+        })
+        .unwrap();
+
+        // A second instance must not steal the socket
+        assert_eq!(
+            serve(&path, |_| Response::ok("")).err().map(|e| e.kind()),
+            Some(io::ErrorKind::AddrInUse)
+        );
+
+        let send = |line: &str| send(&path, line).unwrap();
+        assert_eq!(send("toggle"), "ok: toggled 'term': hid window 0x00000007");
+        assert_eq!(send("show notes"), "ok: shown 'notes': launched the app");
+        assert_eq!(
+            send("launch broken"),
+            "error: cannot launch 'broken': cannot start the app: entity not found"
+        );
+        assert_eq!(send("toggle nope"), "error: no binding named 'nope'");
+        assert_eq!(send("reload"), "ok: reloaded");
+        assert_eq!(send("bogus"), "error: unknown command 'bogus'");
+        assert_eq!(
+            send("status"),
+            "ok: term: visible; notes: not running; broken: not running"
+        );
+
+        let json: serde_json::Value =
+            serde_json::from_str(&send(r#"{"command":"status"}"#)).unwrap();
+        assert_eq!(json["ok"], true);
+        assert_eq!(json["status"][0]["window"], 7);
+        assert_eq!(json["status"][1]["window"], serde_json::Value::Null);
+        assert_eq!(
+            send(r#"{"command":"launch","binding":"notes"}"#),
+            r#"{"ok":true,"message":"launched 'notes': launched the app"}"#
+        );
+
+        assert_eq!(send("quit"), "ok: quitting");
+        let calls = daemon.join().unwrap();
+        assert_eq!(
+            calls,
+            ["hide 7", "launch /usr/bin/notes", "launch /usr/bin/notes"]
+        );
+
+        drop(server);
+        assert!(!path.exists());
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
@@ -0,0 +1,74 @@
+// Shared by the backends that talk to the desktop over D-Bus (KWin, GNOME).
+use crate::common_backend::{BackendError, REPLY_TIMEOUT};
+use std::io;
+use zbus::blocking::connection::Builder;
+
+// A connection to the bus at `address`, or the session bus.
+pub fn builder(address: Option<&str>) -> zbus::Result<Builder<'static>> {
+    let builder = match address {
+        Some(address) => Builder::address(address)?,
+        None => Builder::session()?,
+    };
+    Ok(builder.method_timeout(REPLY_TIMEOUT))
+}
+
+// Whether some connection on the bus owns `name`, e.g. org.kde.KWin.
+pub fn has_owner(address: Option<&str>, name: &str) -> zbus::Result<bool> {
+    let conn = builder(address)?.build()?;
+    conn.call_method(
+        Some("org.freedesktop.DBus"),
+        "/org/freedesktop/DBus",
+        Some("org.freedesktop.DBus"),
+        "NameHasOwner",
+        &(name,),
+    )?
+    .body()
+    .deserialize()
+}
+
+pub fn to_backend_error(error: zbus::Error) -> BackendError {
+    match &error {
+        zbus::Error::MethodError(name, _, _)
+            if name.as_str() == "org.freedesktop.DBus.Error.ServiceUnknown" =>
+        {
+            BackendError::DisplayUnavailable
+        }
+        zbus::Error::InputOutput(e) if e.kind() == io::ErrorKind::TimedOut => {
+            BackendError::Protocol("no answer over D-Bus".to_string())
+        }
+        zbus::Error::InputOutput(_) => BackendError::DisplayUnavailable,
+        _ => BackendError::Protocol(error.to_string()),
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use std::io::{BufRead, BufReader};
+    use std::process::{Child, Command, Stdio};
+
+    // A private dbus-daemon, or None (test skipped) if it is not installed.
+    pub struct PrivateBus(Child, pub String);
+
+    impl PrivateBus {
+        pub fn start() -> Option<Self> {
+            let mut child = Command::new("dbus-daemon")
+                .args(["--session", "--nofork", "--print-address=1"])
+                .stdout(Stdio::piped())
+                .stderr(Stdio::null())
+                .spawn()
+                .ok()?;
+            let mut line = String::new();
+            BufReader::new(child.stdout.take()?)
+                .read_line(&mut line)
+                .ok()?;
+            Some(Self(child, line.trim().to_string()))
+        }
+    }
+
+    impl Drop for PrivateBus {
+        fn drop(&mut self) {
+            let _ = self.0.kill();
+            let _ = self.0.wait();
+        }
+    }
+}
@@ -0,0 +1,326 @@
+use crate::common_backend::{
+    current_desktop_is, spawn_app, BackendError, BackendResult, HideMethod, Probe, WindowBackend,
+    WindowInfo,
+};
+use crate::dbus;
+use crate::matching::matches_app;
+use std::env;
+use zbus::blocking::Connection;
+
+const SHELL: &str = "org.gnome.Shell";
+const PATH: &str = "/io/github/MasaRyu/HotkeyLauncher";
+const INTERFACE: &str = "io.github.MasaRyu.HotkeyLauncher.Windows";
+
+// Wire form of one ListWindows entry: id, wm_class, title, minimized,
+// on_active_workspace, focused, pid (0 or -1 when unknown).
+type ShellWindow = (u64, String, String, bool, bool, bool, i32);
+
+// GNOME Shell (Wayland) through the companion extension in gnome-extension/,
+// which exports ListWindows/Activate/Minimize/MoveToWorkspace on the session
+// bus. Hiding minimises.
+pub struct GnomeBackend {
+    // Bus address; None for the session bus
+    address: Option<String>,
+    hide_method: HideMethod,
+    conn: Option<Connection>,
+}
+
+impl GnomeBackend {
+    pub fn new(hide_method: HideMethod) -> Self {
+        Self {
+            address: None,
+            hide_method,
+            conn: None,
+        }
+    }
+
+    // A GNOME Wayland session; under X11 the X11 backend does the job.
+    pub fn probe() -> Probe {
+        if !current_desktop_is("GNOME") {
+            Probe::none("XDG_CURRENT_DESKTOP does not list GNOME")
+        } else if env::var_os("WAYLAND_DISPLAY").is_none() {
+            Probe::none("GNOME on X11; the x11 backend handles it")
+        } else {
+            Self::probe_extension(None)
+        }
+    }
+
+    // Without the extension every lookup fails, so even launching stops
+    // working; the launch-only wayland backend then scores higher.
+    fn probe_extension(address: Option<&str>) -> Probe {
+        let mut backend = Self::new(HideMethod::Auto);
+        backend.address = address.map(str::to_string);
+        match backend.windows() {
+            Ok(_) => Probe::new(90, "GNOME Wayland session with the extension enabled"),
+            Err(BackendError::DisplayUnavailable) => {
+                Probe::new(3, "GNOME Shell is not on the session bus")
+            }
+            Err(_) => Probe::new(
+                3,
+                "the hotkey-launcher GNOME Shell extension is not enabled",
+            ),
+        }
+    }
+
+    fn call<B, R>(&mut self, method: &str, body: &B) -> BackendResult<R>
+    where
+        B: serde::Serialize + zbus::zvariant::DynamicType,
+        R: for<'de> serde::Deserialize<'de> + zbus::zvariant::Type,
+    {
+        if self.conn.is_none() {
+            let conn = dbus::builder(self.address.as_deref()).and_then(|builder| builder.build());
+            self.conn = Some(conn.map_err(|_| BackendError::DisplayUnavailable)?);
+        }
+        let conn = self.conn.as_ref().unwrap();
+        let reply = conn
+            .call_method(Some(SHELL), PATH, Some(INTERFACE), method, body)
+            .map_err(to_backend_error);
+        if matches!(reply, Err(BackendError::DisplayUnavailable)) {
+            // The shell restarted or the bus went away; reconnect next time
+            self.conn = None;
+        }
+        reply?
+            .body()
+            .deserialize()
+            .map_err(|e| BackendError::Protocol(e.to_string()))
+    }
+
+    fn windows(&mut self) -> BackendResult<Vec<ShellWindow>> {
+        self.call("ListWindows", &())
+    }
+
+    fn window(&mut self, id: u64) -> BackendResult<ShellWindow> {
+        self.windows()?
+            .into_iter()
+            .find(|w| w.0 == id)
+            .ok_or(BackendError::WindowGone(id))
+    }
+
+    // Runs an action that answers whether the window still exists.
+    fn act<B>(&mut self, method: &str, id: u64, body: &B) -> BackendResult<()>
+    where
+        B: serde::Serialize + zbus::zvariant::DynamicType,
+    {
+        if self.call::<_, bool>(method, body)? {
+            Ok(())
+        } else {
+            Err(BackendError::WindowGone(id))
+        }
+    }
+}
+
+fn to_backend_error(error: zbus::Error) -> BackendError {
+    match &error {
+        // The shell is there but our object is not
+        zbus::Error::MethodError(name, _, _)
+            if name
+                .as_str()
+                .starts_with("org.freedesktop.DBus.Error.Unknown") =>
+        {
+            BackendError::Protocol(
+                "the hotkey-launcher GNOME Shell extension is not enabled".to_string(),
+            )
+        }
+        _ => dbus::to_backend_error(error),
+    }
+}
+
+impl WindowBackend for GnomeBackend {
+    fn list_windows(&mut self, app_name: &str) -> BackendResult<Vec<WindowInfo>> {
+        Ok(self
+            .windows()?
+            .into_iter()
+            .filter(|(_, class, title, ..)| matches_app(app_name, Some(title), Some(class)))
+            .map(
+                |(id, class, title, minimized, on_active_workspace, focused, pid)| WindowInfo {
+                    id,
+                    class: Some(class),
+                    title: Some(title),
+                    workspace: None,
+                    on_current_ws: on_active_workspace,
+                    visible: !minimized,
+                    focused,
+                    pid: u32::try_from(pid).ok().filter(|&pid| pid > 0),
+                },
+            )
+            .collect())
+    }
+
+    fn is_on_current_workspace(&mut self, window: u64) -> BackendResult<bool> {
+        Ok(self.window(window)?.4)
+    }
+
+    fn is_visible(&mut self, window: u64) -> BackendResult<bool> {
+        Ok(!self.window(window)?.3)
+    }
+
+    fn move_to_current_workspace(&mut self, window: u64) -> BackendResult<()> {
+        self.act("MoveToWorkspace", window, &(window, -1i32))
+    }
+
+    fn show(&mut self, window: u64) -> BackendResult<()> {
+        self.act("Activate", window, &(window,))
+    }
+
+    fn hide(&mut self, window: u64) -> BackendResult<()> {
+        match self.hide_method {
+            HideMethod::None => Err(BackendError::Unsupported(
+                "hiding with wayland_hide_method = none",
+            )),
+            HideMethod::Auto | HideMethod::Scratchpad => self.act("Minimize", window, &(window,)),
+        }
+    }
+
+    fn launch_app(&mut self, app_path: &str) -> BackendResult<u32> {
+        spawn_app(app_path)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::common_backend::test_support::{app, assert_display_unavailable};
+    use crate::common_backend::{toggle_or_launch, Launched, Outcome};
+    use crate::dbus::tests::PrivateBus;
+    use std::sync::{Arc, Mutex};
+    use zbus::blocking::connection;
+
+    // The extension's interface, over a list of windows.
+    struct MockExtension {
+        windows: Arc<Mutex<Vec<ShellWindow>>>,
+    }
+
+    impl MockExtension {
+        fn update(&self, id: u64, f: impl FnOnce(&mut ShellWindow)) -> bool {
+            let mut windows = self.windows.lock().unwrap();
+            windows.iter_mut().find(|w| w.0 == id).map(f).is_some()
+        }
+    }
+
+    #[zbus::interface(name = "io.github.MasaRyu.HotkeyLauncher.Windows")]
+    impl MockExtension {
+        fn list_windows(&self) -> Vec<ShellWindow> {
+            self.windows.lock().unwrap().clone()
+        }
+
+        fn activate(&self, id: u64) -> bool {
+            self.update(id, |w| {
+                w.3 = false;
+                w.5 = true;
+            })
+        }
+
+        fn minimize(&self, id: u64) -> bool {
+            self.update(id, |w| w.3 = true)
+        }
+
+        fn move_to_workspace(&self, id: u64, workspace: i32) -> bool {
+            assert_eq!(workspace, -1);
+            self.update(id, |w| w.4 = true)
+        }
+    }
+
+    #[test]
+    fn toggles_through_the_extension() {
+        let Some(bus) = PrivateBus::start() else {
+            eprintln!("dbus-daemon not found; skipping");
+            return;
+        };
+        let windows = Arc::new(Mutex::new(vec![
+            (7, "Alacritty".into(), "~".into(), false, true, true, 4242),
+            (
+                9,
+                "org.gnome.TextEditor".into(),
+                "notes".into(),
+                true,
+                false,
+                false,
+                0,
+            ),
+        ]));
+        let _shell = connection::Builder::address(bus.1.as_str())
+            .unwrap()
+            .name(SHELL)
+            .unwrap()
+            .serve_at(
+                PATH,
+                MockExtension {
+                    windows: windows.clone(),
+                },
+            )
+            .unwrap()
+            .build()
+            .unwrap();
+
+        let mut backend = GnomeBackend::new(HideMethod::Auto);
+        backend.address = Some(bus.1.clone());
+        assert_eq!(GnomeBackend::probe_extension(Some(&bus.1)).score, 90);
+
+        let listed = backend.list_windows("class=Alacritty").unwrap();
+        assert_eq!(listed.len(), 1);
+        assert!(listed[0].on_current_ws && listed[0].visible && listed[0].focused);
+        assert_eq!(listed[0].pid, Some(4242));
+        assert_eq!(
+            toggle_or_launch(&mut backend, &app("Alacritty"), &mut Launched::default()).unwrap(),
+            Outcome::Hidden(7)
+        );
+        assert_eq!(
+            toggle_or_launch(&mut backend, &app("Alacritty"), &mut Launched::default()).unwrap(),
+            Outcome::Shown(7)
+        );
+        assert_eq!(
+            toggle_or_launch(&mut backend, &app("title=notes"), &mut Launched::default()).unwrap(),
+            Outcome::Moved(9)
+        );
+        assert_eq!(
+            windows.lock().unwrap()[1],
+            (
+                9,
+                "org.gnome.TextEditor".into(),
+                "notes".into(),
+                false,
+                true,
+                true,
+                0
+            )
+        );
+
+        windows.lock().unwrap().remove(0);
+        assert!(matches!(backend.show(7), Err(BackendError::WindowGone(7))));
+    }
+
+    #[test]
+    fn missing_extension_is_reported() {
+        let Some(bus) = PrivateBus::start() else {
+            eprintln!("dbus-daemon not found; skipping");
+            return;
+        };
+        let mut backend = GnomeBackend::new(HideMethod::Auto);
+        backend.address = Some(bus.1.clone());
+        assert_display_unavailable(&mut backend);
+        assert_eq!(GnomeBackend::probe_extension(Some(&bus.1)).score, 3);
+
+        // The shell with its own objects, but not ours
+        let _shell = connection::Builder::address(bus.1.as_str())
+            .unwrap()
+            .name(SHELL)
+            .unwrap()
+            .serve_at(
+                "/org/gnome/Shell",
+                MockExtension {
+                    windows: Default::default(),
+                },
+            )
+            .unwrap()
+            .build()
+            .unwrap();
+        let error = backend.find_window("Alacritty").unwrap_err();
+        assert!(
+            error.to_string().contains("extension is not enabled"),
+            "{error}"
+        );
+        let probe = GnomeBackend::probe_extension(Some(&bus.1));
+        assert_eq!(probe.score, 3);
+        assert!(probe.reason.contains("extension is not enabled"));
+    }
+}
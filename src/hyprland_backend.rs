@@ -0,0 +1,468 @@
+use crate::common_backend::{
+    spawn_app, BackendError, BackendResult, HideMethod, Probe, WindowBackend, WindowInfo,
+};
+use crate::ipc;
+use crate::matching::matches_app;
+use serde::Deserialize;
+use std::env;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::os::unix::net::UnixStream;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread;
+
+// Where hidden windows go; Hyprland has no minimizing.
+const SPECIAL_WORKSPACE: &str = "special:hotkey-launcher";
+
+// Hyprland over its sockets in $XDG_RUNTIME_DIR/hypr/<instance>/. Requests go
+// to .socket.sock, one connection each. The window list is cached and
+// refetched only after .socket2.sock reports an event, so a hotkey usually
+// needs no round trip before the dispatch.
+pub struct HyprlandBackend {
+    dir: PathBuf,
+    hide_method: HideMethod,
+    cache: Option<State>,
+    events: Option<Events>,
+}
+
+// Set by the event thread; the cache is only trusted while it listens.
+struct Events {
+    changed: Arc<AtomicBool>,
+    listening: Arc<AtomicBool>,
+}
+
+#[derive(Debug, Clone)]
+struct State {
+    clients: Vec<Client>,
+    monitors: Vec<Monitor>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+struct Client {
+    address: String,
+    #[serde(default = "yes")]
+    mapped: bool,
+    #[serde(default)]
+    hidden: bool,
+    workspace: WorkspaceRef,
+    #[serde(default)]
+    class: String,
+    #[serde(default)]
+    title: String,
+    // -1 when unknown
+    #[serde(default)]
+    pid: i64,
+    // 0 for the focused window
+    #[serde(rename = "focusHistoryID", default = "unfocused")]
+    focus_history_id: i64,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+struct WorkspaceRef {
+    id: i64,
+    #[serde(default)]
+    name: String,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+struct Monitor {
+    #[serde(rename = "activeWorkspace")]
+    active_workspace: WorkspaceRef,
+    // id 0 when no special workspace is open on the monitor
+    #[serde(rename = "specialWorkspace", default)]
+    special_workspace: Option<WorkspaceRef>,
+    #[serde(default)]
+    focused: bool,
+}
+
+fn yes() -> bool {
+    true
+}
+
+fn unfocused() -> i64 {
+    -1
+}
+
+impl WorkspaceRef {
+    // As a dispatcher argument. Named workspaces have negative ids, which a
+    // dispatcher would take as a relative offset.
+    fn selector(&self) -> String {
+        if self.id > 0 {
+            self.id.to_string()
+        } else {
+            format!("name:{}", self.name)
+        }
+    }
+}
+
+impl Client {
+    // Addresses are hex pointers such as "0x55d1c0a0f2b0"
+    fn id(&self) -> Option<u64> {
+        u64::from_str_radix(self.address.trim_start_matches("0x"), 16).ok()
+    }
+}
+
+impl State {
+    fn focused_monitor(&self) -> Option<&Monitor> {
+        self.monitors
+            .iter()
+            .find(|m| m.focused)
+            .or(self.monitors.first())
+    }
+
+    fn client(&self, id: u64) -> BackendResult<&Client> {
+        self.clients
+            .iter()
+            .find(|c| c.id() == Some(id))
+            .ok_or(BackendError::WindowGone(id))
+    }
+
+    fn is_on_current_workspace(&self, client: &Client) -> bool {
+        self.focused_monitor().is_some_and(|m| {
+            m.active_workspace.id == client.workspace.id
+                || m.special_workspace
+                    .as_ref()
+                    .is_some_and(|s| s.id != 0 && s.id == client.workspace.id)
+        })
+    }
+
+    // Shown on any monitor, either as its workspace or an open special one
+    fn is_visible(&self, client: &Client) -> bool {
+        let shown = self.monitors.iter().any(|m| {
+            m.active_workspace.id == client.workspace.id
+                || m.special_workspace
+                    .as_ref()
+                    .is_some_and(|s| s.id != 0 && s.id == client.workspace.id)
+        });
+        client.mapped && !client.hidden && shown
+    }
+}
+
+impl HyprlandBackend {
+    pub fn new(dir: PathBuf, hide_method: HideMethod) -> Self {
+        let events = Events::listen(&dir.join(".socket2.sock"));
+        Self {
+            dir,
+            hide_method,
+            cache: None,
+            events,
+        }
+    }
+
+    // Newer Hyprland keeps its sockets under $XDG_RUNTIME_DIR, older under /tmp.
+    pub fn socket_dir() -> Option<PathBuf> {
+        let instance = env::var_os("HYPRLAND_INSTANCE_SIGNATURE")?;
+        let runtime = env::var_os("XDG_RUNTIME_DIR")
+            .map(|dir| PathBuf::from(dir).join("hypr").join(&instance));
+        match runtime {
+            Some(dir) if dir.join(".socket.sock").exists() => Some(dir),
+            _ => Some(Path::new("/tmp/hypr").join(&instance)),
+        }
+    }
+
+    pub fn probe() -> Probe {
+        let socket = Self::socket_dir().map(|dir| dir.join(".socket.sock"));
+        Probe::socket("HYPRLAND_INSTANCE_SIGNATURE", socket.as_deref())
+    }
+
+    fn request(&self, command: &str) -> BackendResult<String> {
+        let mut stream = ipc::connect(&self.dir.join(".socket.sock"))
+            .map_err(|_| BackendError::DisplayUnavailable)?;
+        let io = |e: std::io::Error| BackendError::Protocol(e.to_string());
+        stream.write_all(command.as_bytes()).map_err(io)?;
+        let mut reply = String::new();
+        stream.read_to_string(&mut reply).map_err(io)?;
+        Ok(reply)
+    }
+
+    fn query<T: for<'de> Deserialize<'de>>(&self, command: &str) -> BackendResult<T> {
+        let reply = self.request(command)?;
+        serde_json::from_str(&reply).map_err(|e| BackendError::Protocol(format!("{command}: {e}")))
+    }
+
+    fn dispatch(&mut self, args: &str) -> BackendResult<()> {
+        let reply = self.request(&format!("dispatch {args}"))?;
+        // Our own change; don't wait for its event to refetch
+        self.cache = None;
+        match reply.trim() {
+            "ok" => Ok(()),
+            error => Err(BackendError::Protocol(error.to_string())),
+        }
+    }
+
+    fn state(&mut self) -> BackendResult<&State> {
+        let fresh = self.events.as_ref().is_some_and(Events::unchanged);
+        if !fresh || self.cache.is_none() {
+            let state = State {
+                clients: self.query("j/clients")?,
+                monitors: self.query("j/monitors")?,
+            };
+            self.cache = Some(state);
+        }
+        Ok(self.cache.as_ref().unwrap())
+    }
+
+    fn address(id: u64) -> String {
+        format!("address:0x{id:x}")
+    }
+}
+
+impl Events {
+    // None if the event socket cannot be opened; every lookup then refetches.
+    fn listen(path: &Path) -> Option<Self> {
+        let stream = UnixStream::connect(path).ok()?;
+        let changed = Arc::new(AtomicBool::new(true));
+        let listening = Arc::new(AtomicBool::new(true));
+        let (thread_changed, thread_listening) = (changed.clone(), listening.clone());
+        thread::spawn(move || {
+            // Lines such as "openwindow>>55d1c0a0f2b0,1,Alacritty,~"; any of
+            // them may change what a lookup would see
+            for line in BufReader::new(stream).lines() {
+                if line.is_err() {
+                    break;
+                }
+                thread_changed.store(true, Ordering::SeqCst);
+            }
+            thread_listening.store(false, Ordering::SeqCst);
+        });
+        Some(Self { changed, listening })
+    }
+
+    // True if nothing happened since the last call.
+    fn unchanged(&self) -> bool {
+        self.listening.load(Ordering::SeqCst) && !self.changed.swap(false, Ordering::SeqCst)
+    }
+}
+
+impl WindowBackend for HyprlandBackend {
+    fn list_windows(&mut self, app_name: &str) -> BackendResult<Vec<WindowInfo>> {
+        let state = self.state()?;
+        Ok(state
+            .clients
+            .iter()
+            .filter(|c| matches_app(app_name, Some(&c.title), Some(&c.class)))
+            .filter_map(|c| {
+                Some(WindowInfo {
+                    id: c.id()?,
+                    class: Some(c.class.clone()),
+                    title: Some(c.title.clone()),
+                    workspace: Some(c.workspace.name.clone()),
+                    on_current_ws: state.is_on_current_workspace(c),
+                    visible: state.is_visible(c),
+                    focused: c.focus_history_id == 0,
+                    pid: u32::try_from(c.pid).ok().filter(|&pid| pid > 0),
+                })
+            })
+            .collect())
+    }
+
+    fn is_on_current_workspace(&mut self, window: u64) -> BackendResult<bool> {
+        let state = self.state()?;
+        Ok(state.is_on_current_workspace(state.client(window)?))
+    }
+
+    fn is_visible(&mut self, window: u64) -> BackendResult<bool> {
+        let state = self.state()?;
+        Ok(state.is_visible(state.client(window)?))
+    }
+
+    fn move_to_current_workspace(&mut self, window: u64) -> BackendResult<()> {
+        let state = self.state()?;
+        state.client(window)?;
+        let Some(monitor) = state.focused_monitor() else {
+            return Err(BackendError::Protocol("no monitor".to_string()));
+        };
+        let workspace = monitor.active_workspace.selector();
+        self.dispatch(&format!(
+            "movetoworkspacesilent {workspace},{}",
+            Self::address(window)
+        ))
+    }
+
+    fn show(&mut self, window: u64) -> BackendResult<()> {
+        // A hidden window sits on another workspace, so it has been moved
+        // back before this
+        self.state()?.client(window)?;
+        self.dispatch(&format!("focuswindow {}", Self::address(window)))
+    }
+
+    fn hide(&mut self, window: u64) -> BackendResult<()> {
+        match self.hide_method {
+            HideMethod::None => Err(BackendError::Unsupported(
+                "hiding with wayland_hide_method = none",
+            )),
+            HideMethod::Auto | HideMethod::Scratchpad => self.dispatch(&format!(
+                "movetoworkspacesilent {SPECIAL_WORKSPACE},{}",
+                Self::address(window)
+            )),
+        }
+    }
+
+    fn launch_app(&mut self, app_path: &str) -> BackendResult<u32> {
+        spawn_app(app_path)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::common_backend::test_support::{app, assert_display_unavailable, TempDir};
+    use crate::common_backend::{toggle_or_launch, Launched, Outcome};
+    use std::os::unix::net::UnixListener;
+    use std::sync::{mpsc, Mutex};
+    use std::time::Duration;
+
+    // Recorded from Hyprland 0.41, trimmed. Alacritty is focused on
+    // workspace 1, the notes app hidden by us, Firefox on workspace 2.
+    const CLIENTS: &str = r#"[
+        {"address": "0x5f1a2b3c4d50", "mapped": true, "hidden": false,
+         "workspace": {"id": 1, "name": "1"}, "floating": false, "monitor": 0,
+         "class": "Alacritty", "title": "~", "pid": 4242, "focusHistoryID": 0},
+        {"address": "0x5f1a2b3c4e60", "mapped": true, "hidden": false,
+         "workspace": {"id": -98, "name": "special:hotkey-launcher"}, "monitor": 0,
+         "class": "gedit", "title": "notes", "pid": 4243, "focusHistoryID": 2},
+        {"address": "0x5f1a2b3c4f70", "mapped": true, "hidden": false,
+         "workspace": {"id": 2, "name": "2"}, "monitor": 0,
+         "class": "firefox", "title": "Mozilla Firefox", "pid": 4244, "focusHistoryID": 1}
+    ]"#;
+    const MONITORS: &str = r#"[
+        {"id": 0, "name": "eDP-1", "activeWorkspace": {"id": 1, "name": "1"},
+         "specialWorkspace": {"id": 0, "name": ""}, "focused": true}
+    ]"#;
+
+    struct FakeHyprland {
+        dir: TempDir,
+        requests: Arc<Mutex<Vec<String>>>,
+        events: mpsc::Sender<String>,
+    }
+
+    impl FakeHyprland {
+        fn start(name: &str, monitors: &'static str) -> Self {
+            let dir = TempDir::new(name);
+
+            let listener = UnixListener::bind(dir.join(".socket.sock")).unwrap();
+            let requests = Arc::new(Mutex::new(Vec::new()));
+            let recorded = requests.clone();
+            thread::spawn(move || {
+                for stream in listener.incoming() {
+                    let Ok(mut stream) = stream else { return };
+                    let mut buffer = [0u8; 1024];
+                    let n = stream.read(&mut buffer).unwrap();
+                    let request = String::from_utf8_lossy(&buffer[..n]).into_owned();
+                    let reply = match request.as_str() {
+                        "j/clients" => CLIENTS,
+                        "j/monitors" => monitors,
+                        r if r.starts_with("dispatch ") => "ok",
+                        _ => "unknown request",
+                    };
+                    recorded.lock().unwrap().push(request);
+                    let _ = stream.write_all(reply.as_bytes());
+                }
+            });
+
+            let events_listener = UnixListener::bind(dir.join(".socket2.sock")).unwrap();
+            let (events, lines) = mpsc::channel::<String>();
+            thread::spawn(move || {
+                let (mut stream, _) = events_listener.accept().unwrap();
+                for line in lines {
+                    let _ = stream.write_all(format!("{line}\n").as_bytes());
+                }
+            });
+            Self {
+                dir,
+                requests,
+                events,
+            }
+        }
+
+        fn take_requests(&self) -> Vec<String> {
+            std::mem::take(&mut *self.requests.lock().unwrap())
+        }
+    }
+
+    #[test]
+    fn toggles_through_a_special_workspace() {
+        let hypr = FakeHyprland::start("hyprland-toggle", MONITORS);
+        let mut backend = HyprlandBackend::new(hypr.dir.to_path_buf(), HideMethod::Auto);
+
+        let listed = backend.list_windows("class=Alacritty").unwrap();
+        assert_eq!(listed[0].id, 0x5f1a2b3c4d50);
+        assert!(listed[0].on_current_ws && listed[0].visible && listed[0].focused);
+        let notes = &backend.list_windows("gedit").unwrap()[0];
+        assert!(!notes.on_current_ws && !notes.visible);
+
+        assert_eq!(
+            toggle_or_launch(&mut backend, &app("Alacritty"), &mut Launched::default()).unwrap(),
+            Outcome::Hidden(0x5f1a2b3c4d50)
+        );
+        assert_eq!(
+            toggle_or_launch(&mut backend, &app("gedit"), &mut Launched::default()).unwrap(),
+            Outcome::Moved(0x5f1a2b3c4e60)
+        );
+        let dispatched: Vec<String> = hypr
+            .take_requests()
+            .into_iter()
+            .filter(|r| r.starts_with("dispatch"))
+            .collect();
+        assert_eq!(
+            dispatched,
+            [
+                "dispatch movetoworkspacesilent special:hotkey-launcher,address:0x5f1a2b3c4d50",
+                "dispatch movetoworkspacesilent 1,address:0x5f1a2b3c4e60",
+                "dispatch focuswindow address:0x5f1a2b3c4e60",
+            ]
+        );
+        assert!(matches!(
+            backend.is_visible(0x1234),
+            Err(BackendError::WindowGone(0x1234))
+        ));
+    }
+
+    #[test]
+    fn moves_to_a_named_workspace_by_name() {
+        const NAMED: &str = r#"[
+            {"id": 0, "name": "eDP-1", "activeWorkspace": {"id": -1337, "name": "mail"},
+             "specialWorkspace": {"id": 0, "name": ""}, "focused": true}
+        ]"#;
+        let hypr = FakeHyprland::start("hyprland-named", NAMED);
+        let mut backend = HyprlandBackend::new(hypr.dir.to_path_buf(), HideMethod::Auto);
+        assert_eq!(
+            toggle_or_launch(&mut backend, &app("firefox"), &mut Launched::default()).unwrap(),
+            Outcome::Moved(0x5f1a2b3c4f70)
+        );
+        assert!(hypr.take_requests().contains(
+            &"dispatch movetoworkspacesilent name:mail,address:0x5f1a2b3c4f70".to_string()
+        ));
+    }
+
+    #[test]
+    fn refetches_only_after_an_event() {
+        let hypr = FakeHyprland::start("hyprland-events", MONITORS);
+        let mut backend = HyprlandBackend::new(hypr.dir.to_path_buf(), HideMethod::Auto);
+        backend.find_window("Alacritty").unwrap();
+        assert_eq!(hypr.take_requests(), ["j/clients", "j/monitors"]);
+
+        // Nothing happened: answered from the cache
+        backend.find_window("Alacritty").unwrap();
+        backend.is_visible(0x5f1a2b3c4f70).unwrap();
+        assert!(hypr.take_requests().is_empty());
+
+        hypr.events
+            .send("openwindow>>5f1a2b3c5080,1,Alacritty,~".to_string())
+            .unwrap();
+        let changed = backend.events.as_ref().unwrap().changed.clone();
+        let deadline = std::time::Instant::now() + Duration::from_secs(2);
+        while !changed.load(Ordering::SeqCst) && std::time::Instant::now() < deadline {
+            thread::sleep(Duration::from_millis(10));
+        }
+        backend.find_window("Alacritty").unwrap();
+        assert_eq!(hypr.take_requests(), ["j/clients", "j/monitors"]);
+    }
+
+    #[test]
+    fn missing_socket_is_display_unavailable() {
+        let mut backend = HyprlandBackend::new(PathBuf::from("/nonexistent"), HideMethod::Auto);
+        assert_display_unavailable(&mut backend);
+    }
+}
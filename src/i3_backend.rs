@@ -0,0 +1,352 @@
+use crate::common_backend::{
+    spawn_app, BackendError, BackendResult, HideMethod, Probe, WindowBackend, WindowInfo,
+};
+use crate::i3ipc::{for_con, quote, IpcClient, IpcWindow, Workspace, SCRATCHPAD};
+use crate::ipc::{Reconnecting, RequestError};
+use crate::matching::matches_app;
+use std::env;
+use std::path::PathBuf;
+use std::process::Command;
+
+// i3 and sway over their shared IPC protocol. Windows are found in GET_TREE
+// by app_id (sway) or class, and hidden in the scratchpad: neither has
+// minimizing, so EWMH iconify does nothing useful under i3.
+pub struct I3Backend {
+    ipc: Reconnecting<IpcClient>,
+    hide_method: HideMethod,
+}
+
+impl I3Backend {
+    pub fn sway(socket: PathBuf, hide_method: HideMethod) -> Self {
+        Self {
+            ipc: Reconnecting::new(socket, IpcClient::connect),
+            hide_method,
+        }
+    }
+
+    // wayland_hide_method does not apply: i3 always uses the scratchpad.
+    pub fn i3(socket: PathBuf) -> Self {
+        Self::sway(socket, HideMethod::Scratchpad)
+    }
+
+    pub fn sway_socket() -> Option<PathBuf> {
+        env::var_os("SWAYSOCK").map(PathBuf::from)
+    }
+
+    // $I3SOCK, which i3 sets for the programs it starts, else whatever a
+    // running i3 reports. None if i3 is not running.
+    pub fn i3_socket() -> Option<PathBuf> {
+        if let Some(socket) = env::var_os("I3SOCK") {
+            return Some(PathBuf::from(socket));
+        }
+        env::var_os("DISPLAY")?;
+        let output = Command::new("i3").arg("--get-socketpath").output().ok()?;
+        if !output.status.success() {
+            return None;
+        }
+        let path = String::from_utf8(output.stdout).ok()?;
+        let path = path.trim();
+        (!path.is_empty()).then(|| PathBuf::from(path))
+    }
+
+    pub fn probe_sway() -> Probe {
+        Probe::socket("SWAYSOCK", Self::sway_socket().as_deref())
+    }
+
+    pub fn probe_i3() -> Probe {
+        // sway sets I3SOCK too, for i3 tools
+        if env::var_os("SWAYSOCK").is_some() {
+            return Probe::none("the i3 socket is sway's");
+        }
+        match Self::i3_socket() {
+            Some(socket) => Probe::socket("I3SOCK", Some(&socket)),
+            None => Probe::none("i3 is not running"),
+        }
+    }
+
+    fn with_ipc<T>(
+        &mut self,
+        f: impl FnMut(&mut IpcClient) -> Result<T, RequestError>,
+    ) -> BackendResult<T> {
+        self.ipc.request(f)
+    }
+
+    fn run(&mut self, command: &str) -> BackendResult<()> {
+        self.with_ipc(|ipc| ipc.command(command))?
+            .map_err(BackendError::Protocol)
+    }
+
+    fn windows(&mut self) -> BackendResult<Vec<IpcWindow>> {
+        self.with_ipc(|ipc| ipc.tree())
+            .map(|tree| crate::i3ipc::windows(&tree))
+    }
+
+    fn window(&mut self, id: u64) -> BackendResult<IpcWindow> {
+        self.windows()?
+            .into_iter()
+            .find(|w| w.con_id == id)
+            .ok_or(BackendError::WindowGone(id))
+    }
+
+    fn workspaces(&mut self) -> BackendResult<Vec<Workspace>> {
+        self.with_ipc(|ipc| ipc.workspaces())
+    }
+
+    fn matching(&mut self, app_name: &str) -> BackendResult<Vec<WindowState>> {
+        let workspaces = self.workspaces()?;
+        Ok(self
+            .windows()?
+            .into_iter()
+            .filter(|w| matches_app(app_name, w.title.as_deref(), w.class.as_deref()))
+            .map(|w| WindowState::new(w, &workspaces))
+            .collect())
+    }
+
+    fn state(&mut self, id: u64) -> BackendResult<WindowState> {
+        let workspaces = self.workspaces()?;
+        Ok(WindowState::new(self.window(id)?, &workspaces))
+    }
+}
+
+// A window with the workspace facts toggling needs.
+struct WindowState {
+    window: IpcWindow,
+    on_current_ws: bool,
+    visible: bool,
+}
+
+impl WindowState {
+    fn new(window: IpcWindow, workspaces: &[Workspace]) -> Self {
+        let workspace = workspaces
+            .iter()
+            .find(|ws| Some(&ws.name) == window.workspace.as_ref());
+        let on_current_ws = workspace.is_some_and(|ws| ws.focused);
+        // Scratchpad windows are never on a real workspace
+        let visible = !window.in_scratchpad()
+            && window
+                .visible
+                .unwrap_or_else(|| workspace.is_some_and(|ws| ws.visible));
+        Self {
+            window,
+            on_current_ws,
+            visible,
+        }
+    }
+}
+
+impl WindowBackend for I3Backend {
+    fn list_windows(&mut self, app_name: &str) -> BackendResult<Vec<WindowInfo>> {
+        Ok(self
+            .matching(app_name)?
+            .into_iter()
+            .map(|s| WindowInfo {
+                id: s.window.con_id,
+                workspace: s.window.workspace.map(|ws| match ws.as_str() {
+                    SCRATCHPAD => "scratchpad".to_string(),
+                    _ => ws,
+                }),
+                class: s.window.class,
+                title: s.window.title,
+                on_current_ws: s.on_current_ws,
+                visible: s.visible,
+                focused: s.window.focused,
+                pid: s.window.pid,
+            })
+            .collect())
+    }
+
+    fn is_on_current_workspace(&mut self, window: u64) -> BackendResult<bool> {
+        Ok(self.state(window)?.on_current_ws)
+    }
+
+    fn is_visible(&mut self, window: u64) -> BackendResult<bool> {
+        Ok(self.state(window)?.visible)
+    }
+
+    // Scratchpad windows are left alone: `scratchpad show` brings them here.
+    fn move_to_current_workspace(&mut self, window: u64) -> BackendResult<()> {
+        if self.window(window)?.in_scratchpad() {
+            return Ok(());
+        }
+        let current = self.workspaces()?.into_iter().find(|ws| ws.focused);
+        let Some(current) = current else {
+            return Err(BackendError::Protocol("no focused workspace".to_string()));
+        };
+        self.run(&for_con(
+            window,
+            &format!("move container to workspace {}", quote(&current.name)),
+        ))
+    }
+
+    fn show(&mut self, window: u64) -> BackendResult<()> {
+        if self.window(window)?.in_scratchpad() {
+            self.run(&for_con(window, "scratchpad show"))
+        } else {
+            self.run(&for_con(window, "focus"))
+        }
+    }
+
+    fn hide(&mut self, window: u64) -> BackendResult<()> {
+        match self.hide_method {
+            HideMethod::None => Err(BackendError::Unsupported(
+                "hiding with wayland_hide_method = none",
+            )),
+            HideMethod::Auto | HideMethod::Scratchpad => {
+                self.run(&for_con(window, "move scratchpad"))
+            }
+        }
+    }
+
+    fn launch_app(&mut self, app_path: &str) -> BackendResult<u32> {
+        spawn_app(app_path)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::common_backend::test_support::{app, assert_display_unavailable};
+    use crate::common_backend::{toggle_or_launch, Launched, Outcome};
+    use crate::i3ipc::tests::FakeServer;
+    use crate::i3ipc::{GET_TREE, GET_WORKSPACES, RUN_COMMAND};
+
+    // Workspace 1 is focused and 2 is visible on another output. Alacritty is
+    // focused on 1, the notes app is in the scratchpad and Firefox is on 3.
+    const TREE: &str = r#"{"id": 1, "type": "root", "nodes": [
+        {"id": 2, "type": "output", "name": "__i3", "nodes": [
+            {"id": 3, "type": "workspace", "name": "__i3_scratch", "nodes": [],
+             "floating_nodes": [{"id": 10, "type": "floating_con", "name": "notes",
+                                 "app_id": "gedit", "nodes": []}]}]},
+        {"id": 4, "type": "output", "name": "eDP-1", "nodes": [
+            {"id": 5, "type": "workspace", "name": "1", "nodes": [
+                {"id": 11, "type": "con", "name": "~", "app_id": "Alacritty",
+                 "focused": true, "visible": true, "nodes": []}]},
+            {"id": 6, "type": "workspace", "name": "3", "nodes": [
+                {"id": 12, "type": "con", "name": "Mozilla Firefox", "window": 8388611,
+                 "window_properties": {"class": "firefox"}, "nodes": []}]}]}]}"#;
+    const WORKSPACES: &str = r#"[
+        {"name": "1", "focused": true, "visible": true},
+        {"name": "2", "focused": false, "visible": true},
+        {"name": "3", "focused": false, "visible": false}]"#;
+
+    fn sway() -> (FakeServer, I3Backend) {
+        let server = FakeServer::start("sway", |kind, _| match kind {
+            GET_TREE => TREE.to_string(),
+            GET_WORKSPACES => WORKSPACES.to_string(),
+            RUN_COMMAND => r#"[{"success": true}]"#.to_string(),
+            _ => "null".to_string(),
+        });
+        let backend = I3Backend::sway(server.path.clone(), HideMethod::Auto);
+        (server, backend)
+    }
+
+    #[test]
+    fn finds_windows_by_app_id_and_class() {
+        let (_server, mut sway) = sway();
+        let listed = sway.list_windows("Alacritty").unwrap();
+        assert_eq!(listed.len(), 1);
+        assert_eq!(listed[0].id, 11);
+        assert!(listed[0].on_current_ws && listed[0].visible && listed[0].focused);
+
+        let notes = &sway.list_windows("class=gedit").unwrap()[0];
+        assert_eq!(notes.workspace.as_deref(), Some("scratchpad"));
+        assert!(!notes.on_current_ws && !notes.visible);
+
+        assert_eq!(sway.find_window("firefox").unwrap(), Some(12));
+        assert!(!sway.is_visible(12).unwrap());
+        assert!(matches!(
+            sway.is_visible(99),
+            Err(BackendError::WindowGone(99))
+        ));
+    }
+
+    #[test]
+    fn toggles_through_the_scratchpad() {
+        let (server, mut sway) = sway();
+        assert_eq!(
+            toggle_or_launch(&mut sway, &app("Alacritty"), &mut Launched::default()).unwrap(),
+            Outcome::Hidden(11)
+        );
+        assert_eq!(
+            toggle_or_launch(&mut sway, &app("gedit"), &mut Launched::default()).unwrap(),
+            Outcome::Moved(10)
+        );
+        assert_eq!(
+            toggle_or_launch(&mut sway, &app("firefox"), &mut Launched::default()).unwrap(),
+            Outcome::Moved(12)
+        );
+        assert_eq!(
+            server.commands(),
+            [
+                "[con_id=11] move scratchpad",
+                "[con_id=10] scratchpad show",
+                "[con_id=12] move container to workspace \"1\"",
+                "[con_id=12] focus",
+            ]
+        );
+
+        // wayland_hide_method = "none" leaves the window alone, and says so.
+        // The fake serves one connection at a time.
+        drop(sway);
+        let mut sway = I3Backend::sway(server.path.clone(), HideMethod::None);
+        assert!(matches!(
+            toggle_or_launch(&mut sway, &app("Alacritty"), &mut Launched::default()),
+            Err(BackendError::Unsupported(_))
+        ));
+        assert_eq!(server.commands().len(), 4);
+    }
+
+    // i3 trees carry the X11 window id and class but no `visible` field, so
+    // visibility comes from the workspace.
+    const I3_TREE: &str = r#"{"id": 1, "type": "root", "nodes": [
+        {"id": 2, "type": "output", "name": "__i3", "nodes": [
+            {"id": 3, "type": "con", "name": "content", "nodes": [
+                {"id": 4, "type": "workspace", "name": "__i3_scratch", "nodes": []}]}]},
+        {"id": 5, "type": "output", "name": "HDMI-1", "nodes": [
+            {"id": 6, "type": "con", "name": "content", "nodes": [
+                {"id": 7, "type": "workspace", "name": "2: web", "nodes": [
+                    {"id": 20, "type": "con", "name": "user@host: ~", "window": 4194307,
+                     "window_properties": {"class": "Alacritty", "instance": "Alacritty",
+                                           "title": "user@host: ~"},
+                     "nodes": []}]}]}]}]}"#;
+
+    #[test]
+    fn i3_hides_to_the_scratchpad_whatever_the_hide_method() {
+        let server = FakeServer::start("i3", |kind, _| match kind {
+            GET_TREE => I3_TREE.to_string(),
+            GET_WORKSPACES => {
+                r#"[{"name": "2: web", "focused": true, "visible": true}]"#.to_string()
+            }
+            RUN_COMMAND => r#"[{"success": true}]"#.to_string(),
+            _ => "null".to_string(),
+        });
+        let mut i3 = I3Backend::i3(server.path.clone());
+        let listed = i3.list_windows("class=Alacritty").unwrap();
+        assert_eq!(listed.len(), 1);
+        assert!(listed[0].on_current_ws && listed[0].visible);
+        assert_eq!(listed[0].workspace.as_deref(), Some("2: web"));
+
+        assert_eq!(
+            toggle_or_launch(&mut i3, &app("Alacritty"), &mut Launched::default()).unwrap(),
+            Outcome::Hidden(20)
+        );
+        assert_eq!(server.commands(), ["[con_id=20] move scratchpad"]);
+    }
+
+    #[test]
+    fn unanswered_commands_are_not_repeated() {
+        let server = FakeServer::start("sway-silent", |kind, _| match kind {
+            GET_TREE => TREE.to_string(),
+            _ => String::new(),
+        });
+        let mut sway = I3Backend::sway(server.path.clone(), HideMethod::Auto);
+        assert!(matches!(sway.show(10), Err(BackendError::Protocol(_))));
+        assert_eq!(server.commands(), ["[con_id=10] scratchpad show"]);
+    }
+
+    #[test]
+    fn missing_socket_is_display_unavailable() {
+        let mut sway = I3Backend::sway(PathBuf::from("/nonexistent/sway.sock"), HideMethod::Auto);
+        assert_display_unavailable(&mut sway);
+    }
+}
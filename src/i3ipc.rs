@@ -0,0 +1,348 @@
+// Client for the i3 IPC protocol, which sway speaks too: "i3-ipc", then the
+// payload length and message type as native-endian u32s, then a JSON payload.
+// Replies use the same framing with the request's type.
+use crate::ipc::{self, RequestError};
+use serde::Deserialize;
+use std::io::{self, Read, Write};
+use std::os::unix::net::UnixStream;
+use std::path::Path;
+
+const MAGIC: &[u8; 6] = b"i3-ipc";
+
+pub const RUN_COMMAND: u32 = 0;
+pub const GET_WORKSPACES: u32 = 1;
+pub const GET_TREE: u32 = 4;
+
+// Name of the workspace that holds scratchpad windows
+pub const SCRATCHPAD: &str = "__i3_scratch";
+
+pub fn encode(kind: u32, payload: &[u8]) -> Vec<u8> {
+    let mut message = Vec::with_capacity(14 + payload.len());
+    message.extend_from_slice(MAGIC);
+    message.extend_from_slice(&(payload.len() as u32).to_ne_bytes());
+    message.extend_from_slice(&kind.to_ne_bytes());
+    message.extend_from_slice(payload);
+    message
+}
+
+// One framed message as (type, payload).
+pub fn read_message(reader: &mut impl Read) -> io::Result<(u32, Vec<u8>)> {
+    let mut header = [0u8; 14];
+    reader.read_exact(&mut header)?;
+    if &header[..6] != MAGIC {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "not an i3-ipc message",
+        ));
+    }
+    let len = u32::from_ne_bytes(header[6..10].try_into().unwrap()) as usize;
+    let kind = u32::from_ne_bytes(header[10..14].try_into().unwrap());
+    let mut payload = vec![0u8; len];
+    reader.read_exact(&mut payload)?;
+    Ok((kind, payload))
+}
+
+pub struct IpcClient {
+    stream: UnixStream,
+}
+
+impl IpcClient {
+    pub fn connect(path: &Path) -> io::Result<Self> {
+        Ok(Self {
+            stream: ipc::connect(path)?,
+        })
+    }
+
+    pub fn request<T: for<'de> Deserialize<'de>>(
+        &mut self,
+        kind: u32,
+        payload: &str,
+    ) -> Result<T, RequestError> {
+        self.stream
+            .write_all(&encode(kind, payload.as_bytes()))
+            .map_err(RequestError::Unsent)?;
+        let (reply_kind, reply) = read_message(&mut self.stream).map_err(RequestError::Sent)?;
+        if reply_kind != kind {
+            return Err(RequestError::Sent(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("expected a reply of type {kind}, got {reply_kind}"),
+            )));
+        }
+        serde_json::from_slice(&reply)
+            .map_err(|e| RequestError::Sent(io::Error::new(io::ErrorKind::InvalidData, e)))
+    }
+
+    pub fn tree(&mut self) -> Result<Node, RequestError> {
+        self.request(GET_TREE, "")
+    }
+
+    pub fn workspaces(&mut self) -> Result<Vec<Workspace>, RequestError> {
+        self.request(GET_WORKSPACES, "")
+    }
+
+    // Runs a command; Ok(Err(message)) if the window manager rejected it.
+    pub fn command(&mut self, command: &str) -> Result<Result<(), String>, RequestError> {
+        let results: Vec<CommandResult> = self.request(RUN_COMMAND, command)?;
+        Ok(match results.into_iter().find(|r| !r.success) {
+            Some(failed) => Err(failed
+                .error
+                .unwrap_or_else(|| format!("'{command}' failed"))),
+            None => Ok(()),
+        })
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct CommandResult {
+    success: bool,
+    #[serde(default)]
+    error: Option<String>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct Workspace {
+    pub name: String,
+    #[serde(default)]
+    pub focused: bool,
+    #[serde(default)]
+    pub visible: bool,
+}
+
+// A GET_TREE node; only the fields used to find windows.
+#[derive(Debug, Clone, Deserialize)]
+pub struct Node {
+    pub id: u64,
+    #[serde(rename = "type", default)]
+    pub kind: String,
+    #[serde(default)]
+    pub name: Option<String>,
+    // Wayland-native views (sway)
+    #[serde(default)]
+    pub app_id: Option<String>,
+    // X11 id of i3 windows and sway Xwayland views
+    #[serde(default)]
+    pub window: Option<u64>,
+    #[serde(default)]
+    pub window_properties: Option<WindowProperties>,
+    // sway only
+    #[serde(default)]
+    pub pid: Option<u32>,
+    #[serde(default)]
+    pub focused: bool,
+    // sway only; false e.g. for a background tab
+    #[serde(default)]
+    pub visible: Option<bool>,
+    #[serde(default)]
+    pub nodes: Vec<Node>,
+    #[serde(default)]
+    pub floating_nodes: Vec<Node>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct WindowProperties {
+    #[serde(default)]
+    pub class: Option<String>,
+    #[serde(default)]
+    pub title: Option<String>,
+}
+
+// A window container and where it sits.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct IpcWindow {
+    pub con_id: u64,
+    // app_id, or the X11 class for X clients
+    pub class: Option<String>,
+    pub title: Option<String>,
+    pub workspace: Option<String>,
+    pub focused: bool,
+    pub visible: Option<bool>,
+    pub pid: Option<u32>,
+}
+
+impl IpcWindow {
+    pub fn in_scratchpad(&self) -> bool {
+        self.workspace.as_deref() == Some(SCRATCHPAD)
+    }
+}
+
+// Every window in the tree, in tree order (tiled before floating).
+pub fn windows(tree: &Node) -> Vec<IpcWindow> {
+    let mut found = Vec::new();
+    collect(tree, None, &mut found);
+    found
+}
+
+fn collect(node: &Node, workspace: Option<&str>, found: &mut Vec<IpcWindow>) {
+    let workspace = match node.kind.as_str() {
+        "workspace" => node.name.as_deref(),
+        _ => workspace,
+    };
+    let props = node.window_properties.as_ref();
+    let is_window = node.app_id.is_some() || node.window.is_some() || props.is_some();
+    if is_window && node.nodes.is_empty() {
+        found.push(IpcWindow {
+            con_id: node.id,
+            class: node
+                .app_id
+                .clone()
+                .or_else(|| props.and_then(|p| p.class.clone())),
+            title: node
+                .name
+                .clone()
+                .or_else(|| props.and_then(|p| p.title.clone())),
+            workspace: workspace.map(str::to_string),
+            focused: node.focused,
+            visible: node.visible,
+            pid: node.pid,
+        });
+    }
+    for child in node.nodes.iter().chain(&node.floating_nodes) {
+        collect(child, workspace, found);
+    }
+}
+
+// Criteria for one container, e.g. `[con_id=12] focus`.
+pub fn for_con(con_id: u64, command: &str) -> String {
+    format!("[con_id={con_id}] {command}")
+}
+
+// A workspace name as a quoted command argument.
+pub fn quote(name: &str) -> String {
+    format!("\"{}\"", name.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+    use crate::common_backend::test_support::TempDir;
+    use std::os::unix::net::UnixListener;
+    use std::path::PathBuf;
+    use std::sync::{Arc, Mutex};
+    use std::thread;
+
+    // A window manager stand-in on a temporary socket. `reply` answers each
+    // request, or closes the connection when it returns ""; every request is
+    // recorded as (type, payload).
+    pub struct FakeServer {
+        pub path: PathBuf,
+        _dir: TempDir,
+        pub requests: Arc<Mutex<Vec<(u32, String)>>>,
+    }
+
+    impl FakeServer {
+        pub fn start(
+            name: &str,
+            reply: impl Fn(u32, &str) -> String + Send + 'static,
+        ) -> FakeServer {
+            let dir = TempDir::new(name);
+            let path = dir.join("ipc.sock");
+            let listener = UnixListener::bind(&path).unwrap();
+            let requests = Arc::new(Mutex::new(Vec::new()));
+            let recorded = requests.clone();
+            thread::spawn(move || {
+                for stream in listener.incoming() {
+                    let Ok(mut stream) = stream else { return };
+                    while let Ok((kind, payload)) = read_message(&mut stream) {
+                        let payload = String::from_utf8(payload).unwrap();
+                        let answer = reply(kind, &payload);
+                        recorded.lock().unwrap().push((kind, payload));
+                        if answer.is_empty() {
+                            break;
+                        }
+                        if stream.write_all(&encode(kind, answer.as_bytes())).is_err() {
+                            break;
+                        }
+                    }
+                }
+            });
+            FakeServer {
+                path,
+                _dir: dir,
+                requests,
+            }
+        }
+
+        pub fn commands(&self) -> Vec<String> {
+            self.requests
+                .lock()
+                .unwrap()
+                .iter()
+                .filter(|(kind, _)| *kind == RUN_COMMAND)
+                .map(|(_, payload)| payload.clone())
+                .collect()
+        }
+    }
+
+    #[test]
+    fn frames_round_trip() {
+        let message = encode(GET_TREE, b"{}");
+        assert_eq!(&message[..6], b"i3-ipc");
+        assert_eq!(message.len(), 16);
+        assert_eq!(
+            read_message(&mut message.as_slice()).unwrap(),
+            (GET_TREE, b"{}".to_vec())
+        );
+        assert!(read_message(&mut &b"i3-ip"[..]).is_err());
+        assert!(read_message(&mut &b"xx-ipc\0\0\0\0\0\0\0\0"[..]).is_err());
+    }
+
+    #[test]
+    fn finds_windows_and_their_workspaces() {
+        let tree: Node = serde_json::from_str(
+            r#"{"id": 1, "type": "root", "nodes": [
+                {"id": 2, "type": "output", "name": "__i3", "nodes": [
+                    {"id": 3, "type": "workspace", "name": "__i3_scratch", "nodes": [],
+                     "floating_nodes": [
+                        {"id": 4, "type": "floating_con", "nodes": [
+                            {"id": 5, "type": "con", "name": "notes", "app_id": "gedit", "nodes": []}
+                        ]}
+                     ]}
+                ]},
+                {"id": 6, "type": "output", "name": "eDP-1", "nodes": [
+                    {"id": 7, "type": "workspace", "name": "1", "nodes": [
+                        {"id": 8, "type": "con", "name": "~", "app_id": "Alacritty",
+                         "pid": 4242, "focused": true, "visible": true, "nodes": []},
+                        {"id": 9, "type": "con", "name": "Firefox", "window": 4194307,
+                         "window_properties": {"class": "firefox", "title": "Mozilla"},
+                         "nodes": []}
+                    ]}
+                ]}
+            ]}"#,
+        )
+        .unwrap();
+        let found = windows(&tree);
+        assert_eq!(
+            found.iter().map(|w| w.con_id).collect::<Vec<_>>(),
+            vec![5, 8, 9]
+        );
+        assert!(found[0].in_scratchpad());
+        assert_eq!(found[1].class.as_deref(), Some("Alacritty"));
+        assert!(found[1].focused);
+        assert_eq!(found[1].pid, Some(4242));
+        assert_eq!(found[2].pid, None);
+        assert_eq!(found[2].class.as_deref(), Some("firefox"));
+        assert_eq!(found[2].title.as_deref(), Some("Firefox"));
+        assert_eq!(found[2].workspace.as_deref(), Some("1"));
+    }
+
+    #[test]
+    fn commands_over_a_socket() {
+        let server = FakeServer::start("i3ipc", |kind, payload| match (kind, payload) {
+            (RUN_COMMAND, "nop") => r#"[{"success": true}]"#.to_string(),
+            (RUN_COMMAND, _) => r#"[{"success": false, "error": "Unknown command"}]"#.to_string(),
+            (GET_WORKSPACES, _) => {
+                r#"[{"name": "1", "focused": true, "visible": true}]"#.to_string()
+            }
+            _ => "null".to_string(),
+        });
+        let mut client = IpcClient::connect(&server.path).unwrap();
+        assert_eq!(client.command("nop").unwrap(), Ok(()));
+        assert_eq!(
+            client.command("fly").unwrap(),
+            Err("Unknown command".to_string())
+        );
+        assert_eq!(client.workspaces().unwrap()[0].name, "1");
+        assert_eq!(server.commands(), ["nop", "fly"]);
+        assert_eq!(quote(r#"2: "web""#), r#""2: \"web\"""#);
+    }
+}
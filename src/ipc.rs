@@ -0,0 +1,125 @@
+// Compositor IPC over Unix sockets: a connection with the reply timeout set,
+// and one that is opened on first use and reopened after it breaks, e.g.
+// because the compositor restarted (i3/sway, Wayfire).
+use crate::common_backend::{BackendError, BackendResult, REPLY_TIMEOUT};
+use std::io;
+use std::os::unix::net::UnixStream;
+use std::path::{Path, PathBuf};
+
+pub fn connect(path: &Path) -> io::Result<UnixStream> {
+    let stream = UnixStream::connect(path)?;
+    stream.set_read_timeout(Some(REPLY_TIMEOUT))?;
+    Ok(stream)
+}
+
+// Why a request failed, and whether it reached the other end.
+#[derive(Debug)]
+pub enum RequestError {
+    // Could not be written, so it cannot have taken effect
+    Unsent(io::Error),
+    // Written, but no usable reply came back; it may have taken effect
+    Sent(io::Error),
+}
+
+pub struct Reconnecting<C> {
+    path: PathBuf,
+    connect: fn(&Path) -> io::Result<C>,
+    conn: Option<C>,
+}
+
+impl<C> Reconnecting<C> {
+    pub fn new(path: PathBuf, connect: fn(&Path) -> io::Result<C>) -> Self {
+        Self {
+            path,
+            connect,
+            conn: None,
+        }
+    }
+
+    // Runs one request. One that could not be sent over an open connection is
+    // sent again on a fresh one. One that was sent is never repeated: commands
+    // such as `scratchpad show` toggle, so a second run would undo the first.
+    pub fn request<T>(
+        &mut self,
+        mut f: impl FnMut(&mut C) -> Result<T, RequestError>,
+    ) -> BackendResult<T> {
+        let mut fresh = false;
+        loop {
+            let conn = match &mut self.conn {
+                Some(conn) => conn,
+                None => {
+                    let conn =
+                        (self.connect)(&self.path).map_err(|_| BackendError::DisplayUnavailable)?;
+                    fresh = true;
+                    self.conn.insert(conn)
+                }
+            };
+            match f(conn) {
+                Ok(value) => return Ok(value),
+                Err(RequestError::Unsent(_)) if !fresh => self.conn = None,
+                Err(RequestError::Unsent(error) | RequestError::Sent(error)) => {
+                    self.conn = None;
+                    return Err(BackendError::Protocol(error.to_string()));
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn connect(_: &Path) -> io::Result<()> {
+        Ok(())
+    }
+
+    fn broken() -> io::Error {
+        io::ErrorKind::BrokenPipe.into()
+    }
+
+    #[test]
+    fn resends_only_what_was_not_sent() {
+        let mut ipc = Reconnecting::new(PathBuf::new(), connect);
+        ipc.request(|_| Ok(())).unwrap();
+
+        // A stale connection: sent again on a new one
+        let mut attempts = 0;
+        let result = ipc.request(|_| {
+            attempts += 1;
+            match attempts {
+                1 => Err(RequestError::Unsent(broken())),
+                _ => Ok(attempts),
+            }
+        });
+        assert_eq!(result.unwrap(), 2);
+
+        // Sent, then no reply: never repeated
+        let mut attempts = 0;
+        let result = ipc.request(|_| -> Result<(), _> {
+            attempts += 1;
+            Err(RequestError::Sent(io::ErrorKind::TimedOut.into()))
+        });
+        assert!(matches!(result, Err(BackendError::Protocol(_))));
+        assert_eq!(attempts, 1);
+
+        // A new connection that fails is not retried either
+        let mut attempts = 0;
+        let result = ipc.request(|_| -> Result<(), _> {
+            attempts += 1;
+            Err(RequestError::Unsent(broken()))
+        });
+        assert!(result.is_err());
+        assert_eq!(attempts, 1);
+    }
+
+    #[test]
+    fn failed_connect_is_display_unavailable() {
+        let mut ipc: Reconnecting<()> =
+            Reconnecting::new(PathBuf::new(), |_| Err(io::ErrorKind::NotFound.into()));
+        assert!(matches!(
+            ipc.request(|_| Ok(())),
+            Err(BackendError::DisplayUnavailable)
+        ));
+    }
+}
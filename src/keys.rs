@@ -0,0 +1,265 @@
+// Key names for config files: a canonical name for every rdev::Key variant,
+// plus common aliases. Lookups are case-insensitive.
+use rdev::Key;
+use std::fmt;
+
+// Canonical names. key_name() returns these, and parse_key() accepts them.
+const KEY_NAMES: &[(&str, Key)] = &[
+    // Modifiers
+    ("ctrl_left", Key::ControlLeft),
+    ("ctrl_right", Key::ControlRight),
+    ("shift_left", Key::ShiftLeft),
+    ("shift_right", Key::ShiftRight),
+    ("alt", Key::Alt),
+    ("altgr", Key::AltGr),
+    ("super_left", Key::MetaLeft),
+    ("super_right", Key::MetaRight),
+    ("fn", Key::Function),
+    // Editing and navigation
+    ("escape", Key::Escape),
+    ("tab", Key::Tab),
+    ("caps_lock", Key::CapsLock),
+    ("space", Key::Space),
+    ("return", Key::Return),
+    ("backspace", Key::Backspace),
+    ("insert", Key::Insert),
+    ("delete", Key::Delete),
+    ("home", Key::Home),
+    ("end", Key::End),
+    ("page_up", Key::PageUp),
+    ("page_down", Key::PageDown),
+    ("up", Key::UpArrow),
+    ("down", Key::DownArrow),
+    ("left", Key::LeftArrow),
+    ("right", Key::RightArrow),
+    ("print_screen", Key::PrintScreen),
+    ("scroll_lock", Key::ScrollLock),
+    ("pause", Key::Pause),
+    ("num_lock", Key::NumLock),
+    // Function keys
+    ("f1", Key::F1),
+    ("f2", Key::F2),
+    ("f3", Key::F3),
+    ("f4", Key::F4),
+    ("f5", Key::F5),
+    ("f6", Key::F6),
+    ("f7", Key::F7),
+    ("f8", Key::F8),
+    ("f9", Key::F9),
+    ("f10", Key::F10),
+    ("f11", Key::F11),
+    ("f12", Key::F12),
+    // Digits
+    ("0", Key::Num0),
+    ("1", Key::Num1),
+    ("2", Key::Num2),
+    ("3", Key::Num3),
+    ("4", Key::Num4),
+    ("5", Key::Num5),
+    ("6", Key::Num6),
+    ("7", Key::Num7),
+    ("8", Key::Num8),
+    ("9", Key::Num9),
+    // Letters
+    ("a", Key::KeyA),
+    ("b", Key::KeyB),
+    ("c", Key::KeyC),
+    ("d", Key::KeyD),
+    ("e", Key::KeyE),
+    ("f", Key::KeyF),
+    ("g", Key::KeyG),
+    ("h", Key::KeyH),
+    ("i", Key::KeyI),
+    ("j", Key::KeyJ),
+    ("k", Key::KeyK),
+    ("l", Key::KeyL),
+    ("m", Key::KeyM),
+    ("n", Key::KeyN),
+    ("o", Key::KeyO),
+    ("p", Key::KeyP),
+    ("q", Key::KeyQ),
+    ("r", Key::KeyR),
+    ("s", Key::KeyS),
+    ("t", Key::KeyT),
+    ("u", Key::KeyU),
+    ("v", Key::KeyV),
+    ("w", Key::KeyW),
+    ("x", Key::KeyX),
+    ("y", Key::KeyY),
+    ("z", Key::KeyZ),
+    // Punctuation
+    ("grave", Key::BackQuote),
+    ("minus", Key::Minus),
+    ("equal", Key::Equal),
+    ("bracket_left", Key::LeftBracket),
+    ("bracket_right", Key::RightBracket),
+    ("semicolon", Key::SemiColon),
+    ("quote", Key::Quote),
+    ("backslash", Key::BackSlash),
+    ("intl_backslash", Key::IntlBackslash),
+    ("comma", Key::Comma),
+    ("dot", Key::Dot),
+    ("slash", Key::Slash),
+    // Keypad
+    ("kp_0", Key::Kp0),
+    ("kp_1", Key::Kp1),
+    ("kp_2", Key::Kp2),
+    ("kp_3", Key::Kp3),
+    ("kp_4", Key::Kp4),
+    ("kp_5", Key::Kp5),
+    ("kp_6", Key::Kp6),
+    ("kp_7", Key::Kp7),
+    ("kp_8", Key::Kp8),
+    ("kp_9", Key::Kp9),
+    ("kp_enter", Key::KpReturn),
+    ("kp_minus", Key::KpMinus),
+    ("kp_plus", Key::KpPlus),
+    ("kp_multiply", Key::KpMultiply),
+    ("kp_divide", Key::KpDivide),
+    ("kp_delete", Key::KpDelete),
+];
+
+// Accepted on input only. rdev's own variant names (e.g. "ControlLeft",
+// "KeyA") are accepted as well, see parse_key.
+const ALIASES: &[(&str, Key)] = &[
+    ("ctrl", Key::ControlLeft),
+    ("control", Key::ControlLeft),
+    ("control_left", Key::ControlLeft),
+    ("left_ctrl", Key::ControlLeft),
+    ("left_control", Key::ControlLeft),
+    ("control_right", Key::ControlRight),
+    ("right_ctrl", Key::ControlRight),
+    ("right_control", Key::ControlRight),
+    ("shift", Key::ShiftLeft),
+    ("left_shift", Key::ShiftLeft),
+    ("right_shift", Key::ShiftRight),
+    ("alt_left", Key::Alt),
+    ("left_alt", Key::Alt),
+    ("alt_right", Key::AltGr),
+    ("right_alt", Key::AltGr),
+    ("super", Key::MetaLeft),
+    ("meta", Key::MetaLeft),
+    ("meta_left", Key::MetaLeft),
+    ("left_super", Key::MetaLeft),
+    ("meta_right", Key::MetaRight),
+    ("right_super", Key::MetaRight),
+    ("esc", Key::Escape),
+    ("enter", Key::Return),
+    ("capslock", Key::CapsLock),
+    ("del", Key::Delete),
+    ("pageup", Key::PageUp),
+    ("pagedown", Key::PageDown),
+    ("print", Key::PrintScreen),
+    ("backquote", Key::BackQuote),
+    ("backtick", Key::BackQuote),
+    ("equals", Key::Equal),
+    ("apostrophe", Key::Quote),
+    ("period", Key::Dot),
+    ("kp_return", Key::KpReturn),
+];
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UnknownKey(pub String);
+
+impl fmt::Display for UnknownKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "unknown key name '{}'", self.0)
+    }
+}
+
+// Raw keycodes are written as "unknown:<code>" (or rdev's "Unknown(<code>)").
+pub fn parse_key(s: &str) -> Result<Key, UnknownKey> {
+    let k = s.trim().to_ascii_lowercase();
+    if let Some(key) = KEY_NAMES
+        .iter()
+        .chain(ALIASES)
+        .find(|(name, _)| *name == k)
+        .map(|(_, key)| *key)
+    {
+        return Ok(key);
+    }
+    if let Some(code) = k
+        .strip_prefix("unknown:")
+        .or_else(|| k.strip_prefix("unknown(")?.strip_suffix(')'))
+    {
+        return code
+            .parse()
+            .map(Key::Unknown)
+            .map_err(|_| UnknownKey(s.to_string()));
+    }
+    KEY_NAMES
+        .iter()
+        .find(|(_, key)| format!("{key:?}").eq_ignore_ascii_case(&k))
+        .map(|(_, key)| *key)
+        .ok_or_else(|| UnknownKey(s.to_string()))
+}
+
+pub fn key_name(key: Key) -> String {
+    if let Key::Unknown(code) = key {
+        return format!("unknown:{code}");
+    }
+    KEY_NAMES
+        .iter()
+        .find(|(_, k)| *k == key)
+        .map(|(name, _)| name.to_string())
+        .unwrap_or_else(|| format!("{key:?}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn table_round_trips() {
+        for (name, key) in KEY_NAMES {
+            assert_eq!(parse_key(name), Ok(*key), "parse {name}");
+            assert_eq!(key_name(*key), *name, "name of {key:?}");
+            assert_eq!(parse_key(&name.to_ascii_uppercase()), Ok(*key));
+        }
+    }
+
+    #[test]
+    fn table_has_one_name_per_key() {
+        for (i, (name, key)) in KEY_NAMES.iter().enumerate() {
+            for (other_name, other_key) in &KEY_NAMES[i + 1..] {
+                assert_ne!(name, other_name);
+                assert_ne!(key, other_key, "{name} and {other_name}");
+            }
+        }
+        // Every variant except Unknown(_) has a canonical name
+        assert_eq!(KEY_NAMES.len(), 105);
+    }
+
+    #[test]
+    fn aliases_and_rdev_names() {
+        for (alias, key) in ALIASES {
+            assert_eq!(parse_key(alias), Ok(*key), "alias {alias}");
+            assert!(KEY_NAMES.iter().all(|(name, _)| name != alias));
+        }
+        for (_, key) in KEY_NAMES {
+            assert_eq!(parse_key(&format!("{key:?}")), Ok(*key));
+        }
+        assert_eq!(parse_key("CTRL"), Ok(Key::ControlLeft));
+        assert_eq!(parse_key(" right_control "), Ok(Key::ControlRight));
+    }
+
+    #[test]
+    fn unknown_codes_round_trip() {
+        assert_eq!(parse_key("unknown:135"), Ok(Key::Unknown(135)));
+        assert_eq!(parse_key("Unknown(135)"), Ok(Key::Unknown(135)));
+        assert_eq!(key_name(Key::Unknown(135)), "unknown:135");
+        assert_eq!(parse_key(&key_name(Key::Unknown(7))), Ok(Key::Unknown(7)));
+        assert!(parse_key("unknown:x").is_err());
+    }
+
+    #[test]
+    fn unknown_names_are_errors() {
+        assert_eq!(parse_key("hyper"), Err(UnknownKey("hyper".to_string())));
+        assert!(parse_key("").is_err());
+        assert!(parse_key("ctrl_middle").is_err());
+        assert_eq!(
+            UnknownKey("hyper".into()).to_string(),
+            "unknown key name 'hyper'"
+        );
+    }
+}
@@ -0,0 +1,602 @@
+use crate::common_backend::{
+    current_desktop_is, spawn_app, BackendError, BackendResult, HideMethod, Probe, WindowBackend,
+    WindowInfo, REPLY_TIMEOUT,
+};
+use crate::dbus::{self, to_backend_error};
+use crate::matching::matches_app;
+use serde::Deserialize;
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashMap;
+use std::env;
+use std::fs::{self, OpenOptions};
+use std::hash::{Hash, Hasher};
+use std::io::{self, Write};
+use std::os::unix::fs::OpenOptionsExt;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError, Sender};
+use std::sync::Mutex;
+use zbus::blocking::Connection;
+
+const KWIN: &str = "org.kde.KWin";
+
+// Where the scripts send their results: our unique bus name and this object.
+const RESULT_PATH: &str = "/io/github/MasaRyu/HotkeyLauncher";
+const RESULT_INTERFACE: &str = "io.github.MasaRyu.HotkeyLauncher.KWin";
+
+const SCRIPT: &str = include_str!("kwin_script.js");
+
+// KDE Plasma (Wayland) through KWin scripting: each request loads a short
+// script over D-Bus (org.kde.KWin /Scripting), runs it and waits for it to
+// call back with the result. KWin has no scratchpad; hiding minimises.
+pub struct KwinBackend {
+    // Bus address; None for the session bus
+    address: Option<String>,
+    hide_method: HideMethod,
+    // Where scripts are written for KWin to load: $XDG_RUNTIME_DIR
+    script_dir: Option<PathBuf>,
+    bus: Option<Bus>,
+    // From the last listing, until an action changes things
+    listed: Option<Vec<KwinWindow>>,
+    // KWin window ids are UUIDs; ours are a hash of one. Only the last
+    // listing's are kept, so this is as long as the window list.
+    ids: HashMap<u64, String>,
+}
+
+struct Bus {
+    conn: Connection,
+    results: Receiver<String>,
+    next_token: u64,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+struct KwinWindow {
+    id: String,
+    class: String,
+    title: String,
+    minimized: bool,
+    on_current_desktop: bool,
+    active: bool,
+    // 0 or -1 when unknown
+    #[serde(default)]
+    pid: i64,
+}
+
+impl KwinWindow {
+    // Our id for the window: stable while it exists, like the UUID it hashes
+    fn handle(&self) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        self.id.hash(&mut hasher);
+        hasher.finish()
+    }
+}
+
+#[derive(Deserialize)]
+struct Reply<T> {
+    token: u64,
+    value: T,
+}
+
+#[derive(Deserialize)]
+struct ActionResult {
+    gone: bool,
+}
+
+// The parameters placed in front of the script.
+struct Parameters<'a> {
+    service: &'a str,
+    token: u64,
+    action: &'a str,
+    window: &'a str,
+}
+
+struct Results {
+    sender: Mutex<Sender<String>>,
+}
+
+#[zbus::interface(name = "io.github.MasaRyu.HotkeyLauncher.KWin")]
+impl Results {
+    #[zbus(name = "Result")]
+    fn result(&self, json: String) {
+        let _ = self.sender.lock().unwrap().send(json);
+    }
+}
+
+impl KwinBackend {
+    pub fn new(hide_method: HideMethod) -> Self {
+        Self {
+            address: None,
+            hide_method,
+            script_dir: env::var_os("XDG_RUNTIME_DIR")
+                .filter(|dir| !dir.is_empty())
+                .map(PathBuf::from),
+            bus: None,
+            listed: None,
+            ids: HashMap::new(),
+        }
+    }
+
+    // A Plasma Wayland session; under X11 the X11 backend does the job.
+    pub fn probe() -> Probe {
+        if !current_desktop_is("KDE") {
+            Probe::none("XDG_CURRENT_DESKTOP does not list KDE")
+        } else if env::var_os("WAYLAND_DISPLAY").is_none() {
+            Probe::none("Plasma on X11; the x11 backend handles it")
+        } else {
+            Self::probe_kwin(None)
+        }
+    }
+
+    fn probe_kwin(address: Option<&str>) -> Probe {
+        match dbus::has_owner(address, KWIN) {
+            Ok(true) => Probe::new(90, "Plasma Wayland session, org.kde.KWin is on the bus"),
+            Ok(false) => Probe::new(3, "org.kde.KWin is not on the session bus"),
+            Err(_) => Probe::new(3, "cannot connect to the session bus"),
+        }
+    }
+
+    fn connect(&self) -> zbus::Result<Bus> {
+        let (sender, results) = mpsc::channel();
+        let conn = dbus::builder(self.address.as_deref())?
+            .serve_at(
+                RESULT_PATH,
+                Results {
+                    sender: Mutex::new(sender),
+                },
+            )?
+            .build()?;
+        Ok(Bus {
+            conn,
+            results,
+            next_token: 0,
+        })
+    }
+
+    fn run_script<T: for<'de> Deserialize<'de>>(
+        &mut self,
+        action: &str,
+        window: &str,
+    ) -> BackendResult<T> {
+        let Some(dir) = self.script_dir.clone() else {
+            return Err(BackendError::Protocol(
+                "XDG_RUNTIME_DIR is not set; nowhere to write the KWin script".into(),
+            ));
+        };
+        if self.bus.is_none() {
+            self.bus = Some(
+                self.connect()
+                    .map_err(|_| BackendError::DisplayUnavailable)?,
+            );
+        }
+        let bus = self.bus.as_mut().unwrap();
+        bus.next_token += 1;
+        let result = bus.run(&dir, action, window);
+        if matches!(result, Err(BackendError::DisplayUnavailable)) {
+            // KWin or the bus went away; reconnect next time
+            self.bus = None;
+        }
+        serde_json::from_str(&result?).map_err(|e| BackendError::Protocol(e.to_string()))
+    }
+
+    fn windows(&mut self) -> BackendResult<Vec<KwinWindow>> {
+        if let Some(listed) = &self.listed {
+            return Ok(listed.clone());
+        }
+        let listed: Vec<KwinWindow> = self.run_script("list", "")?;
+        self.ids = listed.iter().map(|w| (w.handle(), w.id.clone())).collect();
+        self.listed = Some(listed.clone());
+        Ok(listed)
+    }
+
+    fn window(&mut self, id: u64) -> BackendResult<KwinWindow> {
+        let uuid = self.uuid(id)?.to_string();
+        self.windows()?
+            .into_iter()
+            .find(|w| w.id == uuid)
+            .ok_or(BackendError::WindowGone(id))
+    }
+
+    fn uuid(&self, id: u64) -> BackendResult<&str> {
+        self.ids
+            .get(&id)
+            .map(String::as_str)
+            .ok_or(BackendError::WindowGone(id))
+    }
+
+    fn act(&mut self, action: &str, id: u64) -> BackendResult<()> {
+        let uuid = self.uuid(id)?.to_string();
+        self.listed = None;
+        let result: ActionResult = self.run_script(action, &uuid)?;
+        if result.gone {
+            return Err(BackendError::WindowGone(id));
+        }
+        Ok(())
+    }
+}
+
+impl Bus {
+    // Loads, runs and unloads one script; returns the JSON it sent back.
+    fn run(&mut self, dir: &Path, action: &str, window: &str) -> BackendResult<String> {
+        let token = self.next_token;
+        let service = self
+            .conn
+            .unique_name()
+            .map(|name| name.to_string())
+            .unwrap_or_default();
+        let source = script(&Parameters {
+            service: &service,
+            token,
+            action,
+            window,
+        });
+        let plugin = format!("alacritty-hotkey-launcher-{}-{token}", std::process::id());
+        let path = dir.join(format!("{plugin}.js"));
+        write_script(&path, &source)
+            .map_err(|e| BackendError::Protocol(format!("cannot write {}: {e}", path.display())))?;
+        let result = self.load_and_run(&path, &plugin, token);
+        let _ = self.call(
+            "/Scripting",
+            "org.kde.kwin.Scripting",
+            "unloadScript",
+            &(&plugin,),
+        );
+        let _ = fs::remove_file(&path);
+        result
+    }
+
+    fn load_and_run(&mut self, path: &Path, plugin: &str, token: u64) -> BackendResult<String> {
+        let id: i32 = self
+            .call(
+                "/Scripting",
+                "org.kde.kwin.Scripting",
+                "loadScript",
+                &(path.to_string_lossy().as_ref(), plugin),
+            )?
+            .body()
+            .deserialize()
+            .map_err(to_backend_error)?;
+        if id < 0 {
+            return Err(BackendError::Protocol(
+                "KWin did not load the script".into(),
+            ));
+        }
+        // Plasma 6 path, then Plasma 5
+        let script = format!("/Scripting/Script{id}");
+        if self
+            .call(&script, "org.kde.kwin.Script", "run", &())
+            .is_err()
+        {
+            self.call(&format!("/{id}"), "org.kde.kwin.Script", "run", &())?;
+        }
+        loop {
+            let json = match self.results.recv_timeout(REPLY_TIMEOUT) {
+                Ok(json) => json,
+                Err(RecvTimeoutError::Timeout) => {
+                    return Err(BackendError::Protocol("KWin script did not answer".into()))
+                }
+                Err(RecvTimeoutError::Disconnected) => {
+                    return Err(BackendError::DisplayUnavailable)
+                }
+            };
+            // Skip answers to earlier scripts that timed out
+            let reply: Reply<serde_json::Value> =
+                serde_json::from_str(&json).map_err(|e| BackendError::Protocol(e.to_string()))?;
+            if reply.token == token {
+                return Ok(reply.value.to_string());
+            }
+        }
+    }
+
+    fn call<B>(
+        &self,
+        path: &str,
+        interface: &str,
+        method: &str,
+        body: &B,
+    ) -> BackendResult<zbus::Message>
+    where
+        B: serde::Serialize + zbus::zvariant::DynamicType,
+    {
+        self.conn
+            .call_method(Some(KWIN), path, Some(interface), method, body)
+            .map_err(to_backend_error)
+    }
+}
+
+fn script(parameters: &Parameters) -> String {
+    let string = |s: &str| serde_json::to_string(s).unwrap();
+    format!(
+        "const SERVICE = {};\nconst PATH = {};\nconst INTERFACE = {};\n\
+         const TOKEN = {};\nconst ACTION = {};\nconst WINDOW = {};\n{SCRIPT}",
+        string(parameters.service),
+        string(RESULT_PATH),
+        string(RESULT_INTERFACE),
+        parameters.token,
+        string(parameters.action),
+        string(parameters.window),
+    )
+}
+
+// KWin runs whatever is in the file, so it must be ours: readable only by us,
+// and never an existing file or a symlink someone placed there.
+fn write_script(path: &Path, source: &str) -> io::Result<()> {
+    OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .mode(0o600)
+        .open(path)?
+        .write_all(source.as_bytes())
+}
+
+impl WindowBackend for KwinBackend {
+    fn list_windows(&mut self, app_name: &str) -> BackendResult<Vec<WindowInfo>> {
+        // A fresh listing per lookup; KWin tells us nothing in between
+        self.listed = None;
+        let windows = self.windows()?;
+        Ok(windows
+            .iter()
+            .filter(|w| matches_app(app_name, Some(&w.title), Some(&w.class)))
+            .map(|w| WindowInfo {
+                id: w.handle(),
+                class: Some(w.class.clone()),
+                title: Some(w.title.clone()),
+                workspace: None,
+                on_current_ws: w.on_current_desktop,
+                visible: !w.minimized,
+                focused: w.active,
+                pid: u32::try_from(w.pid).ok().filter(|&pid| pid > 0),
+            })
+            .collect())
+    }
+
+    fn is_on_current_workspace(&mut self, window: u64) -> BackendResult<bool> {
+        Ok(self.window(window)?.on_current_desktop)
+    }
+
+    fn is_visible(&mut self, window: u64) -> BackendResult<bool> {
+        Ok(!self.window(window)?.minimized)
+    }
+
+    fn move_to_current_workspace(&mut self, window: u64) -> BackendResult<()> {
+        self.act("move", window)
+    }
+
+    fn show(&mut self, window: u64) -> BackendResult<()> {
+        self.act("show", window)
+    }
+
+    fn hide(&mut self, window: u64) -> BackendResult<()> {
+        match self.hide_method {
+            HideMethod::None => Err(BackendError::Unsupported(
+                "hiding with wayland_hide_method = none",
+            )),
+            HideMethod::Auto | HideMethod::Scratchpad => self.act("minimize", window),
+        }
+    }
+
+    fn launch_app(&mut self, app_path: &str) -> BackendResult<u32> {
+        spawn_app(app_path)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::common_backend::test_support::{app, assert_display_unavailable, TempDir};
+    use crate::common_backend::{toggle_or_launch, Launched, Outcome};
+    use crate::dbus::tests::PrivateBus;
+    use serde::Serialize;
+    use std::sync::Arc;
+    use zbus::blocking::connection;
+
+    #[derive(Clone, Serialize)]
+    struct MockWindow {
+        id: String,
+        class: String,
+        title: String,
+        minimized: bool,
+        on_current_desktop: bool,
+        active: bool,
+    }
+
+    // Stands in for KWin: "runs" a loaded script by reading the parameters
+    // at its top and answering the way the script would.
+    struct MockScripting {
+        windows: Arc<Mutex<Vec<MockWindow>>>,
+        scripts: Arc<Mutex<Vec<String>>>,
+    }
+
+    struct MockScript {
+        source: String,
+        windows: Arc<Mutex<Vec<MockWindow>>>,
+    }
+
+    #[zbus::interface(name = "org.kde.kwin.Scripting")]
+    impl MockScripting {
+        #[zbus(name = "loadScript")]
+        async fn load_script(
+            &self,
+            path: String,
+            _plugin: String,
+            #[zbus(object_server)] server: &zbus::ObjectServer,
+        ) -> i32 {
+            let source = fs::read_to_string(path).unwrap();
+            let id = {
+                let mut scripts = self.scripts.lock().unwrap();
+                scripts.push(source.clone());
+                scripts.len() as i32
+            };
+            let script = MockScript {
+                source,
+                windows: self.windows.clone(),
+            };
+            server
+                .at(format!("/Scripting/Script{id}"), script)
+                .await
+                .unwrap();
+            id
+        }
+
+        #[zbus(name = "unloadScript")]
+        fn unload_script(&self, _plugin: String) -> bool {
+            true
+        }
+    }
+
+    #[zbus::interface(name = "org.kde.kwin.Script")]
+    impl MockScript {
+        #[zbus(name = "run")]
+        async fn run(&self, #[zbus(connection)] conn: &zbus::Connection) {
+            let parameter = |name: &str| -> serde_json::Value {
+                let prefix = format!("const {name} = ");
+                let line = self
+                    .source
+                    .lines()
+                    .find(|l| l.starts_with(&prefix))
+                    .unwrap();
+                serde_json::from_str(line[prefix.len()..].trim_end_matches(';')).unwrap()
+            };
+            let action = parameter("ACTION");
+            let value = {
+                let mut windows = self.windows.lock().unwrap();
+                if action == "list" {
+                    serde_json::to_value(&*windows).unwrap()
+                } else {
+                    let window = windows.iter_mut().find(|w| w.id == parameter("WINDOW"));
+                    let gone = window.is_none();
+                    if let Some(w) = window {
+                        match action.as_str().unwrap() {
+                            "minimize" => w.minimized = true,
+                            "show" => w.minimized = false,
+                            "move" => w.on_current_desktop = true,
+                            _ => {}
+                        }
+                    }
+                    serde_json::json!({ "gone": gone })
+                }
+            };
+            let reply = serde_json::json!({ "token": parameter("TOKEN"), "value": value });
+            conn.call_method(
+                parameter("SERVICE").as_str(),
+                RESULT_PATH,
+                Some(RESULT_INTERFACE),
+                "Result",
+                &(reply.to_string(),),
+            )
+            .await
+            .unwrap();
+        }
+    }
+
+    fn window(id: &str, class: &str, minimized: bool, on_current_desktop: bool) -> MockWindow {
+        MockWindow {
+            id: id.into(),
+            class: class.into(),
+            title: class.to_lowercase(),
+            minimized,
+            on_current_desktop,
+            active: false,
+        }
+    }
+
+    #[test]
+    fn toggles_through_kwin_scripts() {
+        let Some(bus) = PrivateBus::start() else {
+            eprintln!("dbus-daemon not found; skipping");
+            return;
+        };
+        let windows = Arc::new(Mutex::new(vec![
+            window("{a1}", "Alacritty", false, true),
+            window("{b2}", "org.kde.kate", true, false),
+        ]));
+        let scripts = Arc::new(Mutex::new(Vec::new()));
+        let _kwin = connection::Builder::address(bus.1.as_str())
+            .unwrap()
+            .name(KWIN)
+            .unwrap()
+            .serve_at(
+                "/Scripting",
+                MockScripting {
+                    windows: windows.clone(),
+                    scripts: scripts.clone(),
+                },
+            )
+            .unwrap()
+            .build()
+            .unwrap();
+
+        let dir = TempDir::new("kwin-toggle");
+        let mut backend = KwinBackend::new(HideMethod::Auto);
+        backend.address = Some(bus.1.clone());
+        backend.script_dir = Some(dir.to_path_buf());
+        assert_eq!(KwinBackend::probe_kwin(Some(&bus.1)).score, 90);
+
+        let listed = backend.list_windows("class=Alacritty").unwrap();
+        assert_eq!(listed.len(), 1);
+        assert!(listed[0].on_current_ws && listed[0].visible);
+        assert_eq!(
+            toggle_or_launch(&mut backend, &app("Alacritty"), &mut Launched::default()).unwrap(),
+            Outcome::Hidden(listed[0].id)
+        );
+        assert!(windows.lock().unwrap()[0].minimized);
+
+        let kate = backend.find_window("class=org.kde.kate").unwrap().unwrap();
+        assert_eq!(
+            toggle_or_launch(
+                &mut backend,
+                &app("class=org.kde.kate"),
+                &mut Launched::default()
+            )
+            .unwrap(),
+            Outcome::Moved(kate)
+        );
+        let kate_state = windows.lock().unwrap()[1].clone();
+        assert!(kate_state.on_current_desktop && !kate_state.minimized);
+
+        // Closed since it was listed
+        windows.lock().unwrap().remove(0);
+        assert!(matches!(
+            backend.show(listed[0].id),
+            Err(BackendError::WindowGone(_))
+        ));
+        // Ids of closed windows go with the next listing
+        backend.list_windows("Alacritty").unwrap();
+        assert_eq!(backend.ids.len(), 1);
+        assert!(scripts.lock().unwrap()[0].contains("const ACTION = \"list\";"));
+        // Each script is removed once it has run
+        assert_eq!(fs::read_dir(&*dir).unwrap().count(), 0);
+    }
+
+    #[test]
+    fn never_reuses_an_existing_script_file() {
+        let Some(bus) = PrivateBus::start() else {
+            eprintln!("dbus-daemon not found; skipping");
+            return;
+        };
+        let dir = TempDir::new("kwin-existing");
+        let planted = dir.join(format!(
+            "alacritty-hotkey-launcher-{}-1.js",
+            std::process::id()
+        ));
+        fs::write(&planted, "planted").unwrap();
+        let mut backend = KwinBackend::new(HideMethod::Auto);
+        backend.address = Some(bus.1.clone());
+        backend.script_dir = Some(dir.to_path_buf());
+        let error = backend.find_window("Alacritty").unwrap_err();
+        assert!(error.to_string().contains("cannot write"), "{error}");
+        assert_eq!(fs::read_to_string(&planted).unwrap(), "planted");
+    }
+
+    #[test]
+    fn missing_kwin_is_display_unavailable() {
+        let Some(bus) = PrivateBus::start() else {
+            eprintln!("dbus-daemon not found; skipping");
+            return;
+        };
+        let dir = TempDir::new("kwin-missing");
+        let mut backend = KwinBackend::new(HideMethod::Auto);
+        backend.address = Some(bus.1.clone());
+        backend.script_dir = Some(dir.to_path_buf());
+        assert_display_unavailable(&mut backend);
+        assert_eq!(KwinBackend::probe_kwin(Some(&bus.1)).score, 3);
+    }
+}
@@ -0,0 +1,49 @@
+// Run once per request by the kwin backend (src/kwin_backend.rs), which puts
+// SERVICE, PATH, INTERFACE, TOKEN, ACTION and WINDOW in front of this and
+// waits for reply() to call it back over D-Bus. Works with the Plasma 5
+// (clientList, desktop) and Plasma 6 (windowList, desktops) APIs.
+function reply(value) {
+    callDBus(SERVICE, PATH, INTERFACE, "Result", JSON.stringify({ token: TOKEN, value: value }));
+}
+
+const windows = workspace.windowList ? workspace.windowList() : workspace.clientList();
+const active = workspace.activeWindow !== undefined ? workspace.activeWindow : workspace.activeClient;
+const current = workspace.currentDesktop;
+
+function onCurrentDesktop(w) {
+    if (w.onAllDesktops) {
+        return true;
+    }
+    return w.desktops ? w.desktops.indexOf(current) >= 0 : w.desktop === current;
+}
+
+if (ACTION === "list") {
+    reply(windows.filter(w => w.normalWindow).map(w => ({
+        id: String(w.internalId),
+        class: String(w.resourceClass),
+        title: String(w.caption),
+        minimized: w.minimized,
+        on_current_desktop: onCurrentDesktop(w),
+        active: w === active,
+        pid: w.pid,
+    })));
+} else {
+    const w = windows.find(w => String(w.internalId) === WINDOW);
+    if (w && ACTION === "minimize") {
+        w.minimized = true;
+    } else if (w && ACTION === "show") {
+        w.minimized = false;
+        if (workspace.activeWindow !== undefined) {
+            workspace.activeWindow = w;
+        } else {
+            workspace.activeClient = w;
+        }
+    } else if (w && ACTION === "move") {
+        if (w.desktops) {
+            w.desktops = [current];
+        } else {
+            w.desktop = current;
+        }
+    }
+    reply({ gone: !w });
+}
@@ -1,72 +1,468 @@
+mod backends;
+mod cli;
 mod common_backend;
 mod config;
+mod config_path;
+mod control;
+mod dbus;
+mod gnome_backend;
+mod hyprland_backend;
+mod i3_backend;
+mod i3ipc;
+mod ipc;
+mod keys;
+mod kwin_backend;
+mod matching;
+mod niri_backend;
+mod reload;
+mod wayfire_backend;
 mod wayland_backend;
+mod wlr_backend;
 mod x11_backend;
+mod x11_errors;
 mod x11_ewmh;
+mod x11_tracker;
+#[cfg(feature = "x11rb")]
+mod x11rb_backend;
 
-use common_backend::{toggle_or_launch, AppConfig, DoublePressDetector};
-use rdev::{listen, Event, EventType, Key};
+use backends::BackendKind;
+use cli::{Command, Options};
+use common_backend::{run_action, toggle_or_launch, Action, Binding, Launched, WindowBackend};
+use config::{Config, ConfigErrorKind};
+use rdev::{listen, Event, EventType};
+use std::collections::HashMap;
 use std::env;
-use std::time::{Duration, Instant};
+use std::path::{Path, PathBuf};
+use std::process;
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::thread;
+use std::time::Instant;
 
 fn main() {
+    let options = match cli::parse(env::args().skip(1)) {
+        Ok(options) => options,
+        Err(error) => {
+            eprintln!("Error: {}", error);
+            eprint!("\n{}", cli::USAGE);
+            process::exit(2);
+        }
+    };
+
+    match &options.command {
+        Command::Run => run(&options),
+        Command::Toggle(name) => toggle(&options, name.as_deref()),
+        Command::CheckConfig => check_config(&options),
+        Command::ListWindows => list_windows(&options),
+        Command::Doctor => doctor(&options),
+        Command::Ctl(request) => ctl(&request.join(" ")),
+        Command::Help => print!("{}", cli::USAGE),
+    }
+}
+
+// --backend, then the config's `backend` setting; None means detect.
+fn chosen_backend(options: &Options, config: &Config) -> Option<(BackendKind, &'static str)> {
+    options
+        .backend
+        .map(|kind| (kind, "--backend"))
+        .or(config.backend.map(|kind| (kind, "config")))
+}
+
+fn make_backend(options: &Options, config: &Config) -> Box<dyn WindowBackend> {
+    let (kind, source) = chosen_backend(options, config)
+        .unwrap_or_else(|| (backends::best(&backends::probe_all()), "detected"));
+    if options.verbose {
+        println!("Using backend {} ({})", kind.name(), source);
+    }
+    match backends::create(kind, config) {
+        Ok(backend) => backend,
+        Err(error) => {
+            eprintln!("Error: {}", error);
+            process::exit(1);
+        }
+    }
+}
+
+// The file named by --config, else the first one found. Exits if a named file
+// is missing or a file cannot be read.
+fn locate_config(options: &Options) -> Option<config_path::ResolvedConfig> {
+    let found = match &options.config {
+        Some(path) => {
+            config_path::explicit(path.clone(), config_path::ConfigSource::CommandLine).map(Some)
+        }
+        None => config_path::resolve(),
+    };
+    match found {
+        Ok(found) => found,
+        Err(error) => {
+            eprintln!("Error: {}", error);
+            process::exit(1);
+        }
+    }
+}
+
+// The config and the file it came from, or built-in defaults if there is no
+// file. An invalid config is fatal unless --lenient.
+fn load_config(options: &Options) -> (Config, Option<PathBuf>) {
+    let Some(found) = locate_config(options) else {
+        println!("No config file found; using built-in defaults");
+        return (Config::default(), None);
+    };
+    println!("Using config {}", found);
+
+    // --lenient starts anyway, with invalid values replaced by defaults
+    if options.lenient {
+        let (config, problems) = config::load_from_file_lenient(&found.path);
+        report_config_errors(&found.path, &problems);
+        return (config, Some(found.path));
+    }
+    match config::load_from_file(&found.path) {
+        Ok(config) => (config, Some(found.path)),
+        Err(problems) => {
+            report_config_errors(&found.path, &problems);
+            eprintln!("Refusing to start with an invalid config; pass --lenient to start anyway");
+            process::exit(1);
+        }
+    }
+}
+
+fn run(options: &Options) {
+    let (config, config_file) = load_config(options);
+    let mut backend = make_backend(options, &config);
+
+    // Each binding gets its own detector; every key event is fed to all of them.
+    let mut bindings = build_bindings(config);
+
+    // rdev blocks inside listen, so it runs on its own thread and forwards
+    // events here. This lets the loop wake up for time-based triggers.
+    let (tx, rx) = mpsc::channel::<Message>();
+    let input_tx = tx.clone();
+    thread::spawn(move || {
+        if let Err(error) = listen(move |event| {
+            let _ = input_tx.send(Message::Input(event));
+        }) {
+            eprintln!("Error: {:?}", error);
+        }
+    });
+
+    // Reload when the config file changes or on SIGHUP
+    if let Some(path) = &config_file {
+        let watch_tx = tx.clone();
+        if let Err(error) = reload::watch_file(path, move || {
+            let _ = watch_tx.send(Message::Reload);
+        }) {
+            eprintln!("Cannot watch {}: {}", path.display(), error);
+        }
+    }
+    let hup_tx = tx.clone();
+    if let Err(error) = reload::on_sighup(move || {
+        let _ = hup_tx.send(Message::Reload);
+    }) {
+        eprintln!("Cannot install SIGHUP handler: {}", error);
+    }
+
+    // Requests from the control socket run on this thread, like key triggers
+    let control_tx = tx.clone();
+    let _server = control::socket_path().and_then(|path| {
+        let server = control::serve(&path, move |request| {
+            let (reply_tx, reply_rx) = mpsc::channel();
+            let _ = control_tx.send(Message::Control(request, reply_tx));
+            reply_rx
+                .recv()
+                .unwrap_or_else(|_| control::Response::error("daemon stopped"))
+        });
+        match server {
+            Ok(server) => {
+                println!("Control socket {}", path.display());
+                Some(server)
+            }
+            Err(error) => {
+                eprintln!("Control socket {} unavailable: {}", path.display(), error);
+                None
+            }
+        }
+    });
+    drop(tx);
+
     println!("Hotkey listener started");
+    loop {
+        let deadline = bindings
+            .iter()
+            .filter_map(|b| b.detector.next_deadline())
+            .min();
+        let message = match deadline {
+            Some(deadline) => {
+                match rx.recv_timeout(deadline.saturating_duration_since(Instant::now())) {
+                    Ok(message) => Some(message),
+                    Err(RecvTimeoutError::Timeout) => None,
+                    Err(RecvTimeoutError::Disconnected) => break,
+                }
+            }
+            None => match rx.recv() {
+                Ok(message) => Some(message),
+                Err(_) => break,
+            },
+        };
+
+        let now = Instant::now();
+        for binding in bindings.iter_mut() {
+            if let Some(action) = binding.detector.poll(now) {
+                fire(&mut *backend, binding, action, options.verbose);
+            }
+        }
+        match message {
+            Some(Message::Input(event)) => {
+                handle_event(event, &mut bindings, &mut *backend, now, options.verbose)
+            }
+            Some(Message::Reload) => {
+                let _ = reload_config(config_file.as_deref(), options.lenient, &mut bindings);
+            }
+            Some(Message::Control(control::Request::Quit, _)) => {
+                println!("Quit requested");
+                break;
+            }
+            Some(Message::Control(control::Request::Reload, reply)) => {
+                let response =
+                    match reload_config(config_file.as_deref(), options.lenient, &mut bindings) {
+                        Ok(()) => control::Response::ok("reloaded"),
+                        Err(error) => control::Response::error(error),
+                    };
+                let _ = reply.send(response);
+            }
+            Some(Message::Control(request, reply)) => {
+                if options.verbose {
+                    println!("Control request: {:?}", request);
+                }
+                let _ = reply.send(control::execute(&request, &mut bindings, &mut *backend));
+            }
+            None => {}
+        }
+    }
+}
 
-    // Decide backend: prefer X11 if DISPLAY is available (works under Xwayland too)
-    let backend_kind = if env::var_os("DISPLAY").is_some() {
-        BackendKind::X11
-    } else if env::var_os("WAYLAND_DISPLAY").is_some() {
-        BackendKind::Wayland
-    } else {
-        BackendKind::X11 // default fallback
+// One-shot toggle of a binding's app, without listening for keys.
+fn toggle(options: &Options, name: Option<&str>) {
+    let (config, _) = load_config(options);
+    let app = match name {
+        Some(name) => config.bindings.iter().find(|b| b.name == name),
+        None => config.bindings.first(),
     };
+    let Some(app) = app else {
+        eprintln!("Error: no binding named '{}'", name.unwrap_or_default());
+        process::exit(1);
+    };
+    let mut backend = make_backend(options, &config);
+    if options.verbose {
+        println!("Toggling '{}' ({})", app.name, app.app_name);
+    }
+    match toggle_or_launch(&mut *backend, app, &mut Launched::default()) {
+        Ok(outcome) => {
+            if options.verbose {
+                println!("Binding '{}': {}", app.name, outcome);
+            }
+        }
+        Err(error) => {
+            eprintln!("Error: cannot toggle '{}': {}", app.name, error);
+            process::exit(1);
+        }
+    }
+}
 
-    let mut backend: Box<dyn common_backend::WindowBackend> = match backend_kind {
-        BackendKind::X11 => Box::new(x11_backend::X11Backend::new()),
-        BackendKind::Wayland => Box::new(wayland_backend::WaylandBackend::new()),
+// Validates the config, including app paths, and prints it with every
+// inherited and default value filled in.
+fn check_config(options: &Options) {
+    let Some(found) = locate_config(options) else {
+        println!("# No config file found; built-in defaults");
+        print!("{}", Config::default());
+        return;
     };
+    match config::load_from_file(&found.path) {
+        Ok(config) => {
+            println!("# {}", found);
+            print!("{}", config);
+        }
+        Err(problems) => {
+            report_config_errors(&found.path, &problems);
+            eprintln!("{} problem(s) found", problems.len());
+            process::exit(1);
+        }
+    }
+}
+
+// Per binding, every window its app_name matches. The one marked '*' is what
+// a toggle would act on, unless the daemon launched one of the others.
+fn list_windows(options: &Options) {
+    let (config, _) = load_config(options);
+    let mut backend = make_backend(options, &config);
+    for app in &config.bindings {
+        println!("Binding '{}' ({}):", app.name, app.app_name);
+        let windows = match backend.list_windows(&app.app_name) {
+            Ok(windows) => windows,
+            Err(error) => {
+                println!("  error: {}", error);
+                continue;
+            }
+        };
+        if windows.is_empty() {
+            println!("  no matching windows");
+            continue;
+        }
+        let preferred = backend.find_window(&app.app_name).ok().flatten();
+        for w in windows {
+            println!(
+                "  {} 0x{:08x}  workspace {}{}  {}{}  class={}  pid={}  title={:?}",
+                if Some(w.id) == preferred { '*' } else { ' ' },
+                w.id,
+                w.workspace.as_deref().unwrap_or("?"),
+                if w.on_current_ws { " (current)" } else { "" },
+                if w.visible { "visible" } else { "hidden" },
+                if w.focused { ", focused" } else { "" },
+                w.class.as_deref().unwrap_or("?"),
+                w.pid.map_or("?".to_string(), |pid| pid.to_string()),
+                w.title.as_deref().unwrap_or(""),
+            );
+        }
+    }
+}
 
-    // Load config from file if present; fall back to defaults
-    let config_path = env::var("ALACRITTY_HOTKEY_LAUNCHER_CONFIG")
-        .ok()
-        .unwrap_or_else(|| "src/config.toml".to_string());
-    let config = config::load_from_file(&config_path).unwrap_or_else(|| AppConfig {
-        double_press_interval: Duration::from_millis(300),
-        app_path: "/usr/local/bin/alacritty".to_string(),
-        app_name: "class=Alacritty".to_string(),
-        detect_key: Key::ControlLeft,
+// What every backend's probe finds, and the backend that would be used.
+fn doctor(options: &Options) {
+    // Config problems are reported but do not stop the diagnosis
+    let (config, _) = load_config(&Options {
+        lenient: true,
+        ..options.clone()
     });
+    let probes = backends::probe_all();
+    println!("Backend probes (the highest score is used):");
+    for (kind, probe) in &probes {
+        println!("  {:<9} {:>3}  {}", kind.name(), probe.score, probe.reason);
+    }
+    let (kind, source) =
+        chosen_backend(options, &config).unwrap_or((backends::best(&probes), "detected"));
+    println!("Backend: {} ({})", kind.name(), source);
+}
+
+// Sends one request to the running daemon and prints the answer.
+fn ctl(request: &str) {
+    let Some(path) = control::socket_path() else {
+        eprintln!("Error: XDG_RUNTIME_DIR is not set");
+        process::exit(1);
+    };
+    match control::send(&path, request) {
+        Ok(response) => {
+            println!("{}", response);
+            if response.starts_with("error:") || response.contains(r#""ok":false"#) {
+                process::exit(1);
+            }
+        }
+        Err(error) => {
+            eprintln!(
+                "Error: cannot reach the daemon at {}: {}",
+                path.display(),
+                error
+            );
+            process::exit(1);
+        }
+    }
+}
 
-    let mut detector = DoublePressDetector::new(config.double_press_interval, config.detect_key);
+// Everything the main loop reacts to.
+enum Message {
+    Input(Event),
+    Reload,
+    // A control socket request and where to send the answer
+    Control(control::Request, mpsc::Sender<control::Response>),
+}
 
-    if let Err(error) =
-        listen(move |event| handle_event(event, &mut detector, &mut *backend, &config))
-    {
-        eprintln!("Error: {:?}", error);
+fn build_bindings(config: Config) -> Vec<Binding> {
+    let bindings: Vec<Binding> = config.bindings.into_iter().map(Binding::new).collect();
+    for binding in &bindings {
+        println!(
+            "Binding '{}': {} -> {}",
+            binding.config.name, binding.config.trigger, binding.config.app_name
+        );
     }
+    bindings
 }
 
-#[derive(Copy, Clone)]
-enum BackendKind {
-    X11,
-    Wayland,
+// Swaps in the new bindings only if the whole file is valid, or with
+// --lenient, as at startup, if it could at least be read and parsed. Detectors
+// are rebuilt, so a tap sequence in progress is dropped; the processes a
+// binding launched stay with the binding of the same name.
+fn reload_config(
+    path: Option<&Path>,
+    lenient: bool,
+    bindings: &mut Vec<Binding>,
+) -> Result<(), String> {
+    let Some(path) = path else {
+        println!("No config file to reload");
+        return Err("no config file to reload".to_string());
+    };
+    let (config, problems) = config::load_from_file_lenient(path);
+    report_config_errors(path, &problems);
+    let unreadable = problems
+        .iter()
+        .any(|p| matches!(p.kind, ConfigErrorKind::Io | ConfigErrorKind::Syntax));
+    if !problems.is_empty() && (!lenient || unreadable) {
+        eprintln!("Keeping the previous config");
+        return Err(format!(
+            "{} problem(s) in {}; keeping the previous config",
+            problems.len(),
+            path.display()
+        ));
+    }
+    println!("Reloaded config {}", path.display());
+    let mut launched: HashMap<String, Launched> = bindings
+        .drain(..)
+        .map(|b| (b.config.name, b.launched))
+        .collect();
+    *bindings = build_bindings(config);
+    for binding in bindings.iter_mut() {
+        if let Some(pids) = launched.remove(&binding.config.name) {
+            binding.launched = pids;
+        }
+    }
+    Ok(())
+}
+
+fn report_config_errors(path: &Path, problems: &[config::ConfigError]) {
+    for problem in problems {
+        eprintln!("{}: {}", path.display(), problem);
+    }
+}
+
+fn fire(backend: &mut dyn WindowBackend, binding: &mut Binding, action: Action, verbose: bool) {
+    match run_action(backend, &binding.config, &mut binding.launched, action) {
+        Ok(outcome) => {
+            if verbose {
+                println!("Binding '{}': {}: {}", binding.config.name, action, outcome);
+            }
+        }
+        Err(error) => eprintln!(
+            "Binding '{}': {} failed: {}",
+            binding.config.name, action, error
+        ),
+    }
 }
 
 fn handle_event(
     event: Event,
-    detector: &mut DoublePressDetector,
-    backend: &mut dyn common_backend::WindowBackend,
-    config: &AppConfig,
+    bindings: &mut [Binding],
+    backend: &mut dyn WindowBackend,
+    now: Instant,
+    verbose: bool,
 ) {
-    match event.event_type {
-        EventType::KeyPress(key) => {
-            if detector.on_key_press(key, Instant::now()) {
-                toggle_or_launch(backend, config);
+    for binding in bindings.iter_mut() {
+        let fired = match event.event_type {
+            EventType::KeyPress(key) => binding.detector.on_key_press(key, now),
+            EventType::KeyRelease(key) => binding.detector.on_key_release(key, now),
+            EventType::ButtonPress(_) => {
+                binding.detector.on_button_press(now);
+                None
             }
+            _ => None,
+        };
+        if let Some(action) = fired {
+            fire(backend, binding, action, verbose);
         }
-        EventType::KeyRelease(key) => {
-            detector.on_key_release(key, Instant::now());
-        }
-        _ => {}
     }
 }
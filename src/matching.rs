@@ -0,0 +1,168 @@
+// Which windows an app_name selects, and which of several to act on. Shared
+// by all backends.
+
+pub fn matches_app(target: &str, title: Option<&str>, wm_class: Option<&str>) -> bool {
+    let t = target.trim();
+    if t.is_empty() {
+        return false;
+    }
+
+    // Optional explicit prefixes
+    let (mode, pat) = if let Some(rest) = t.strip_prefix("class=") {
+        ("class_eq", rest)
+    } else if let Some(rest) = t.strip_prefix("title=") {
+        ("title_eq", rest)
+    } else if let Some(rest) = t.strip_prefix("title_contains=") {
+        ("title_contains", rest)
+    } else {
+        ("default", t)
+    };
+
+    let p = pat.to_ascii_lowercase();
+    match mode {
+        "class_eq" => wm_class
+            .map(|s| s.eq_ignore_ascii_case(pat))
+            .unwrap_or(false),
+        "title_eq" => title.map(|s| s.eq_ignore_ascii_case(pat)).unwrap_or(false),
+        "title_contains" => title
+            .map(|s| s.to_ascii_lowercase().contains(&p))
+            .unwrap_or(false),
+        _ => {
+            // Default: if WM_CLASS is present, require exact match (case-insensitive).
+            // Fallback to title contains only when class is unavailable.
+            if let Some(cls) = wm_class {
+                if cls.eq_ignore_ascii_case(pat) {
+                    return true;
+                }
+            }
+            if wm_class.is_none() {
+                if let Some(ti) = title {
+                    return ti.to_ascii_lowercase().contains(&p);
+                }
+            }
+            false
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Candidate {
+    pub window: u64,
+    pub on_current_ws: bool,
+    pub visible: bool,
+}
+
+// Selection policy: prefer on-current-workspace and visible; then on-current-workspace hidden;
+// then any visible; finally any.
+pub fn select_preferred_window(candidates: &[Candidate]) -> Option<u64> {
+    if candidates.is_empty() {
+        return None;
+    }
+    if let Some(c) = candidates.iter().find(|c| c.on_current_ws && c.visible) {
+        return Some(c.window);
+    }
+    if let Some(c) = candidates.iter().find(|c| c.on_current_ws && !c.visible) {
+        return Some(c.window);
+    }
+    if let Some(c) = candidates.iter().find(|c| !c.on_current_ws && c.visible) {
+        return Some(c.window);
+    }
+    Some(candidates[0].window)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn app_match_by_title_or_class() {
+        assert!(matches_app("Alacritty", Some("Terminal — Alacritty"), None));
+        assert!(matches_app("alacritty", None, Some("Alacritty")));
+        assert!(!matches_app("Alacritty", Some("Other"), Some("OtherApp")));
+    }
+
+    #[test]
+    fn app_match_explicit_modes() {
+        // class equals
+        assert!(matches_app("class=Alacritty", None, Some("alacritty")));
+        assert!(!matches_app("class=Alacritty", None, Some("org.alacritty")));
+        // title equals
+        assert!(matches_app("title=MyTerm", Some("myterm"), None));
+        assert!(!matches_app("title=MyTerm", Some("Other MyTerm!"), None));
+        // title contains
+        assert!(matches_app(
+            "title_contains=MyTerm",
+            Some("Other MyTerm!"),
+            None
+        ));
+    }
+
+    #[test]
+    fn selection_prefers_current_visible_then_current_hidden() {
+        let cands = vec![
+            Candidate {
+                window: 10,
+                on_current_ws: false,
+                visible: true,
+            },
+            Candidate {
+                window: 11,
+                on_current_ws: true,
+                visible: false,
+            },
+            Candidate {
+                window: 12,
+                on_current_ws: true,
+                visible: true,
+            },
+        ];
+        // Should pick 12 (current & visible)
+        assert_eq!(select_preferred_window(&cands), Some(12));
+
+        let cands2 = vec![
+            Candidate {
+                window: 20,
+                on_current_ws: false,
+                visible: true,
+            },
+            Candidate {
+                window: 21,
+                on_current_ws: true,
+                visible: false,
+            },
+        ];
+        // No current-visible: pick 21 (current & hidden)
+        assert_eq!(select_preferred_window(&cands2), Some(21));
+    }
+
+    #[test]
+    fn selection_falls_back_to_visible_then_any() {
+        let cands = vec![
+            Candidate {
+                window: 30,
+                on_current_ws: false,
+                visible: true,
+            },
+            Candidate {
+                window: 31,
+                on_current_ws: false,
+                visible: false,
+            },
+        ];
+        assert_eq!(select_preferred_window(&cands), Some(30));
+
+        let cands2 = vec![
+            Candidate {
+                window: 40,
+                on_current_ws: false,
+                visible: false,
+            },
+            Candidate {
+                window: 41,
+                on_current_ws: false,
+                visible: false,
+            },
+        ];
+        assert_eq!(select_preferred_window(&cands2), Some(40));
+    }
+}
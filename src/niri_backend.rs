@@ -0,0 +1,350 @@
+use crate::common_backend::{
+    spawn_app, BackendError, BackendResult, HideMethod, Probe, WindowBackend, WindowInfo,
+};
+use crate::ipc;
+use crate::matching::matches_app;
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::env;
+use std::io::{self, BufRead, BufReader, Write};
+use std::path::PathBuf;
+
+// niri through its IPC socket: a connection carries one JSON request line,
+// answered by one line of {"Ok": ...} or {"Err": "..."}. niri has no
+// minimizing, so hiding moves the window to a named "stash" workspace
+// (niri_stash_workspace), which must be declared in the niri config.
+pub struct NiriBackend {
+    socket: PathBuf,
+    hide_method: HideMethod,
+    stash: String,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+struct Window {
+    id: u64,
+    #[serde(default)]
+    title: Option<String>,
+    #[serde(default)]
+    app_id: Option<String>,
+    #[serde(default)]
+    workspace_id: Option<u64>,
+    #[serde(default)]
+    is_focused: bool,
+    #[serde(default)]
+    pid: Option<u32>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+struct Workspace {
+    id: u64,
+    #[serde(default)]
+    name: Option<String>,
+    // Shown on its output
+    #[serde(default)]
+    is_active: bool,
+    #[serde(default)]
+    is_focused: bool,
+}
+
+impl NiriBackend {
+    pub fn new(socket: PathBuf, hide_method: HideMethod, stash: String) -> Self {
+        Self {
+            socket,
+            hide_method,
+            stash,
+        }
+    }
+
+    pub fn socket() -> Option<PathBuf> {
+        env::var_os("NIRI_SOCKET").map(PathBuf::from)
+    }
+
+    pub fn probe() -> Probe {
+        Probe::socket("NIRI_SOCKET", Self::socket().as_deref())
+    }
+
+    fn request(&self, request: Value) -> BackendResult<Value> {
+        let mut stream =
+            ipc::connect(&self.socket).map_err(|_| BackendError::DisplayUnavailable)?;
+        let io = |e: io::Error| BackendError::Protocol(e.to_string());
+        stream
+            .write_all(format!("{request}\n").as_bytes())
+            .map_err(io)?;
+        let mut line = String::new();
+        BufReader::new(stream).read_line(&mut line).map_err(io)?;
+        let reply: Value =
+            serde_json::from_str(&line).map_err(|e| BackendError::Protocol(e.to_string()))?;
+        match (reply.get("Ok"), reply.get("Err")) {
+            (Some(value), _) => Ok(value.clone()),
+            (None, Some(error)) => Err(BackendError::Protocol(
+                error.as_str().unwrap_or_default().to_string(),
+            )),
+            (None, None) => Err(BackendError::Protocol(
+                "reply is neither Ok nor Err".to_string(),
+            )),
+        }
+    }
+
+    // Requests without arguments are plain strings, e.g. "Windows"; the
+    // reply is {"Windows": [...]}.
+    fn query<T: for<'de> Deserialize<'de>>(&self, name: &str) -> BackendResult<T> {
+        let mut reply = self.request(json!(name))?;
+        serde_json::from_value(reply[name].take())
+            .map_err(|e| BackendError::Protocol(format!("{name}: {e}")))
+    }
+
+    fn action(&self, action: Value) -> BackendResult<()> {
+        self.request(json!({ "Action": action })).map(|_| ())
+    }
+
+    fn windows(&mut self) -> BackendResult<Vec<Window>> {
+        self.query("Windows")
+    }
+
+    fn window(&mut self, id: u64) -> BackendResult<Window> {
+        self.windows()?
+            .into_iter()
+            .find(|w| w.id == id)
+            .ok_or(BackendError::WindowGone(id))
+    }
+
+    fn workspaces(&mut self) -> BackendResult<Vec<Workspace>> {
+        self.query("Workspaces")
+    }
+
+    fn focused_workspace(&mut self) -> BackendResult<Workspace> {
+        self.workspaces()?
+            .into_iter()
+            .find(|w| w.is_focused)
+            .ok_or_else(|| BackendError::Protocol("no focused workspace".to_string()))
+    }
+
+    fn state(&self, window: &Window, workspaces: &[Workspace]) -> (bool, bool) {
+        let workspace = workspaces
+            .iter()
+            .find(|w| Some(w.id) == window.workspace_id);
+        let on_current = workspace.is_some_and(|w| w.is_focused);
+        let stashed = workspace.is_some_and(|w| w.name.as_deref() == Some(self.stash.as_str()));
+        let visible = workspace.is_some_and(|w| w.is_active) && !stashed;
+        (on_current, visible)
+    }
+
+    fn move_to(&mut self, window: u64, reference: Value) -> BackendResult<()> {
+        self.window(window)?;
+        self.action(json!({ "MoveWindowToWorkspace": {
+            "window_id": window,
+            "reference": reference,
+            "focus": false,
+        }}))
+    }
+}
+
+impl WindowBackend for NiriBackend {
+    fn list_windows(&mut self, app_name: &str) -> BackendResult<Vec<WindowInfo>> {
+        let workspaces = self.workspaces()?;
+        Ok(self
+            .windows()?
+            .into_iter()
+            .filter(|w| matches_app(app_name, w.title.as_deref(), w.app_id.as_deref()))
+            .map(|w| {
+                let (on_current_ws, visible) = self.state(&w, &workspaces);
+                let workspace = workspaces
+                    .iter()
+                    .find(|ws| Some(ws.id) == w.workspace_id)
+                    .and_then(|ws| ws.name.clone());
+                WindowInfo {
+                    id: w.id,
+                    class: w.app_id,
+                    title: w.title,
+                    workspace,
+                    on_current_ws,
+                    visible,
+                    focused: w.is_focused,
+                    pid: w.pid,
+                }
+            })
+            .collect())
+    }
+
+    fn is_on_current_workspace(&mut self, window: u64) -> BackendResult<bool> {
+        let workspaces = self.workspaces()?;
+        let window = self.window(window)?;
+        Ok(self.state(&window, &workspaces).0)
+    }
+
+    fn is_visible(&mut self, window: u64) -> BackendResult<bool> {
+        let workspaces = self.workspaces()?;
+        let window = self.window(window)?;
+        Ok(self.state(&window, &workspaces).1)
+    }
+
+    fn move_to_current_workspace(&mut self, window: u64) -> BackendResult<()> {
+        let focused = self.focused_workspace()?;
+        self.move_to(window, json!({ "Id": focused.id }))
+    }
+
+    fn show(&mut self, window: u64) -> BackendResult<()> {
+        // A stashed window shows up on the focused workspace
+        let workspaces = self.workspaces()?;
+        let stashed = self.window(window)?.workspace_id.is_some_and(|id| {
+            workspaces
+                .iter()
+                .any(|w| w.id == id && w.name.as_deref() == Some(self.stash.as_str()))
+        });
+        if stashed {
+            self.move_to_current_workspace(window)?;
+        }
+        self.action(json!({ "FocusWindow": { "id": window } }))
+    }
+
+    fn hide(&mut self, window: u64) -> BackendResult<()> {
+        match self.hide_method {
+            HideMethod::None => Err(BackendError::Unsupported(
+                "hiding with wayland_hide_method = none",
+            )),
+            HideMethod::Auto | HideMethod::Scratchpad => {
+                let stash = self.stash.clone();
+                self.move_to(window, json!({ "Name": stash }))
+            }
+        }
+    }
+
+    fn launch_app(&mut self, app_path: &str) -> BackendResult<u32> {
+        spawn_app(app_path)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::common_backend::test_support::{app, assert_display_unavailable, TempDir};
+    use crate::common_backend::{toggle_or_launch, Launched, Outcome};
+    use std::os::unix::net::UnixListener;
+    use std::sync::{Arc, Mutex};
+    use std::thread;
+
+    // niri 25.x replies, trimmed. Workspace 1 is focused; "stash" is a named
+    // workspace on the same output.
+    const WINDOWS: &str = r#"{"Ok":{"Windows":[
+        {"id":12,"title":"~","app_id":"Alacritty","pid":4242,"workspace_id":1,
+         "is_focused":true,"is_floating":false,"is_urgent":false},
+        {"id":15,"title":"notes","app_id":"org.gnome.TextEditor","pid":4243,"workspace_id":3,
+         "is_focused":false,"is_floating":false,"is_urgent":false}
+    ]}}"#;
+    const WORKSPACES: &str = r#"{"Ok":{"Workspaces":[
+        {"id":1,"idx":1,"name":null,"output":"eDP-1","is_urgent":false,"is_active":true,
+         "is_focused":true,"active_window_id":12},
+        {"id":2,"idx":2,"name":null,"output":"eDP-1","is_urgent":false,"is_active":false,
+         "is_focused":false,"active_window_id":null},
+        {"id":3,"idx":3,"name":"stash","output":"eDP-1","is_urgent":false,"is_active":false,
+         "is_focused":false,"active_window_id":15}
+    ]}}"#;
+
+    // Answers one request per connection, as niri does, and records it.
+    struct FakeNiri {
+        dir: TempDir,
+        requests: Arc<Mutex<Vec<Value>>>,
+    }
+
+    impl FakeNiri {
+        fn start(name: &str) -> Self {
+            let dir = TempDir::new(name);
+            let listener = UnixListener::bind(dir.join("niri.sock")).unwrap();
+            let requests = Arc::new(Mutex::new(Vec::new()));
+            let recorded = requests.clone();
+            thread::spawn(move || {
+                for stream in listener.incoming() {
+                    let Ok(mut stream) = stream else { return };
+                    let mut line = String::new();
+                    BufReader::new(&stream).read_line(&mut line).unwrap();
+                    let request: Value = serde_json::from_str(&line).unwrap();
+                    let reply = match &request {
+                        Value::String(s) if s == "Windows" => WINDOWS,
+                        Value::String(s) if s == "Workspaces" => WORKSPACES,
+                        Value::Object(o) if o.contains_key("Action") => r#"{"Ok":"Handled"}"#,
+                        _ => r#"{"Err":"error parsing request"}"#,
+                    };
+                    recorded.lock().unwrap().push(request);
+                    // Replies are single lines
+                    let reply: Value = serde_json::from_str(reply).unwrap();
+                    let _ = stream.write_all(format!("{reply}\n").as_bytes());
+                }
+            });
+            Self { dir, requests }
+        }
+
+        fn actions(&self) -> Vec<Value> {
+            self.requests
+                .lock()
+                .unwrap()
+                .iter()
+                .filter_map(|r| r.get("Action").cloned())
+                .collect()
+        }
+    }
+
+    #[test]
+    fn stashes_on_a_named_workspace() {
+        let niri = FakeNiri::start("niri");
+        let mut backend = NiriBackend::new(
+            niri.dir.join("niri.sock"),
+            HideMethod::Auto,
+            "stash".to_string(),
+        );
+
+        let listed = backend.list_windows("class=Alacritty").unwrap();
+        assert!(listed[0].on_current_ws && listed[0].visible && listed[0].focused);
+        let notes = &backend.list_windows("title=notes").unwrap()[0];
+        assert!(!notes.on_current_ws && !notes.visible);
+        assert_eq!(notes.workspace.as_deref(), Some("stash"));
+
+        assert_eq!(
+            toggle_or_launch(&mut backend, &app("Alacritty"), &mut Launched::default()).unwrap(),
+            Outcome::Hidden(12)
+        );
+        assert_eq!(
+            toggle_or_launch(&mut backend, &app("title=notes"), &mut Launched::default()).unwrap(),
+            Outcome::Moved(15)
+        );
+        let moved_here = json!({ "MoveWindowToWorkspace": {
+            "window_id": 15, "reference": { "Id": 1 }, "focus": false,
+        }});
+        assert_eq!(
+            niri.actions(),
+            [
+                json!({ "MoveWindowToWorkspace": {
+                    "window_id": 12, "reference": { "Name": "stash" }, "focus": false,
+                }}),
+                moved_here.clone(),
+                // The replayed state still has it stashed
+                moved_here,
+                json!({ "FocusWindow": { "id": 15 } }),
+            ]
+        );
+    }
+
+    #[test]
+    fn reports_rejected_requests_and_gone_windows() {
+        let niri = FakeNiri::start("niri-errors");
+        let mut backend = NiriBackend::new(
+            niri.dir.join("niri.sock"),
+            HideMethod::Auto,
+            "stash".to_string(),
+        );
+        assert!(matches!(
+            backend.query::<Value>("Bogus"),
+            Err(BackendError::Protocol(e)) if e == "error parsing request"
+        ));
+        assert!(matches!(
+            backend.hide(99),
+            Err(BackendError::WindowGone(99))
+        ));
+
+        let mut missing = NiriBackend::new(
+            PathBuf::from("/nonexistent/niri.sock"),
+            HideMethod::Auto,
+            "stash".into(),
+        );
+        assert_display_unavailable(&mut missing);
+    }
+}
@@ -0,0 +1,97 @@
+// Config reload triggers: changes to the config file (inotify) and SIGHUP.
+// Both run on their own thread and only notify; the main loop does the reload.
+use inotify::{Inotify, WatchMask};
+use signal_hook::consts::SIGHUP;
+use signal_hook::iterator::Signals;
+use std::io;
+use std::path::Path;
+use std::thread;
+
+// Watches the directory rather than the file itself: editors commonly save by
+// writing a new file and renaming it over the old one, which would leave a
+// watch on the file pointing at the deleted inode.
+pub fn watch_file(path: &Path, notify: impl Fn() + Send + 'static) -> io::Result<()> {
+    let dir = match path.parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => dir,
+        _ => Path::new("."),
+    };
+    let file_name = path
+        .file_name()
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "not a file path"))?
+        .to_owned();
+
+    let mut inotify = Inotify::init()?;
+    inotify.watches().add(
+        dir,
+        WatchMask::CLOSE_WRITE | WatchMask::MOVED_TO | WatchMask::CREATE,
+    )?;
+
+    thread::spawn(move || {
+        let mut buffer = [0u8; 4096];
+        loop {
+            let events = match inotify.read_events_blocking(&mut buffer) {
+                Ok(events) => events,
+                Err(error) => {
+                    eprintln!("Config watch stopped: {}", error);
+                    return;
+                }
+            };
+            // One notification per batch; a save often produces several events
+            if events
+                .into_iter()
+                .any(|event| event.name == Some(file_name.as_os_str()))
+            {
+                notify();
+            }
+        }
+    });
+    Ok(())
+}
+
+pub fn on_sighup(notify: impl Fn() + Send + 'static) -> io::Result<()> {
+    let mut signals = Signals::new([SIGHUP])?;
+    thread::spawn(move || {
+        for _ in signals.forever() {
+            notify();
+        }
+    });
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use std::sync::mpsc;
+    use std::time::Duration;
+
+    #[test]
+    fn notifies_on_write_and_replace() {
+        let dir = std::env::temp_dir().join(format!("ahl-reload-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("config.toml");
+        fs::write(&path, "[settings]\n").unwrap();
+
+        let (tx, rx) = mpsc::channel();
+        watch_file(&path, move || {
+            let _ = tx.send(());
+        })
+        .unwrap();
+        let wait = || rx.recv_timeout(Duration::from_secs(2));
+
+        // Other files in the directory are ignored
+        fs::write(dir.join("other.toml"), "").unwrap();
+        assert!(rx.recv_timeout(Duration::from_millis(200)).is_err());
+
+        fs::write(&path, "[settings]\ninterval = 250\n").unwrap();
+        assert!(wait().is_ok());
+        while rx.recv_timeout(Duration::from_millis(100)).is_ok() {}
+
+        // Write-then-rename, as most editors save
+        let tmp = dir.join("config.toml.tmp");
+        fs::write(&tmp, "[settings]\ninterval = 350\n").unwrap();
+        fs::rename(&tmp, &path).unwrap();
+        assert!(wait().is_ok());
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
@@ -0,0 +1,376 @@
+use crate::common_backend::{
+    spawn_app, BackendError, BackendResult, HideMethod, Probe, WindowBackend, WindowInfo,
+};
+use crate::ipc::{self, Reconnecting, RequestError};
+use crate::matching::matches_app;
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::env;
+use std::io::{self, Read, Write};
+use std::os::unix::net::UnixStream;
+use std::path::{Path, PathBuf};
+
+// Wayfire through its IPC socket (the ipc, ipc-rules and wm-actions plugins).
+// Each message is a little-endian u32 length and a JSON object; requests are
+// {"method": ..., "data": {...}}. Hiding minimises.
+pub struct WayfireBackend {
+    ipc: Reconnecting<WayfireIpc>,
+    hide_method: HideMethod,
+}
+
+struct WayfireIpc {
+    stream: UnixStream,
+}
+
+impl WayfireIpc {
+    fn connect(path: &Path) -> io::Result<Self> {
+        Ok(Self {
+            stream: ipc::connect(path)?,
+        })
+    }
+
+    fn request(&mut self, method: &str, data: &Value) -> Result<Value, RequestError> {
+        let message = json!({ "method": method, "data": data }).to_string();
+        let mut framed = (message.len() as u32).to_le_bytes().to_vec();
+        framed.extend_from_slice(message.as_bytes());
+        self.stream
+            .write_all(&framed)
+            .map_err(RequestError::Unsent)?;
+        let reply = read_message(&mut self.stream).map_err(RequestError::Sent)?;
+        serde_json::from_slice(&reply)
+            .map_err(|e| RequestError::Sent(io::Error::new(io::ErrorKind::InvalidData, e)))
+    }
+}
+
+fn read_message(reader: &mut impl Read) -> io::Result<Vec<u8>> {
+    let mut header = [0u8; 4];
+    reader.read_exact(&mut header)?;
+    let mut message = vec![0u8; u32::from_le_bytes(header) as usize];
+    reader.read_exact(&mut message)?;
+    Ok(message)
+}
+
+#[derive(Debug, Clone, Deserialize)]
+struct View {
+    id: u64,
+    #[serde(rename = "app-id", default)]
+    app_id: String,
+    #[serde(default)]
+    title: String,
+    // -1 when unknown
+    #[serde(default)]
+    pid: i64,
+    // "toplevel" for application windows
+    #[serde(default)]
+    role: String,
+    #[serde(default)]
+    mapped: bool,
+    #[serde(default)]
+    minimized: bool,
+    #[serde(default)]
+    activated: bool,
+    #[serde(default)]
+    sticky: bool,
+    #[serde(rename = "output-id", default)]
+    output_id: i64,
+    #[serde(rename = "wset-index", default)]
+    wset_index: i64,
+    // Relative to the current workspace of its output
+    geometry: Geometry,
+}
+
+#[derive(Debug, Clone, Copy, Deserialize)]
+struct Geometry {
+    x: i64,
+    y: i64,
+    width: i64,
+    height: i64,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+struct Output {
+    id: i64,
+    #[serde(rename = "wset-index", default)]
+    wset_index: i64,
+    geometry: Geometry,
+}
+
+impl View {
+    // On the workspace the focused output shows, sticky or overlapping it
+    fn is_on(&self, output: &Output) -> bool {
+        let screen = output.geometry;
+        let g = self.geometry;
+        let overlaps =
+            g.x < screen.width && g.x + g.width > 0 && g.y < screen.height && g.y + g.height > 0;
+        self.output_id == output.id
+            && self.wset_index == output.wset_index
+            && (self.sticky || overlaps)
+    }
+}
+
+impl WayfireBackend {
+    pub fn new(socket: PathBuf, hide_method: HideMethod) -> Self {
+        Self {
+            ipc: Reconnecting::new(socket, WayfireIpc::connect),
+            hide_method,
+        }
+    }
+
+    pub fn socket() -> Option<PathBuf> {
+        env::var_os("WAYFIRE_SOCKET").map(PathBuf::from)
+    }
+
+    pub fn probe() -> Probe {
+        Probe::socket("WAYFIRE_SOCKET", Self::socket().as_deref())
+    }
+
+    fn request(&mut self, method: &str, data: Value) -> BackendResult<Value> {
+        let reply = self.ipc.request(|ipc| ipc.request(method, &data))?;
+        match reply.get("error").and_then(Value::as_str) {
+            Some(error) => Err(BackendError::Protocol(format!("{method}: {error}"))),
+            None => Ok(reply),
+        }
+    }
+
+    fn query<T: for<'de> Deserialize<'de>>(
+        &mut self,
+        method: &str,
+        data: Value,
+    ) -> BackendResult<T> {
+        serde_json::from_value(self.request(method, data)?)
+            .map_err(|e| BackendError::Protocol(format!("{method}: {e}")))
+    }
+
+    fn views(&mut self) -> BackendResult<Vec<View>> {
+        let views: Vec<View> = self.query("window-rules/list-views", json!({}))?;
+        Ok(views
+            .into_iter()
+            .filter(|v| v.role == "toplevel" && v.mapped)
+            .collect())
+    }
+
+    fn view(&mut self, id: u64) -> BackendResult<View> {
+        self.views()?
+            .into_iter()
+            .find(|v| v.id == id)
+            .ok_or(BackendError::WindowGone(id))
+    }
+
+    fn focused_output(&mut self) -> BackendResult<Output> {
+        #[derive(Deserialize)]
+        struct Focused {
+            info: Output,
+        }
+        let focused: Focused = self.query("window-rules/get-focused-output", json!({}))?;
+        Ok(focused.info)
+    }
+
+    fn set_minimized(&mut self, id: u64, state: bool) -> BackendResult<()> {
+        self.view(id)?;
+        self.request(
+            "wm-actions/set-minimized",
+            json!({ "view_id": id, "state": state }),
+        )
+        .map(|_| ())
+    }
+}
+
+impl WindowBackend for WayfireBackend {
+    fn list_windows(&mut self, app_name: &str) -> BackendResult<Vec<WindowInfo>> {
+        let output = self.focused_output()?;
+        Ok(self
+            .views()?
+            .into_iter()
+            .filter(|v| matches_app(app_name, Some(&v.title), Some(&v.app_id)))
+            .map(|v| WindowInfo {
+                id: v.id,
+                on_current_ws: v.is_on(&output),
+                visible: !v.minimized,
+                focused: v.activated,
+                pid: u32::try_from(v.pid).ok().filter(|&pid| pid > 0),
+                class: Some(v.app_id),
+                title: Some(v.title),
+                workspace: None,
+            })
+            .collect())
+    }
+
+    fn is_on_current_workspace(&mut self, window: u64) -> BackendResult<bool> {
+        let output = self.focused_output()?;
+        Ok(self.view(window)?.is_on(&output))
+    }
+
+    fn is_visible(&mut self, window: u64) -> BackendResult<bool> {
+        Ok(!self.view(window)?.minimized)
+    }
+
+    // Onto the focused output (and so its workspace set), placed within the
+    // workspace it shows.
+    fn move_to_current_workspace(&mut self, window: u64) -> BackendResult<()> {
+        let output = self.focused_output()?;
+        let g = self.view(window)?.geometry;
+        let screen = output.geometry;
+        self.request(
+            "window-rules/configure-view",
+            json!({
+                "id": window,
+                "output_id": output.id,
+                "geometry": {
+                    "x": g.x.rem_euclid(screen.width.max(1)),
+                    "y": g.y.rem_euclid(screen.height.max(1)),
+                    "width": g.width,
+                    "height": g.height,
+                },
+            }),
+        )
+        .map(|_| ())
+    }
+
+    fn show(&mut self, window: u64) -> BackendResult<()> {
+        self.set_minimized(window, false)?;
+        self.request("window-rules/focus-view", json!({ "id": window }))
+            .map(|_| ())
+    }
+
+    fn hide(&mut self, window: u64) -> BackendResult<()> {
+        match self.hide_method {
+            HideMethod::None => Err(BackendError::Unsupported(
+                "hiding with wayland_hide_method = none",
+            )),
+            HideMethod::Auto | HideMethod::Scratchpad => self.set_minimized(window, true),
+        }
+    }
+
+    fn launch_app(&mut self, app_path: &str) -> BackendResult<u32> {
+        spawn_app(app_path)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::common_backend::test_support::{app, assert_display_unavailable, TempDir};
+    use crate::common_backend::{toggle_or_launch, Launched, Outcome};
+    use std::os::unix::net::UnixListener;
+    use std::sync::{Arc, Mutex};
+    use std::thread;
+
+    // Captured from Wayfire 0.9 (ipc-rules), trimmed: Alacritty on the
+    // focused output's current workspace, a minimised editor on the
+    // workspace to the right, a panel (not a toplevel).
+    const VIEWS: &str = r#"[
+        {"id": 12, "pid": 4242, "title": "~", "app-id": "Alacritty", "base-geometry": {},
+         "parent": -1, "geometry": {"x": 40, "y": 60, "width": 800, "height": 500},
+         "bbox": {}, "output-id": 1, "output-name": "eDP-1", "last-focus-timestamp": 10,
+         "role": "toplevel", "mapped": true, "layer": "workspace", "tiled-edges": 0,
+         "fullscreen": false, "minimized": false, "activated": true, "sticky": false,
+         "wset-index": 1, "min-size": {}, "max-size": {}, "focusable": true, "type": "toplevel"},
+        {"id": 15, "pid": 4243, "title": "notes", "app-id": "org.gnome.gedit",
+         "geometry": {"x": 1960, "y": 100, "width": 700, "height": 600},
+         "output-id": 1, "role": "toplevel", "mapped": true, "minimized": true,
+         "activated": false, "sticky": false, "wset-index": 1, "type": "toplevel"},
+        {"id": 3, "pid": 4000, "title": "panel", "app-id": "wf-panel",
+         "geometry": {"x": 0, "y": 0, "width": 1920, "height": 30},
+         "output-id": 1, "role": "desktop-environment", "mapped": true, "minimized": false,
+         "activated": false, "sticky": true, "wset-index": 1, "type": "background"}
+    ]"#;
+    const FOCUSED_OUTPUT: &str = r#"{"result": "ok", "info": {
+        "id": 1, "name": "eDP-1", "geometry": {"x": 0, "y": 0, "width": 1920, "height": 1080},
+        "workarea": {"x": 0, "y": 30, "width": 1920, "height": 1050}, "wset-index": 1,
+        "workspace": {"x": 0, "y": 0, "grid_width": 3, "grid_height": 3}}}"#;
+
+    // Replays the captured replies and records the requests.
+    struct FakeWayfire {
+        dir: TempDir,
+        requests: Arc<Mutex<Vec<Value>>>,
+    }
+
+    impl FakeWayfire {
+        fn start(name: &str) -> Self {
+            let dir = TempDir::new(name);
+            let listener = UnixListener::bind(dir.join("wayfire.sock")).unwrap();
+            let requests = Arc::new(Mutex::new(Vec::new()));
+            let recorded = requests.clone();
+            thread::spawn(move || {
+                for stream in listener.incoming() {
+                    let Ok(mut stream) = stream else { return };
+                    while let Ok(message) = read_message(&mut stream) {
+                        let request: Value = serde_json::from_slice(&message).unwrap();
+                        let reply = match request["method"].as_str().unwrap() {
+                            "window-rules/list-views" => VIEWS.to_string(),
+                            "window-rules/get-focused-output" => FOCUSED_OUTPUT.to_string(),
+                            "wm-actions/set-minimized"
+                            | "window-rules/focus-view"
+                            | "window-rules/configure-view" => r#"{"result": "ok"}"#.to_string(),
+                            method => format!(r#"{{"error": "No such method found: {method}"}}"#),
+                        };
+                        recorded.lock().unwrap().push(request);
+                        let _ = stream.write_all(&(reply.len() as u32).to_le_bytes());
+                        let _ = stream.write_all(reply.as_bytes());
+                    }
+                }
+            });
+            Self { dir, requests }
+        }
+
+        // The requests that change something, in order
+        fn actions(&self) -> Vec<Value> {
+            self.requests
+                .lock()
+                .unwrap()
+                .iter()
+                .filter(|r| {
+                    !matches!(
+                        r["method"].as_str(),
+                        Some("window-rules/list-views" | "window-rules/get-focused-output")
+                    )
+                })
+                .cloned()
+                .collect()
+        }
+    }
+
+    #[test]
+    fn toggles_views_over_ipc() {
+        let wayfire = FakeWayfire::start("wayfire");
+        let mut backend = WayfireBackend::new(wayfire.dir.join("wayfire.sock"), HideMethod::Auto);
+
+        let listed = backend.list_windows("class=Alacritty").unwrap();
+        assert_eq!(listed.len(), 1);
+        assert!(listed[0].on_current_ws && listed[0].visible && listed[0].focused);
+        let notes = &backend.list_windows("title=notes").unwrap()[0];
+        assert!(!notes.on_current_ws && !notes.visible);
+        assert!(backend.find_window("wf-panel").unwrap().is_none());
+
+        assert_eq!(
+            toggle_or_launch(&mut backend, &app("Alacritty"), &mut Launched::default()).unwrap(),
+            Outcome::Hidden(12)
+        );
+        assert_eq!(
+            toggle_or_launch(&mut backend, &app("title=notes"), &mut Launched::default()).unwrap(),
+            Outcome::Moved(15)
+        );
+        assert_eq!(
+            wayfire.actions(),
+            [
+                json!({"method": "wm-actions/set-minimized", "data": {"view_id": 12, "state": true}}),
+                json!({"method": "window-rules/configure-view", "data": {
+                    "id": 15, "output_id": 1,
+                    "geometry": {"x": 40, "y": 100, "width": 700, "height": 600}}}),
+                json!({"method": "wm-actions/set-minimized", "data": {"view_id": 15, "state": false}}),
+                json!({"method": "window-rules/focus-view", "data": {"id": 15}}),
+            ]
+        );
+        assert!(matches!(
+            backend.show(99),
+            Err(BackendError::WindowGone(99))
+        ));
+    }
+
+    #[test]
+    fn missing_socket_is_display_unavailable() {
+        let mut backend =
+            WayfireBackend::new(PathBuf::from("/nonexistent/wayfire.sock"), HideMethod::Auto);
+        assert_display_unavailable(&mut backend);
+    }
+}
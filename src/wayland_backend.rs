@@ -1,35 +1,47 @@
-use crate::common_backend::WindowBackend;
-use std::process::Command;
+use crate::common_backend::{
+    spawn_app, BackendError, BackendResult, Probe, WindowBackend, WindowInfo,
+};
 
 // Wayland is compositor-specific for global window control.
-// This backend acts conservatively: we cannot reliably find/toggle windows
-// without compositor protocols (e.g., GNOME extension, sway IPC, hyprctl).
-// Therefore, we fallback to launching when requested and report no window found.
+// This backend is the fallback for compositors without a dedicated backend
+// (sway has one): it reports no window found and only launches.
 pub struct WaylandBackend;
 
 impl WaylandBackend {
     pub fn new() -> Self {
         Self
     }
+
+    // The last resort in a Wayland session
+    pub fn probe() -> Probe {
+        if std::env::var_os("WAYLAND_DISPLAY").is_some() {
+            Probe::new(5, "Wayland session; can only launch apps")
+        } else {
+            Probe::none("WAYLAND_DISPLAY is not set")
+        }
+    }
 }
 
 impl WindowBackend for WaylandBackend {
-    fn find_window(&mut self, _app_name: &str) -> Option<u64> {
-        None
+    fn list_windows(&mut self, _app_name: &str) -> BackendResult<Vec<WindowInfo>> {
+        Ok(Vec::new())
     }
-    fn is_on_current_workspace(&mut self, _window: u64) -> bool {
-        false
+    fn is_on_current_workspace(&mut self, _window: u64) -> BackendResult<bool> {
+        Err(BackendError::Unsupported("workspace lookup"))
     }
-    fn is_visible(&mut self, _window: u64) -> bool {
-        false
+    fn is_visible(&mut self, _window: u64) -> BackendResult<bool> {
+        Err(BackendError::Unsupported("visibility lookup"))
     }
-    fn move_to_current_workspace(&mut self, _window: u64) { /* no-op */
+    fn move_to_current_workspace(&mut self, _window: u64) -> BackendResult<()> {
+        Err(BackendError::Unsupported("moving windows"))
     }
-    fn show(&mut self, _window: u64) { /* no-op */
+    fn show(&mut self, _window: u64) -> BackendResult<()> {
+        Err(BackendError::Unsupported("showing windows"))
     }
-    fn hide(&mut self, _window: u64) { /* no-op */
+    fn hide(&mut self, _window: u64) -> BackendResult<()> {
+        Err(BackendError::Unsupported("hiding windows"))
     }
-    fn launch_app(&mut self, app_path: &str) {
-        let _ = Command::new(app_path).spawn();
+    fn launch_app(&mut self, app_path: &str) -> BackendResult<u32> {
+        spawn_app(app_path)
     }
 }
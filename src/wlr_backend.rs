@@ -0,0 +1,603 @@
+use crate::common_backend::{
+    spawn_app, BackendError, BackendResult, HideMethod, Probe, WindowBackend, WindowInfo,
+};
+use crate::matching::matches_app;
+use std::os::unix::net::UnixStream;
+use std::path::PathBuf;
+use wayland_client::globals::{registry_queue_init, GlobalListContents};
+use wayland_client::protocol::{wl_registry, wl_seat::WlSeat};
+use wayland_client::{event_created_child, Connection, Dispatch, EventQueue, Proxy, QueueHandle};
+use wayland_protocols_wlr::foreign_toplevel::v1::client::{
+    zwlr_foreign_toplevel_handle_v1::{self, ZwlrForeignToplevelHandleV1},
+    zwlr_foreign_toplevel_manager_v1::{self, ZwlrForeignToplevelManagerV1},
+};
+
+const MANAGER: &str = "zwlr_foreign_toplevel_manager_v1";
+
+// Any compositor offering wlr-foreign-toplevel-management (river, labwc,
+// Wayfire, niri, ...). The protocol knows nothing of workspaces: every window
+// counts as being on the current one, and "visible" means it is the active,
+// unminimised window. Showing activates it, which brings it to the user;
+// hiding minimises.
+pub struct WlrBackend {
+    // Compositor socket; None for $WAYLAND_DISPLAY
+    socket: Option<PathBuf>,
+    hide_method: HideMethod,
+    session: Option<Session>,
+}
+
+struct Session {
+    queue: EventQueue<Toplevels>,
+    toplevels: Toplevels,
+    seat: Option<WlSeat>,
+}
+
+#[derive(Default)]
+struct Toplevels {
+    windows: Vec<Toplevel>,
+    // The manager went away; a new session is needed
+    finished: bool,
+}
+
+struct Toplevel {
+    handle: ZwlrForeignToplevelHandleV1,
+    // Collected until the next done event, then applied
+    pending: Info,
+    info: Option<Info>,
+}
+
+#[derive(Debug, Clone, Default)]
+struct Info {
+    app_id: String,
+    title: String,
+    minimized: bool,
+    activated: bool,
+}
+
+impl Toplevels {
+    fn toplevel_mut(&mut self, handle: &ZwlrForeignToplevelHandleV1) -> Option<&mut Toplevel> {
+        self.windows.iter_mut().find(|t| t.handle == *handle)
+    }
+}
+
+impl Toplevel {
+    fn id(&self) -> u64 {
+        self.handle.id().protocol_id() as u64
+    }
+}
+
+impl WlrBackend {
+    pub fn new(hide_method: HideMethod) -> Self {
+        Self {
+            socket: None,
+            hide_method,
+            session: None,
+        }
+    }
+
+    // Below the compositor-specific backends: without workspaces it can only
+    // show and hide.
+    pub fn probe() -> Probe {
+        if std::env::var_os("WAYLAND_DISPLAY").is_none() {
+            return Probe::none("WAYLAND_DISPLAY is not set");
+        }
+        let globals = Connection::connect_to_env()
+            .ok()
+            .and_then(|conn| registry_queue_init::<Toplevels>(&conn).ok());
+        let Some((globals, _)) = globals else {
+            return Probe::none("cannot connect to the Wayland compositor");
+        };
+        let offered = globals
+            .contents()
+            .with_list(|list| list.iter().any(|g| g.interface == MANAGER));
+        if offered {
+            Probe::new(60, format!("the compositor offers {MANAGER}"))
+        } else {
+            Probe::none(format!("the compositor does not offer {MANAGER}"))
+        }
+    }
+
+    fn connect(&self) -> BackendResult<Session> {
+        let conn = match &self.socket {
+            Some(path) => UnixStream::connect(path)
+                .ok()
+                .and_then(|stream| Connection::from_socket(stream).ok()),
+            None => Connection::connect_to_env().ok(),
+        }
+        .ok_or(BackendError::DisplayUnavailable)?;
+        let (globals, queue) = registry_queue_init::<Toplevels>(&conn)
+            .map_err(|_| BackendError::DisplayUnavailable)?;
+        let qh = queue.handle();
+        globals
+            .bind::<ZwlrForeignToplevelManagerV1, _, _>(&qh, 1..=3, ())
+            .map_err(|_| {
+                BackendError::Protocol(format!("the compositor does not offer {MANAGER}"))
+            })?;
+        // Only needed to activate windows
+        let seat = globals.bind::<WlSeat, _, _>(&qh, 1..=1, ()).ok();
+        Ok(Session {
+            queue,
+            toplevels: Toplevels::default(),
+            seat,
+        })
+    }
+
+    // The session, after processing everything the compositor has sent.
+    fn session(&mut self) -> BackendResult<&mut Session> {
+        if self.session.is_none() {
+            self.session = Some(self.connect()?);
+        }
+        let session = self.session.as_mut().unwrap();
+        let synced = session.queue.roundtrip(&mut session.toplevels);
+        if synced.is_err() || session.toplevels.finished {
+            // The compositor went away or stopped telling us; start over next time
+            self.session = None;
+            return Err(BackendError::DisplayUnavailable);
+        }
+        Ok(self.session.as_mut().unwrap())
+    }
+
+    fn info(&mut self, window: u64) -> BackendResult<Info> {
+        self.session()?
+            .toplevels
+            .windows
+            .iter()
+            .find(|t| t.id() == window)
+            .and_then(|t| t.info.clone())
+            .ok_or(BackendError::WindowGone(window))
+    }
+
+    // Sends requests for one window and waits until the compositor has them.
+    fn request(
+        &mut self,
+        window: u64,
+        f: impl FnOnce(&ZwlrForeignToplevelHandleV1, Option<&WlSeat>) -> BackendResult<()>,
+    ) -> BackendResult<()> {
+        let session = self.session()?;
+        let toplevel = session
+            .toplevels
+            .windows
+            .iter()
+            .find(|t| t.id() == window)
+            .ok_or(BackendError::WindowGone(window))?;
+        f(&toplevel.handle, session.seat.as_ref())?;
+        self.session().map(|_| ())
+    }
+}
+
+impl Dispatch<wl_registry::WlRegistry, GlobalListContents> for Toplevels {
+    fn event(
+        _: &mut Self,
+        _: &wl_registry::WlRegistry,
+        _: wl_registry::Event,
+        _: &GlobalListContents,
+        _: &Connection,
+        _: &QueueHandle<Self>,
+    ) {
+    }
+}
+
+impl Dispatch<WlSeat, ()> for Toplevels {
+    fn event(
+        _: &mut Self,
+        _: &WlSeat,
+        _: wayland_client::protocol::wl_seat::Event,
+        _: &(),
+        _: &Connection,
+        _: &QueueHandle<Self>,
+    ) {
+    }
+}
+
+impl Dispatch<ZwlrForeignToplevelManagerV1, ()> for Toplevels {
+    fn event(
+        state: &mut Self,
+        _: &ZwlrForeignToplevelManagerV1,
+        event: zwlr_foreign_toplevel_manager_v1::Event,
+        _: &(),
+        _: &Connection,
+        _: &QueueHandle<Self>,
+    ) {
+        match event {
+            zwlr_foreign_toplevel_manager_v1::Event::Toplevel { toplevel } => {
+                state.windows.push(Toplevel {
+                    handle: toplevel,
+                    pending: Info::default(),
+                    info: None,
+                });
+            }
+            zwlr_foreign_toplevel_manager_v1::Event::Finished => state.finished = true,
+            _ => {}
+        }
+    }
+
+    event_created_child!(Toplevels, ZwlrForeignToplevelManagerV1, [
+        zwlr_foreign_toplevel_manager_v1::EVT_TOPLEVEL_OPCODE => (ZwlrForeignToplevelHandleV1, ()),
+    ]);
+}
+
+impl Dispatch<ZwlrForeignToplevelHandleV1, ()> for Toplevels {
+    fn event(
+        state: &mut Self,
+        handle: &ZwlrForeignToplevelHandleV1,
+        event: zwlr_foreign_toplevel_handle_v1::Event,
+        _: &(),
+        _: &Connection,
+        _: &QueueHandle<Self>,
+    ) {
+        use zwlr_foreign_toplevel_handle_v1::{Event, State};
+
+        if let Event::Closed = event {
+            state.windows.retain(|t| t.handle != *handle);
+            handle.destroy();
+            return;
+        }
+        let Some(toplevel) = state.toplevel_mut(handle) else {
+            return;
+        };
+        match event {
+            Event::Title { title } => toplevel.pending.title = title,
+            Event::AppId { app_id } => toplevel.pending.app_id = app_id,
+            Event::State { state } => {
+                // An array of native-endian u32 state values
+                let states: Vec<u32> = state
+                    .chunks_exact(4)
+                    .map(|c| u32::from_ne_bytes([c[0], c[1], c[2], c[3]]))
+                    .collect();
+                toplevel.pending.minimized = states.contains(&(State::Minimized as u32));
+                toplevel.pending.activated = states.contains(&(State::Activated as u32));
+            }
+            Event::Done => toplevel.info = Some(toplevel.pending.clone()),
+            _ => {}
+        }
+    }
+}
+
+impl WindowBackend for WlrBackend {
+    fn list_windows(&mut self, app_name: &str) -> BackendResult<Vec<WindowInfo>> {
+        Ok(self
+            .session()?
+            .toplevels
+            .windows
+            .iter()
+            .filter_map(|t| Some((t.id(), t.info.as_ref()?)))
+            .filter(|(_, info)| matches_app(app_name, Some(&info.title), Some(&info.app_id)))
+            .map(|(id, info)| WindowInfo {
+                id,
+                class: Some(info.app_id.clone()),
+                title: Some(info.title.clone()),
+                workspace: None,
+                on_current_ws: true,
+                visible: info.activated && !info.minimized,
+                focused: info.activated,
+                // The protocol does not say which client owns a toplevel
+                pid: None,
+            })
+            .collect())
+    }
+
+    fn is_on_current_workspace(&mut self, window: u64) -> BackendResult<bool> {
+        self.info(window).map(|_| true)
+    }
+
+    fn is_visible(&mut self, window: u64) -> BackendResult<bool> {
+        let info = self.info(window)?;
+        Ok(info.activated && !info.minimized)
+    }
+
+    fn move_to_current_workspace(&mut self, _window: u64) -> BackendResult<()> {
+        Err(BackendError::Unsupported(
+            "moving windows between workspaces",
+        ))
+    }
+
+    fn show(&mut self, window: u64) -> BackendResult<()> {
+        self.request(window, |handle, seat| {
+            let seat = seat.ok_or(BackendError::Unsupported("activating without a seat"))?;
+            handle.unset_minimized();
+            handle.activate(seat);
+            Ok(())
+        })
+    }
+
+    fn hide(&mut self, window: u64) -> BackendResult<()> {
+        match self.hide_method {
+            HideMethod::None => Err(BackendError::Unsupported(
+                "hiding with wayland_hide_method = none",
+            )),
+            HideMethod::Auto | HideMethod::Scratchpad => self.request(window, |handle, _| {
+                handle.set_minimized();
+                Ok(())
+            }),
+        }
+    }
+
+    fn launch_app(&mut self, app_path: &str) -> BackendResult<u32> {
+        spawn_app(app_path)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::common_backend::test_support::{app, assert_display_unavailable, TempDir};
+    use crate::common_backend::{toggle_or_launch, Launched, Outcome};
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::sync::{Arc, Mutex};
+    use std::thread;
+    use std::time::Duration;
+    use wayland_protocols_wlr::foreign_toplevel::v1::server::{
+        zwlr_foreign_toplevel_handle_v1 as server_handle,
+        zwlr_foreign_toplevel_manager_v1 as server_manager,
+    };
+    use wayland_server::backend::{ClientData, ClientId, DisconnectReason};
+    use wayland_server::protocol::wl_seat;
+    use wayland_server::{
+        Client, DataInit, Display, DisplayHandle, GlobalDispatch, ListeningSocket, New, Resource,
+    };
+
+    #[derive(Debug, Clone)]
+    struct MockWindow {
+        app_id: &'static str,
+        title: &'static str,
+        minimized: bool,
+        activated: bool,
+        open: bool,
+    }
+
+    // A compositor with just the toplevel manager and a seat, run on a
+    // thread over a socket in a temporary directory.
+    struct Compositor {
+        windows: Arc<Mutex<Vec<MockWindow>>>,
+        handles: Vec<(usize, server_handle::ZwlrForeignToplevelHandleV1)>,
+    }
+
+    impl Compositor {
+        fn send_all(&self) {
+            let windows = self.windows.lock().unwrap();
+            for (index, handle) in &self.handles {
+                let w = &windows[*index];
+                let mut states = Vec::new();
+                if w.minimized {
+                    states.extend_from_slice(&1u32.to_ne_bytes());
+                }
+                if w.activated {
+                    states.extend_from_slice(&2u32.to_ne_bytes());
+                }
+                handle.title(w.title.to_string());
+                handle.app_id(w.app_id.to_string());
+                handle.state(states);
+                handle.done();
+            }
+        }
+
+        // Tells clients about windows closed by the test.
+        fn close_gone(&mut self) {
+            let windows = self.windows.lock().unwrap();
+            self.handles.retain(|(index, handle)| {
+                let open = windows[*index].open;
+                if !open {
+                    handle.closed();
+                }
+                open
+            });
+        }
+    }
+
+    impl GlobalDispatch<server_manager::ZwlrForeignToplevelManagerV1, ()> for Compositor {
+        fn bind(
+            state: &mut Self,
+            dh: &DisplayHandle,
+            client: &Client,
+            resource: New<server_manager::ZwlrForeignToplevelManagerV1>,
+            _: &(),
+            data_init: &mut DataInit<'_, Self>,
+        ) {
+            let manager = data_init.init(resource, ());
+            let count = state.windows.lock().unwrap().len();
+            for index in 0..count {
+                let handle = client
+                    .create_resource::<server_handle::ZwlrForeignToplevelHandleV1, usize, Self>(
+                        dh,
+                        manager.version(),
+                        index,
+                    )
+                    .unwrap();
+                manager.toplevel(&handle);
+                state.handles.push((index, handle));
+            }
+            state.send_all();
+        }
+    }
+
+    impl wayland_server::Dispatch<server_manager::ZwlrForeignToplevelManagerV1, ()> for Compositor {
+        fn request(
+            _: &mut Self,
+            _: &Client,
+            manager: &server_manager::ZwlrForeignToplevelManagerV1,
+            request: server_manager::Request,
+            _: &(),
+            _: &DisplayHandle,
+            _: &mut DataInit<'_, Self>,
+        ) {
+            if let server_manager::Request::Stop = request {
+                manager.finished();
+            }
+        }
+    }
+
+    impl wayland_server::Dispatch<server_handle::ZwlrForeignToplevelHandleV1, usize> for Compositor {
+        fn request(
+            state: &mut Self,
+            _: &Client,
+            _: &server_handle::ZwlrForeignToplevelHandleV1,
+            request: server_handle::Request,
+            index: &usize,
+            _: &DisplayHandle,
+            _: &mut DataInit<'_, Self>,
+        ) {
+            {
+                let mut windows = state.windows.lock().unwrap();
+                match request {
+                    server_handle::Request::SetMinimized => {
+                        windows[*index].minimized = true;
+                        windows[*index].activated = false;
+                    }
+                    server_handle::Request::UnsetMinimized => windows[*index].minimized = false,
+                    server_handle::Request::Activate { .. } => {
+                        for (i, w) in windows.iter_mut().enumerate() {
+                            w.activated = i == *index;
+                        }
+                    }
+                    _ => return,
+                }
+            }
+            state.send_all();
+        }
+    }
+
+    impl GlobalDispatch<wl_seat::WlSeat, ()> for Compositor {
+        fn bind(
+            _: &mut Self,
+            _: &DisplayHandle,
+            _: &Client,
+            resource: New<wl_seat::WlSeat>,
+            _: &(),
+            data_init: &mut DataInit<'_, Self>,
+        ) {
+            data_init.init(resource, ());
+        }
+    }
+
+    impl wayland_server::Dispatch<wl_seat::WlSeat, ()> for Compositor {
+        fn request(
+            _: &mut Self,
+            _: &Client,
+            _: &wl_seat::WlSeat,
+            _: wl_seat::Request,
+            _: &(),
+            _: &DisplayHandle,
+            _: &mut DataInit<'_, Self>,
+        ) {
+        }
+    }
+
+    struct NoData;
+
+    impl ClientData for NoData {
+        fn initialized(&self, _: ClientId) {}
+        fn disconnected(&self, _: ClientId, _: DisconnectReason) {}
+    }
+
+    struct FakeCompositor {
+        dir: TempDir,
+        windows: Arc<Mutex<Vec<MockWindow>>>,
+        stop: Arc<AtomicBool>,
+    }
+
+    impl FakeCompositor {
+        fn start(name: &str, windows: Vec<MockWindow>) -> Self {
+            let dir = TempDir::new(name);
+            let socket = ListeningSocket::bind_absolute(dir.join("wayland-0")).unwrap();
+            let windows = Arc::new(Mutex::new(windows));
+            let stop = Arc::new(AtomicBool::new(false));
+
+            let mut state = Compositor {
+                windows: windows.clone(),
+                handles: Vec::new(),
+            };
+            let stopped = stop.clone();
+            thread::spawn(move || {
+                let mut display = Display::<Compositor>::new().unwrap();
+                let dh = display.handle();
+                dh.create_global::<Compositor, server_manager::ZwlrForeignToplevelManagerV1, ()>(
+                    3,
+                    (),
+                );
+                dh.create_global::<Compositor, wl_seat::WlSeat, ()>(1, ());
+                while !stopped.load(Ordering::SeqCst) {
+                    if let Ok(Some(stream)) = socket.accept() {
+                        display
+                            .handle()
+                            .insert_client(stream, Arc::new(NoData))
+                            .unwrap();
+                    }
+                    display.dispatch_clients(&mut state).unwrap();
+                    state.close_gone();
+                    display.flush_clients().unwrap();
+                    thread::sleep(Duration::from_millis(2));
+                }
+            });
+            Self { dir, windows, stop }
+        }
+    }
+
+    impl Drop for FakeCompositor {
+        fn drop(&mut self) {
+            self.stop.store(true, Ordering::SeqCst);
+        }
+    }
+
+    fn window(app_id: &'static str, title: &'static str, activated: bool) -> MockWindow {
+        MockWindow {
+            app_id,
+            title,
+            minimized: false,
+            activated,
+            open: true,
+        }
+    }
+
+    #[test]
+    fn toggles_foreign_toplevels() {
+        let compositor = FakeCompositor::start(
+            "wlr-toggle",
+            vec![
+                window("Alacritty", "~", true),
+                window("org.gnome.gedit", "notes", false),
+            ],
+        );
+        let mut backend = WlrBackend::new(HideMethod::Auto);
+        backend.socket = Some(compositor.dir.join("wayland-0"));
+
+        let listed = backend.list_windows("class=Alacritty").unwrap();
+        assert_eq!(listed.len(), 1);
+        assert!(listed[0].visible && listed[0].focused);
+        let alacritty = listed[0].id;
+
+        assert_eq!(
+            toggle_or_launch(&mut backend, &app("Alacritty"), &mut Launched::default()).unwrap(),
+            Outcome::Hidden(alacritty)
+        );
+        assert!(compositor.windows.lock().unwrap()[0].minimized);
+        assert_eq!(
+            toggle_or_launch(&mut backend, &app("Alacritty"), &mut Launched::default()).unwrap(),
+            Outcome::Shown(alacritty)
+        );
+        // Behind another window: brought forward rather than hidden
+        let notes = backend.find_window("title=notes").unwrap().unwrap();
+        assert_eq!(
+            toggle_or_launch(&mut backend, &app("title=notes"), &mut Launched::default()).unwrap(),
+            Outcome::Shown(notes)
+        );
+        {
+            let windows = compositor.windows.lock().unwrap();
+            assert!(!windows[0].activated && windows[1].activated);
+        }
+
+        compositor.windows.lock().unwrap()[0].open = false;
+        thread::sleep(Duration::from_millis(50));
+        assert!(backend.find_window("Alacritty").unwrap().is_none());
+        assert!(matches!(
+            backend.show(alacritty),
+            Err(BackendError::WindowGone(_))
+        ));
+    }
+
+    #[test]
+    fn missing_compositor_is_display_unavailable() {
+        let mut backend = WlrBackend::new(HideMethod::Auto);
+        backend.socket = Some(PathBuf::from("/nonexistent/wayland-0"));
+        assert_display_unavailable(&mut backend);
+    }
+}
@@ -1,188 +1,433 @@
-use crate::common_backend::WindowBackend;
-use crate::x11_ewmh::{
-    build_net_active_window_message, build_net_wm_desktop_message, matches_app,
-    select_preferred_window, Candidate,
+use crate::common_backend::{
+    spawn_app, BackendError, BackendResult, Probe, WindowBackend, WindowInfo,
 };
+use crate::matching::{matches_app, select_preferred_window, Candidate};
+use crate::x11_errors::{trap, XError};
+use crate::x11_ewmh::{build_net_active_window_message, build_net_wm_desktop_message};
+use crate::x11_tracker::{Tracker, WindowModel};
+use std::env;
 use std::ffi::{CStr, CString};
-use std::process::Command;
+use std::os::raw::c_char;
 use std::ptr;
 
 extern crate x11;
 use core::ffi::{c_int, c_long, c_uchar, c_ulong};
 use x11::xlib::*;
 
-pub struct X11Backend;
+// ICCCM WM_STATE value of a minimized window
+const ICONIC_STATE: c_ulong = 3;
+
+// Keeps one X connection for the life of the daemon. It is opened on first use
+// and reopened if the server goes away (e.g. it was restarted).
+//
+// Lookups are answered from the tracker's window model when it is running and
+// knows the window; requests that change state always use the connection.
+pub struct X11Backend {
+    conn: Option<Connection>,
+    tracker: Option<Tracker>,
+}
 
 impl X11Backend {
     pub fn new() -> Self {
-        Self
+        // The tracker uses Xlib from its own thread
+        unsafe { XInitThreads() };
+        crate::x11_errors::install();
+        Self {
+            conn: None,
+            tracker: None,
+        }
     }
 
-    fn with_display<T, F: FnOnce(*mut Display) -> T>(f: F) -> Option<T> {
-        let display = unsafe { XOpenDisplay(ptr::null()) };
-        if display.is_null() {
+    // Confident with an EWMH window manager. Under Xwayland only X11 clients
+    // are visible, so a Wayland backend that answers should win.
+    pub fn probe() -> Probe {
+        let Some(name) = env::var_os("DISPLAY") else {
+            return Probe::none("DISPLAY is not set");
+        };
+        let name = name.to_string_lossy();
+        // Must come before any other Xlib call, as in new()
+        unsafe { XInitThreads() };
+        let Some(conn) = Connection::connect(None) else {
+            return Probe::none(format!("cannot open display {name}"));
+        };
+        if env::var_os("WAYLAND_DISPLAY").is_some() {
+            return Probe::new(
+                30,
+                format!("{name} is Xwayland; only X11 clients are visible"),
+            );
+        }
+        match conn.wm_name() {
+            Some(wm) => Probe::new(70, format!("window manager {wm:?} on {name}")),
+            None => Probe::new(40, format!("no EWMH window manager on {name}")),
+        }
+    }
+
+    fn with_connection<T, F: FnOnce(&Connection) -> T>(&mut self, f: F) -> BackendResult<T> {
+        if self.conn.as_ref().is_some_and(Connection::is_lost) {
+            eprintln!("X11 connection lost; reconnecting.");
+            if let Some(conn) = self.conn.take() {
+                conn.abandon();
+            }
+        }
+        if self.conn.is_none() {
+            self.conn = Connection::open();
+            // A new connection usually means a new server; track that one
+            self.tracker = self.conn.as_ref().and_then(|_| Tracker::start());
+        }
+        self.conn
+            .as_ref()
+            .map(f)
+            .ok_or(BackendError::DisplayUnavailable)
+    }
+
+    // Starts the connection (and tracker) on first use, then asks the model.
+    fn with_model<T>(&mut self, f: impl FnOnce(&WindowModel) -> Option<T>) -> Option<T> {
+        self.with_connection(|_| ()).ok()?;
+        self.tracker.as_ref()?.with_model(f).flatten()
+    }
+
+    // Some while the tracker runs: the model's answer, or WindowGone once the
+    // window has left the client list.
+    fn tracked<T>(
+        &mut self,
+        window: u64,
+        f: impl FnOnce(&WindowModel) -> Option<T>,
+    ) -> Option<BackendResult<T>> {
+        self.with_model(|m| Some(f(m).ok_or(BackendError::WindowGone(window))))
+    }
+
+    // Runs f with X errors trapped, so a window destroyed since it was found
+    // gives WindowGone instead of an error the daemon cannot recover from.
+    fn on_window<T>(
+        &mut self,
+        window: u64,
+        f: impl FnOnce(&Connection, Window) -> T,
+    ) -> BackendResult<T> {
+        self.with_connection(|c| c.checked(window as Window, |c| f(c, window as Window)))?
+    }
+
+    // Requests sent to the root on a window's behalf fail silently, so check
+    // that it still exists first: from the model if tracked, else by asking.
+    fn check_exists(&mut self, window: u64) -> BackendResult<()> {
+        match self.tracked(window, |m| m.is_visible(window)) {
+            Some(result) => result.map(drop),
+            None => self.on_window(window, |c, w| c.map_state(w)).map(drop),
+        }
+    }
+}
+
+// EWMH/ICCCM atoms used by the backend, interned once per connection.
+pub(crate) struct Atoms {
+    pub(crate) net_supported: Atom,
+    pub(crate) net_client_list: Atom,
+    pub(crate) net_client_list_stacking: Atom,
+    pub(crate) net_current_desktop: Atom,
+    pub(crate) net_wm_desktop: Atom,
+    pub(crate) net_active_window: Atom,
+    pub(crate) net_wm_name: Atom,
+    pub(crate) utf8_string: Atom,
+    pub(crate) wm_state: Atom,
+    pub(crate) net_supporting_wm_check: Atom,
+    pub(crate) net_wm_pid: Atom,
+}
+
+impl Atoms {
+    const NAMES: [&'static str; 11] = [
+        "_NET_SUPPORTED",
+        "_NET_CLIENT_LIST",
+        "_NET_CLIENT_LIST_STACKING",
+        "_NET_CURRENT_DESKTOP",
+        "_NET_WM_DESKTOP",
+        "_NET_ACTIVE_WINDOW",
+        "_NET_WM_NAME",
+        "UTF8_STRING",
+        "WM_STATE",
+        "_NET_SUPPORTING_WM_CHECK",
+        "_NET_WM_PID",
+    ];
+
+    // One round trip for all of them. Atoms are created if missing so that a
+    // window manager started later still matches the cached values.
+    unsafe fn intern(display: *mut Display) -> Self {
+        let names: Vec<CString> = Self::NAMES
+            .iter()
+            .map(|n| CString::new(*n).unwrap())
+            .collect();
+        let mut ptrs: Vec<*mut c_char> = names.iter().map(|n| n.as_ptr() as *mut c_char).collect();
+        let mut atoms = [0 as Atom; Self::NAMES.len()];
+        XInternAtoms(
+            display,
+            ptrs.as_mut_ptr(),
+            ptrs.len() as c_int,
+            0,
+            atoms.as_mut_ptr(),
+        );
+        // Same order as NAMES
+        Self {
+            net_supported: atoms[0],
+            net_client_list: atoms[1],
+            net_client_list_stacking: atoms[2],
+            net_current_desktop: atoms[3],
+            net_wm_desktop: atoms[4],
+            net_active_window: atoms[5],
+            net_wm_name: atoms[6],
+            utf8_string: atoms[7],
+            wm_state: atoms[8],
+            net_supporting_wm_check: atoms[9],
+            net_wm_pid: atoms[10],
+        }
+    }
+}
+
+// Property data from XGetWindowProperty, freed on drop.
+pub(crate) struct Property {
+    data: *mut c_uchar,
+    nitems: usize,
+    format: c_int,
+}
+
+impl Property {
+    // Format-32 items, which Xlib hands out as longs.
+    fn longs(&self) -> &[c_ulong] {
+        if self.format != 32 {
+            return &[];
+        }
+        unsafe { std::slice::from_raw_parts(self.data as *const c_ulong, self.nitems) }
+    }
+
+    fn bytes(&self) -> &[u8] {
+        if self.format != 8 {
+            return &[];
+        }
+        unsafe { std::slice::from_raw_parts(self.data as *const u8, self.nitems) }
+    }
+
+    pub(crate) fn first_long(&self) -> Option<c_ulong> {
+        self.longs().first().copied()
+    }
+}
+
+impl Drop for Property {
+    fn drop(&mut self) {
+        unsafe { XFree(self.data as *mut _) };
+    }
+}
+
+// Also used by the window tracker, on its own thread with its own connection.
+pub(crate) struct Connection {
+    pub(crate) display: *mut Display,
+    pub(crate) atoms: Atoms,
+}
+
+impl Drop for Connection {
+    fn drop(&mut self) {
+        crate::x11_errors::forget(self.display);
+        unsafe { XCloseDisplay(self.display) };
+    }
+}
+
+impl Connection {
+    pub(crate) fn open() -> Option<Self> {
+        let conn = Self::connect(None);
+        if conn.is_none() {
             eprintln!("X11 cannot open display.");
+        }
+        conn
+    }
+
+    // A named display such as ":99"; None means $DISPLAY.
+    fn connect(name: Option<&str>) -> Option<Self> {
+        let name = name.map(|n| CString::new(n).unwrap());
+        let display = unsafe { XOpenDisplay(name.as_ref().map_or(ptr::null(), |n| n.as_ptr())) };
+        if display.is_null() {
             return None;
         }
-        let out = f(display);
-        unsafe { XCloseDisplay(display) };
-        Some(out)
+        crate::x11_errors::survive(display);
+        let atoms = unsafe { Atoms::intern(display) };
+        Some(Self { display, atoms })
     }
 
-    fn find_window_internal(display: *mut Display, target: &str) -> Option<Window> {
-        unsafe {
-            let root = XDefaultRootWindow(display);
-            // Try stacking list first for better z-order preference
-            let net_client_list_stacking = XInternAtom(
-                display,
-                CString::new("_NET_CLIENT_LIST_STACKING").unwrap().as_ptr(),
-                1,
-            );
-            let net_client_list = XInternAtom(
-                display,
-                CString::new("_NET_CLIENT_LIST").unwrap().as_ptr(),
+    // True once a request hit an I/O error. The socket is checked for EOF as
+    // well, so a server gone while idle is noticed before the next request.
+    pub(crate) fn is_lost(&self) -> bool {
+        if crate::x11_errors::is_lost(self.display) {
+            return true;
+        }
+        let fd = unsafe { XConnectionNumber(self.display) };
+        let mut pfd = libc::pollfd {
+            fd,
+            events: libc::POLLIN,
+            revents: 0,
+        };
+        if unsafe { libc::poll(&mut pfd, 1, 0) } <= 0 {
+            return false;
+        }
+        if pfd.revents & (libc::POLLHUP | libc::POLLERR | libc::POLLNVAL) != 0 {
+            return true;
+        }
+        // Readable: queued events, or a zero-length read at EOF
+        let mut byte = 0u8;
+        let n = unsafe {
+            libc::recv(
+                fd,
+                &mut byte as *mut u8 as *mut libc::c_void,
                 1,
-            );
+                libc::MSG_PEEK | libc::MSG_DONTWAIT,
+            )
+        };
+        n == 0
+    }
 
-            let mut list_prop: *mut c_uchar = ptr::null_mut();
-            let mut nitems: c_ulong = 0;
-            let mut got = false;
-            for atom in [net_client_list_stacking, net_client_list] {
-                let mut actual_type: Atom = 0;
-                let mut actual_format: c_int = 0;
-                let mut bytes_after: c_ulong = 0;
-                XGetWindowProperty(
-                    display,
-                    root,
-                    atom,
-                    0,
-                    4096,
-                    0,
-                    AnyPropertyType as u64,
-                    &mut actual_type,
-                    &mut actual_format,
-                    &mut nitems,
-                    &mut bytes_after,
-                    &mut list_prop,
-                );
-                if !list_prop.is_null() && nitems > 0 {
-                    got = true;
-                    break;
-                }
-                if !list_prop.is_null() {
-                    XFree(list_prop as *mut _);
-                    list_prop = ptr::null_mut();
-                }
-            }
+    // Closing a dead connection would hit the I/O error path too, so only the
+    // socket is closed and the Display is leaked.
+    pub(crate) fn abandon(self) {
+        crate::x11_errors::forget(self.display);
+        unsafe { libc::close(XConnectionNumber(self.display)) };
+        std::mem::forget(self);
+    }
 
-            if !got {
-                // Fallback to XQueryTree path
-                return Self::find_window_by_query_tree(display, target);
-            }
+    // Runs f in an error trap. BadWindow about `window` becomes WindowGone.
+    pub(crate) fn checked<T>(
+        &self,
+        window: Window,
+        f: impl FnOnce(&Self) -> T,
+    ) -> BackendResult<T> {
+        trap(self.display, || f(self)).map_err(|error| to_backend_error(window, error))
+    }
 
-            let windows = list_prop as *const Window;
-            let mut candidates: Vec<Candidate> = Vec::new();
-            for i in 0..nitems {
-                let w = *windows.add(i as usize);
-                let title = Self::get_window_title(display, w);
-                let class = Self::get_wm_class(display, w);
-                if matches_app(target, title.as_deref(), class.as_deref()) {
-                    let on_ws = Self::is_on_current_workspace_internal(display, w);
-                    let vis = Self::is_visible_internal(display, w);
-                    candidates.push(Candidate {
-                        window: w,
-                        on_current_ws: on_ws,
-                        visible: vis,
-                    });
-                }
-            }
-            XFree(list_prop as *mut _);
+    pub(crate) fn root(&self) -> Window {
+        unsafe { XDefaultRootWindow(self.display) }
+    }
 
-            select_preferred_window(&candidates).map(|id| id as Window)
+    pub(crate) fn get_property(
+        &self,
+        window: Window,
+        property: Atom,
+        req_type: Atom,
+        length: c_long,
+    ) -> Option<Property> {
+        let mut actual_type: Atom = 0;
+        let mut actual_format: c_int = 0;
+        let mut nitems: c_ulong = 0;
+        let mut bytes_after: c_ulong = 0;
+        let mut data: *mut c_uchar = ptr::null_mut();
+        unsafe {
+            XGetWindowProperty(
+                self.display,
+                window,
+                property,
+                0,
+                length,
+                0,
+                req_type,
+                &mut actual_type,
+                &mut actual_format,
+                &mut nitems,
+                &mut bytes_after,
+                &mut data,
+            );
         }
+        if data.is_null() {
+            return None;
+        }
+        let prop = Property {
+            data,
+            nitems: nitems as usize,
+            format: actual_format,
+        };
+        if nitems == 0 {
+            return None;
+        }
+        Some(prop)
+    }
+
+    fn find_window(&self, target: &str) -> Option<Window> {
+        select_preferred_window(&self.find_candidates(target)).map(|id| id as Window)
+    }
+
+    // Managed clients per EWMH. Prefers the stacking list for better z-order
+    // preference; None if the window manager publishes neither.
+    pub(crate) fn client_list(&self) -> Option<Vec<Window>> {
+        let root = self.root();
+        [
+            self.atoms.net_client_list_stacking,
+            self.atoms.net_client_list,
+        ]
+        .into_iter()
+        .find_map(|atom| self.get_property(root, atom, AnyPropertyType as Atom, 4096))
+        .map(|list| list.longs().to_vec())
     }
 
-    fn find_window_by_query_tree(display: *mut Display, target: &str) -> Option<Window> {
-        let screen_num = unsafe { XDefaultScreen(display) };
-        let mut root = unsafe { XRootWindow(display, screen_num) };
+    fn find_candidates(&self, target: &str) -> Vec<Candidate> {
+        let Some(list) = self.client_list() else {
+            // Fallback to XQueryTree path
+            return self.find_candidates_by_query_tree(target);
+        };
+        list.into_iter()
+            .filter_map(|w| self.candidate(w, target))
+            .collect()
+    }
 
+    fn find_candidates_by_query_tree(&self, target: &str) -> Vec<Candidate> {
+        let mut root = self.root();
+        let mut parent: Window = 0;
         let mut window_count: u32 = 0;
         let mut windows: *mut Window = ptr::null_mut();
         unsafe {
             XQueryTree(
-                display,
+                self.display,
                 root,
                 &mut root,
-                &mut root,
+                &mut parent,
                 &mut windows,
                 &mut window_count,
             );
         }
-
-        let mut candidates: Vec<Candidate> = Vec::new();
-        if !windows.is_null() {
-            for i in 0..window_count {
-                let window = unsafe { *windows.add(i as usize) };
-                let title = Self::get_window_title(display, window);
-                let class = Self::get_wm_class(display, window);
-                if matches_app(target, title.as_deref(), class.as_deref()) {
-                    let on_ws = Self::is_on_current_workspace_internal(display, window);
-                    let vis = Self::is_visible_internal(display, window);
-                    candidates.push(Candidate {
-                        window,
-                        on_current_ws: on_ws,
-                        visible: vis,
-                    });
-                }
-            }
+        if windows.is_null() {
+            return Vec::new();
         }
-        if !windows.is_null() {
-            unsafe { XFree(windows as *mut _) };
+        let candidates = unsafe { std::slice::from_raw_parts(windows, window_count as usize) }
+            .iter()
+            .filter_map(|&w| self.candidate(w, target))
+            .collect();
+        unsafe { XFree(windows as *mut _) };
+        candidates
+    }
+
+    // Windows destroyed while being read are skipped.
+    fn candidate(&self, window: Window, target: &str) -> Option<Candidate> {
+        self.checked(window, |c| c.read_candidate(window, target))
+            .ok()
+            .flatten()
+    }
+
+    fn read_candidate(&self, window: Window, target: &str) -> Option<Candidate> {
+        let title = self.get_window_title(window);
+        let class = self.get_wm_class(window);
+        if !matches_app(target, title.as_deref(), class.as_deref()) {
+            return None;
         }
-        select_preferred_window(&candidates).map(|id| id as Window)
+        Some(Candidate {
+            window,
+            on_current_ws: self.is_on_current_workspace(window),
+            visible: self.is_visible(window),
+        })
     }
 
-    fn get_window_title(display: *mut Display, window: Window) -> Option<String> {
-        unsafe {
-            // Try _NET_WM_NAME (UTF8)
-            let net_wm_name =
-                XInternAtom(display, CString::new("_NET_WM_NAME").unwrap().as_ptr(), 1);
-            let utf8 = XInternAtom(display, CString::new("UTF8_STRING").unwrap().as_ptr(), 1);
-            let mut actual_type: Atom = 0;
-            let mut actual_format: c_int = 0;
-            let mut nitems: c_ulong = 0;
-            let mut bytes_after: c_ulong = 0;
-            let mut prop: *mut c_uchar = ptr::null_mut();
-            XGetWindowProperty(
-                display,
-                window,
-                net_wm_name,
-                0,
-                1024,
-                0,
-                utf8,
-                &mut actual_type,
-                &mut actual_format,
-                &mut nitems,
-                &mut bytes_after,
-                &mut prop,
-            );
-            if !prop.is_null() && nitems > 0 {
-                let slice = std::slice::from_raw_parts(prop as *const u8, nitems as usize);
-                let title = String::from_utf8_lossy(slice).into_owned();
-                XFree(prop as *mut _);
-                return Some(title);
-            }
-            if !prop.is_null() {
-                XFree(prop as *mut _);
-            }
+    pub(crate) fn get_window_title(&self, window: Window) -> Option<String> {
+        // Try _NET_WM_NAME (UTF8)
+        if let Some(prop) =
+            self.get_property(window, self.atoms.net_wm_name, self.atoms.utf8_string, 1024)
+        {
+            return Some(String::from_utf8_lossy(prop.bytes()).into_owned());
+        }
 
-            // Fallback: WM_NAME via XFetchName
-            let mut name: *mut i8 = ptr::null_mut();
-            XFetchName(display, window, &mut name);
+        // Fallback: WM_NAME via XFetchName
+        unsafe {
+            let mut name: *mut c_char = ptr::null_mut();
+            XFetchName(self.display, window, &mut name);
             if !name.is_null() {
-                let c_str = CStr::from_ptr(name);
-                let title = c_str.to_string_lossy().into_owned();
+                let title = CStr::from_ptr(name).to_string_lossy().into_owned();
                 XFree(name as *mut _);
                 return Some(title);
             }
@@ -190,245 +435,158 @@ impl X11Backend {
         None
     }
 
-    fn get_wm_class(display: *mut Display, window: Window) -> Option<String> {
+    pub(crate) fn get_wm_class(&self, window: Window) -> Option<String> {
         unsafe {
             let mut class_hint: XClassHint = std::mem::zeroed();
-            if XGetClassHint(display, window, &mut class_hint) != 0 {
-                let res_class = if !class_hint.res_class.is_null() {
-                    Some(
-                        CStr::from_ptr(class_hint.res_class)
-                            .to_string_lossy()
-                            .into_owned(),
-                    )
-                } else {
-                    None
-                };
-                if !class_hint.res_name.is_null() {
-                    XFree(class_hint.res_name as *mut _);
-                }
-                if !class_hint.res_class.is_null() {
-                    XFree(class_hint.res_class as *mut _);
-                }
-                return res_class;
+            if XGetClassHint(self.display, window, &mut class_hint) == 0 {
+                return None;
+            }
+            let res_class = if !class_hint.res_class.is_null() {
+                Some(
+                    CStr::from_ptr(class_hint.res_class)
+                        .to_string_lossy()
+                        .into_owned(),
+                )
+            } else {
+                None
+            };
+            if !class_hint.res_name.is_null() {
+                XFree(class_hint.res_name as *mut _);
+            }
+            if !class_hint.res_class.is_null() {
+                XFree(class_hint.res_class as *mut _);
             }
+            res_class
         }
-        None
     }
 
-    fn is_on_current_workspace_internal(display: *mut Display, window: Window) -> bool {
-        let cstring_net_wm_desktop = CString::new("_NET_WM_DESKTOP").unwrap();
-        let net_wm_desktop = unsafe { XInternAtom(display, cstring_net_wm_desktop.as_ptr(), 1) };
-        let cstring_net_current_desktop = CString::new("_NET_CURRENT_DESKTOP").unwrap();
-        let net_current_desktop =
-            unsafe { XInternAtom(display, cstring_net_current_desktop.as_ptr(), 1) };
-
-        let mut current_desktop: c_ulong = 0;
-        let mut window_desktop: c_ulong = 0;
+    pub(crate) fn current_desktop(&self) -> c_ulong {
+        self.get_property(
+            self.root(),
+            self.atoms.net_current_desktop,
+            AnyPropertyType as Atom,
+            1,
+        )
+        .and_then(|p| p.first_long())
+        .unwrap_or(0)
+    }
 
-        let root = unsafe { XDefaultRootWindow(display) };
+    pub(crate) fn window_desktop(&self, window: Window) -> Option<c_ulong> {
+        self.get_property(
+            window,
+            self.atoms.net_wm_desktop,
+            AnyPropertyType as Atom,
+            1,
+        )
+        .and_then(|p| p.first_long())
+    }
 
-        let mut actual_type_return: Atom = 0;
-        let mut actual_format_return: c_int = 0;
-        let mut nitems_return: c_ulong = 0;
-        let mut bytes_after_return: c_ulong = 0;
-        let mut prop_return: *mut c_uchar = ptr::null_mut();
+    // Set by the client itself, so only as trustworthy as the client.
+    pub(crate) fn window_pid(&self, window: Window) -> Option<u32> {
+        self.get_property(window, self.atoms.net_wm_pid, XA_CARDINAL, 1)
+            .and_then(|p| p.first_long())
+            .and_then(|pid| u32::try_from(pid).ok())
+            .filter(|&pid| pid > 0)
+    }
 
-        unsafe {
-            XGetWindowProperty(
-                display,
-                root,
-                net_current_desktop,
-                0,
-                1,
-                0,
-                AnyPropertyType as u64,
-                &mut actual_type_return,
-                &mut actual_format_return,
-                &mut nitems_return,
-                &mut bytes_after_return,
-                &mut prop_return,
-            );
-        }
+    // _NET_WM_DESKTOP as shown by list-windows; 0xFFFFFFFF means all desktops.
+    fn get_desktop(&self, window: Window) -> Option<String> {
+        self.window_desktop(window).map(|d| {
+            if d == 0xFFFF_FFFF {
+                "all".to_string()
+            } else {
+                d.to_string()
+            }
+        })
+    }
 
-        if !prop_return.is_null() {
-            current_desktop = unsafe { *(prop_return as *mut c_ulong) };
-            unsafe { XFree(prop_return as *mut _) };
-        }
+    // Sticky windows (0xFFFFFFFF) are on every desktop.
+    fn is_on_current_workspace(&self, window: Window) -> bool {
+        let desktop = self.window_desktop(window).unwrap_or(0);
+        desktop == self.current_desktop() || desktop == 0xFFFF_FFFF
+    }
 
-        unsafe {
-            XGetWindowProperty(
-                display,
-                window,
-                net_wm_desktop,
-                0,
-                1,
-                0,
-                AnyPropertyType as u64,
-                &mut actual_type_return,
-                &mut actual_format_return,
-                &mut nitems_return,
-                &mut bytes_after_return,
-                &mut prop_return,
-            );
-        }
+    fn active_window(&self) -> Option<Window> {
+        self.get_property(
+            self.root(),
+            self.atoms.net_active_window,
+            AnyPropertyType as Atom,
+            1,
+        )
+        .and_then(|p| p.first_long())
+        .filter(|&w| w != 0)
+    }
 
-        if !prop_return.is_null() {
-            window_desktop = unsafe { *(prop_return as *mut c_ulong) };
-            unsafe { XFree(prop_return as *mut _) };
-        }
+    // ICCCM WM_STATE is IconicState for minimized windows.
+    pub(crate) fn is_iconic(&self, window: Window) -> bool {
+        self.get_property(window, self.atoms.wm_state, AnyPropertyType as Atom, 2)
+            .and_then(|p| p.first_long())
+            == Some(ICONIC_STATE)
+    }
 
-        current_desktop == window_desktop
+    pub(crate) fn map_state(&self, window: Window) -> c_int {
+        let mut attributes: XWindowAttributes = unsafe { std::mem::zeroed() };
+        unsafe { XGetWindowAttributes(self.display, window, &mut attributes) };
+        attributes.map_state
     }
 
-    fn is_visible_internal(display: *mut Display, window: Window) -> bool {
+    fn is_visible(&self, window: Window) -> bool {
         // Prefer ICCCM WM_STATE's IconicState to detect minimized windows
-        unsafe {
-            let wm_state_atom = XInternAtom(display, CString::new("WM_STATE").unwrap().as_ptr(), 1);
-            let mut actual_type: Atom = 0;
-            let mut actual_format: c_int = 0;
-            let mut nitems: c_ulong = 0;
-            let mut bytes_after: c_ulong = 0;
-            let mut prop: *mut c_uchar = ptr::null_mut();
-            XGetWindowProperty(
-                display,
-                window,
-                wm_state_atom,
-                0,
-                2,
-                0,
-                AnyPropertyType as u64,
-                &mut actual_type,
-                &mut actual_format,
-                &mut nitems,
-                &mut bytes_after,
-                &mut prop,
-            );
-            if !prop.is_null() && nitems >= 1 {
-                let state = *(prop as *const c_ulong) as c_long;
-                XFree(prop as *mut _);
-                if state == 3 {
-                    // IconicState
-                    return false;
-                }
-            } else if !prop.is_null() {
-                XFree(prop as *mut _);
-            }
-        }
-        let mut attributes: XWindowAttributes = unsafe { std::mem::zeroed() };
-        unsafe { XGetWindowAttributes(display, window, &mut attributes) };
-        attributes.map_state == IsViewable
+        !self.is_iconic(window) && self.map_state(window) == IsViewable
     }
 
-    fn move_to_current_workspace_internal(display: *mut Display, window: Window) {
-        // Read current desktop
-        let net_current_desktop = unsafe {
-            XInternAtom(
-                display,
-                CString::new("_NET_CURRENT_DESKTOP").unwrap().as_ptr(),
-                1,
-            )
-        };
-        let root = unsafe { XDefaultRootWindow(display) };
-        let mut actual_type: Atom = 0;
-        let mut actual_format: c_int = 0;
-        let mut nitems: c_ulong = 0;
-        let mut bytes_after: c_ulong = 0;
-        let mut prop: *mut c_uchar = ptr::null_mut();
-        unsafe {
-            XGetWindowProperty(
-                display,
-                root,
-                net_current_desktop,
-                0,
-                1,
-                0,
-                AnyPropertyType as u64,
-                &mut actual_type,
-                &mut actual_format,
-                &mut nitems,
-                &mut bytes_after,
-                &mut prop,
-            );
-        }
-        let mut current_desktop: c_ulong = 0;
-        if !prop.is_null() {
-            unsafe {
-                current_desktop = *(prop as *mut c_ulong);
-                XFree(prop as *mut _);
-            }
-        }
+    fn move_to_current_workspace(&self, window: Window) {
+        let current_desktop = self.current_desktop();
         // EWMH: send ClientMessage _NET_WM_DESKTOP to move, fallback to direct property
         unsafe {
-            if Self::ewmh_supported(display) {
-                let net_wm_desktop = XInternAtom(
-                    display,
-                    CString::new("_NET_WM_DESKTOP").unwrap().as_ptr(),
-                    1,
-                );
+            if self.ewmh_supported() {
                 let spec = build_net_wm_desktop_message(
                     window,
-                    current_desktop as u64,
-                    net_wm_desktop as u64,
+                    current_desktop,
+                    self.atoms.net_wm_desktop,
                 );
-                Self::send_client_message(display, root, window, spec);
+                self.send_client_message(window, spec);
             } else {
-                let net_wm_desktop = XInternAtom(
-                    display,
-                    CString::new("_NET_WM_DESKTOP").unwrap().as_ptr(),
-                    1,
-                );
                 XChangeProperty(
-                    display,
+                    self.display,
                     window,
-                    net_wm_desktop,
+                    self.atoms.net_wm_desktop,
                     XA_CARDINAL,
                     32,
                     PropModeReplace,
                     &current_desktop as *const c_ulong as *const u8,
                     1,
                 );
-                XFlush(display);
+                XFlush(self.display);
             }
         }
     }
 
-    fn show_internal(display: *mut Display, window: Window) {
+    fn show(&self, window: Window) {
         unsafe {
-            let root = XDefaultRootWindow(display);
-            if Self::ewmh_supported(display) {
-                let net_active = XInternAtom(
-                    display,
-                    CString::new("_NET_ACTIVE_WINDOW").unwrap().as_ptr(),
-                    1,
-                );
-                let spec = build_net_active_window_message(window, net_active as u64);
-                Self::send_client_message(display, root, window, spec);
+            if self.ewmh_supported() {
+                let spec = build_net_active_window_message(window, self.atoms.net_active_window);
+                self.send_client_message(window, spec);
             } else {
-                XMapWindow(display, window);
-                XFlush(display);
+                XMapWindow(self.display, window);
+                XFlush(self.display);
             }
         }
     }
 
-    fn hide_internal(display: *mut Display, window: Window) {
+    fn hide(&self, window: Window) {
         unsafe {
-            if Self::ewmh_supported(display) {
-                let screen = XDefaultScreen(display);
-                XIconifyWindow(display, window, screen);
-                XFlush(display);
+            if self.ewmh_supported() {
+                let screen = XDefaultScreen(self.display);
+                XIconifyWindow(self.display, window, screen);
             } else {
-                XUnmapWindow(display, window);
-                XFlush(display);
+                XUnmapWindow(self.display, window);
             }
+            XFlush(self.display);
         }
     }
 
-    unsafe fn send_client_message(
-        display: *mut Display,
-        root: Window,
-        window: Window,
-        spec: crate::x11_ewmh::ClientMessageSpec,
-    ) {
+    unsafe fn send_client_message(&self, window: Window, spec: crate::x11_ewmh::ClientMessageSpec) {
         let mut xev: XClientMessageEvent = std::mem::zeroed();
         xev.type_ = ClientMessage;
         xev.window = window;
@@ -443,89 +601,245 @@ impl X11Backend {
         ];
         xev.data = ClientMessageData::from(longs);
         XSendEvent(
-            display,
-            root as c_ulong,
+            self.display,
+            self.root(),
             0,
             (SubstructureRedirectMask | SubstructureNotifyMask) as c_long,
             &mut xev as *mut XClientMessageEvent as *mut XEvent,
         );
-        XFlush(display);
+        XFlush(self.display);
     }
 
-    unsafe fn ewmh_supported(display: *mut Display) -> bool {
-        let root = XDefaultRootWindow(display);
-        let net_supported =
-            XInternAtom(display, CString::new("_NET_SUPPORTED").unwrap().as_ptr(), 1);
-        let mut actual_type: Atom = 0;
-        let mut actual_format: c_int = 0;
-        let mut nitems: c_ulong = 0;
-        let mut bytes_after: c_ulong = 0;
-        let mut prop: *mut c_uchar = std::ptr::null_mut();
-        XGetWindowProperty(
-            display,
-            root,
-            net_supported,
-            0,
+    // The name on the window manager's _NET_SUPPORTING_WM_CHECK window; None
+    // without an EWMH window manager.
+    fn wm_name(&self) -> Option<String> {
+        let check = self
+            .get_property(
+                self.root(),
+                self.atoms.net_supporting_wm_check,
+                XA_WINDOW,
+                1,
+            )?
+            .first_long()? as Window;
+        // Left behind by a window manager that has exited
+        self.checked(check, |conn| conn.get_window_title(check))
+            .ok()
+            .flatten()
+    }
+
+    // Read on every use: the window manager may be replaced while we run.
+    fn ewmh_supported(&self) -> bool {
+        let Some(prop) = self.get_property(
+            self.root(),
+            self.atoms.net_supported,
+            AnyPropertyType as Atom,
             4096,
-            0,
-            AnyPropertyType as u64,
-            &mut actual_type,
-            &mut actual_format,
-            &mut nitems,
-            &mut bytes_after,
-            &mut prop,
-        );
-        if prop.is_null() || nitems == 0 {
+        ) else {
             return false;
-        }
-        let slice = std::slice::from_raw_parts(prop as *const c_ulong, nitems as usize);
-        let required = [
-            XInternAtom(
-                display,
-                CString::new("_NET_WM_DESKTOP").unwrap().as_ptr(),
-                1,
-            ) as u64,
-            XInternAtom(
-                display,
-                CString::new("_NET_ACTIVE_WINDOW").unwrap().as_ptr(),
-                1,
-            ) as u64,
-        ];
-        let supported: Vec<u64> = slice.to_vec();
-        let ok = crate::x11_ewmh::have_atoms(&supported, &required);
-        XFree(prop as *mut _);
-        ok
+        };
+        let required = [self.atoms.net_wm_desktop, self.atoms.net_active_window];
+        crate::x11_ewmh::have_atoms(prop.longs(), &required)
+    }
+}
+
+fn to_backend_error(window: Window, error: XError) -> BackendError {
+    if error.is_bad_window() && error.resource == window {
+        BackendError::WindowGone(window)
+    } else {
+        BackendError::Protocol(error.to_string())
     }
 }
 
 impl WindowBackend for X11Backend {
-    fn find_window(&mut self, app_name: &str) -> Option<u64> {
-        Self::with_display(|d| Self::find_window_internal(d, app_name)).flatten()
+    fn find_window(&mut self, app_name: &str) -> BackendResult<Option<u64>> {
+        if let Some(found) =
+            self.with_model(|m| Some(select_preferred_window(&m.candidates(app_name))))
+        {
+            return Ok(found);
+        }
+        self.with_connection(|c| c.find_window(app_name))
     }
 
-    fn is_on_current_workspace(&mut self, window: u64) -> bool {
-        Self::with_display(|d| Self::is_on_current_workspace_internal(d, window as Window))
-            .unwrap_or(false)
+    fn find_windows(&mut self, app_name: &str) -> BackendResult<Vec<u64>> {
+        let candidates = match self.with_model(|m| Some(m.candidates(app_name))) {
+            Some(candidates) => candidates,
+            None => self.with_connection(|c| c.find_candidates(app_name))?,
+        };
+        Ok(candidates.into_iter().map(|c| c.window).collect())
     }
 
-    fn is_visible(&mut self, window: u64) -> bool {
-        Self::with_display(|d| Self::is_visible_internal(d, window as Window)).unwrap_or(false)
+    fn list_windows(&mut self, app_name: &str) -> BackendResult<Vec<WindowInfo>> {
+        if let Some(listed) = self.with_model(|m| Some(m.list(app_name))) {
+            return Ok(listed);
+        }
+        self.with_connection(|c| {
+            let active = c.active_window();
+            c.find_candidates(app_name)
+                .into_iter()
+                .map(|cand| WindowInfo {
+                    id: cand.window,
+                    class: c.get_wm_class(cand.window),
+                    title: c.get_window_title(cand.window),
+                    workspace: c.get_desktop(cand.window),
+                    on_current_ws: cand.on_current_ws,
+                    visible: cand.visible,
+                    focused: active == Some(cand.window),
+                    pid: c.window_pid(cand.window),
+                })
+                .collect()
+        })
     }
 
-    fn move_to_current_workspace(&mut self, window: u64) {
-        let _ =
-            Self::with_display(|d| Self::move_to_current_workspace_internal(d, window as Window));
+    fn is_on_current_workspace(&mut self, window: u64) -> BackendResult<bool> {
+        if let Some(on_current) = self.tracked(window, |m| m.is_on_current_desktop(window)) {
+            return on_current;
+        }
+        self.on_window(window, |c, w| c.is_on_current_workspace(w))
+    }
+
+    fn is_visible(&mut self, window: u64) -> BackendResult<bool> {
+        if let Some(visible) = self.tracked(window, |m| m.is_visible(window)) {
+            return visible;
+        }
+        self.on_window(window, |c, w| c.is_visible(w))
+    }
+
+    fn move_to_current_workspace(&mut self, window: u64) -> BackendResult<()> {
+        self.check_exists(window)?;
+        self.on_window(window, |c, w| c.move_to_current_workspace(w))
+    }
+
+    fn show(&mut self, window: u64) -> BackendResult<()> {
+        self.check_exists(window)?;
+        self.on_window(window, |c, w| c.show(w))
     }
 
-    fn show(&mut self, window: u64) {
-        let _ = Self::with_display(|d| Self::show_internal(d, window as Window));
+    fn hide(&mut self, window: u64) -> BackendResult<()> {
+        self.check_exists(window)?;
+        self.on_window(window, |c, w| c.hide(w))
     }
 
-    fn hide(&mut self, window: u64) {
-        let _ = Self::with_display(|d| Self::hide_internal(d, window as Window));
+    fn launch_app(&mut self, app_path: &str) -> BackendResult<u32> {
+        spawn_app(app_path)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{BufRead, BufReader};
+    use std::process::{Child, Command, Stdio};
+
+    // A private Xvfb. Tests that need one are #[ignore]d so a missing Xvfb
+    // shows up as ignored, not passed; run them with `cargo test -- --ignored`.
+    struct Xvfb(Child, String);
+
+    impl Xvfb {
+        fn start() -> Self {
+            Self::spawn().expect("Xvfb not found; install it to run this test")
+        }
+
+        fn spawn() -> Option<Self> {
+            let mut child = Command::new("Xvfb")
+                .args(["-displayfd", "1", "-nolisten", "tcp"])
+                .stdout(Stdio::piped())
+                .stderr(Stdio::null())
+                .spawn()
+                .ok()?;
+            let mut line = String::new();
+            BufReader::new(child.stdout.take()?)
+                .read_line(&mut line)
+                .ok()?;
+            Some(Self(child, format!(":{}", line.trim())))
+        }
     }
 
-    fn launch_app(&mut self, app_path: &str) {
-        let _ = Command::new(app_path).spawn();
+    impl Drop for Xvfb {
+        fn drop(&mut self) {
+            let _ = self.0.kill();
+            let _ = self.0.wait();
+        }
+    }
+
+    #[test]
+    #[ignore = "needs Xvfb"]
+    fn destroyed_window_is_an_error_not_an_exit() {
+        let xvfb = Xvfb::start();
+        let conn = Connection::connect(Some(&xvfb.1)).unwrap();
+        let (live, gone) = unsafe {
+            let create = || XCreateSimpleWindow(conn.display, conn.root(), 0, 0, 10, 10, 0, 0, 0);
+            let (live, gone) = (create(), create());
+            XDestroyWindow(conn.display, gone);
+            XSync(conn.display, 0);
+            (live, gone)
+        };
+
+        assert_eq!(conn.checked(live, |c| c.is_visible(live)).ok(), Some(false));
+        assert!(matches!(
+            conn.checked(gone, |c| c.is_visible(gone)),
+            Err(BackendError::WindowGone(w)) if w == gone
+        ));
+        assert!(matches!(
+            conn.checked(gone, |c| c.hide(gone)),
+            Err(BackendError::WindowGone(_))
+        ));
+        assert!(conn.candidate(gone, "anything").is_none());
+        // The connection is still usable afterwards
+        assert!(conn.checked(live, |c| c.map_state(live)).is_ok());
+    }
+
+    #[test]
+    #[ignore = "needs Xvfb"]
+    fn lost_server_is_noticed_not_an_exit() {
+        let mut xvfb = Xvfb::start();
+        let conn = Connection::connect(Some(&xvfb.1)).unwrap();
+        let _ = xvfb.0.kill();
+        let _ = xvfb.0.wait();
+        // Xlib's default would exit the test process here
+        assert_eq!(conn.wm_name(), None);
+        assert!(conn.is_lost());
+        drop(conn);
+    }
+
+    #[test]
+    #[ignore = "needs Xvfb"]
+    fn wm_name_follows_the_supporting_wm_check() {
+        let xvfb = Xvfb::start();
+        let conn = Connection::connect(Some(&xvfb.1)).unwrap();
+        assert_eq!(conn.wm_name(), None);
+
+        let point_check_at = |window: Window| unsafe {
+            XChangeProperty(
+                conn.display,
+                conn.root(),
+                conn.atoms.net_supporting_wm_check,
+                XA_WINDOW,
+                32,
+                PropModeReplace,
+                &window as *const Window as *const c_uchar,
+                1,
+            );
+        };
+        let check = unsafe {
+            let check = XCreateSimpleWindow(conn.display, conn.root(), 0, 0, 1, 1, 0, 0, 0);
+            let name = b"testwm";
+            XChangeProperty(
+                conn.display,
+                check,
+                conn.atoms.net_wm_name,
+                conn.atoms.utf8_string,
+                8,
+                PropModeReplace,
+                name.as_ptr(),
+                name.len() as c_int,
+            );
+            check
+        };
+        point_check_at(check);
+        assert_eq!(conn.wm_name().as_deref(), Some("testwm"));
+
+        // The window manager exited without clearing the property
+        unsafe { XDestroyWindow(conn.display, check) };
+        assert_eq!(conn.wm_name(), None);
     }
 }
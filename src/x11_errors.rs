@@ -0,0 +1,157 @@
+// Xlib reports protocol errors (BadWindow for a window destroyed since it was
+// listed, ...) asynchronously through a process-wide handler whose default
+// exits the process. This installs one that never exits and lets a caller
+// trap the errors caused by a block of requests and get them back as a Result.
+// A lost server connection (an I/O error) also exits by default; `survive`
+// keeps a display's I/O errors from doing so.
+use std::collections::{HashMap, HashSet};
+use std::ffi::CStr;
+use std::fmt;
+use std::os::raw::{c_char, c_void};
+use std::sync::{Mutex, Once};
+
+use core::ffi::c_int;
+use x11::xlib::*;
+
+// Since libX11 1.7, not bound by the x11 crate. Called after the I/O error
+// handler instead of exit(); if it returns, the display's requests fail from
+// then on without reaching the server.
+extern "C" {
+    fn XSetIOErrorExitHandler(
+        display: *mut Display,
+        handler: Option<unsafe extern "C" fn(*mut Display, *mut c_void)>,
+        user_data: *mut c_void,
+    );
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct XError {
+    pub error_code: u8,
+    pub request_code: u8,
+    pub resource: u64,
+    // As given by XGetErrorText, e.g. "BadWindow (invalid Window parameter)"
+    pub text: String,
+}
+
+impl XError {
+    pub fn is_bad_window(&self) -> bool {
+        self.error_code == BadWindow
+    }
+}
+
+impl fmt::Display for XError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} (request {}, resource 0x{:08x})",
+            self.text, self.request_code, self.resource
+        )
+    }
+}
+
+// Displays with a trap in progress, keyed by address, and the first error
+// each has caught.
+static TRAPS: Mutex<Option<HashMap<usize, Option<XError>>>> = Mutex::new(None);
+static INSTALL: Once = Once::new();
+// Displays that hit an I/O error, by address.
+static LOST: Mutex<Option<HashSet<usize>>> = Mutex::new(None);
+
+// Replaces Xlib's default handlers for the whole process. Safe to call again.
+pub fn install() {
+    INSTALL.call_once(|| unsafe {
+        XSetErrorHandler(Some(on_error));
+        XSetIOErrorHandler(Some(on_io_error));
+    });
+}
+
+// Marks `display` lost on an I/O error instead of exiting. The caller then
+// drops it with `forget` and opens a new one.
+pub fn survive(display: *mut Display) {
+    install();
+    unsafe { XSetIOErrorExitHandler(display, Some(on_io_exit), std::ptr::null_mut()) };
+}
+
+pub fn is_lost(display: *mut Display) -> bool {
+    lost()
+        .as_ref()
+        .is_some_and(|lost| lost.contains(&(display as usize)))
+}
+
+// Before the display is closed or leaked, so a new one at the same address
+// does not count as lost.
+pub fn forget(display: *mut Display) {
+    if let Some(lost) = lost().as_mut() {
+        lost.remove(&(display as usize));
+    }
+}
+
+fn lost() -> std::sync::MutexGuard<'static, Option<HashSet<usize>>> {
+    LOST.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+unsafe extern "C" fn on_io_error(_display: *mut Display) -> c_int {
+    eprintln!("X11 connection to the server lost.");
+    0
+}
+
+unsafe extern "C" fn on_io_exit(display: *mut Display, _data: *mut c_void) {
+    lost()
+        .get_or_insert_with(HashSet::new)
+        .insert(display as usize);
+}
+
+// Runs f and waits for the server to process every request it made. Each
+// display belongs to one thread, so traps on different displays can overlap;
+// traps on one display must not nest.
+pub fn trap<T>(display: *mut Display, f: impl FnOnce() -> T) -> Result<T, XError> {
+    install();
+    // Errors from earlier, untrapped requests are not this block's
+    unsafe { XSync(display, 0) };
+    traps()
+        .get_or_insert_with(HashMap::new)
+        .insert(display as usize, None);
+    let value = f();
+    unsafe { XSync(display, 0) };
+    let caught = traps()
+        .as_mut()
+        .and_then(|traps| traps.remove(&(display as usize)))
+        .flatten();
+    match caught {
+        Some(error) => Err(error),
+        None => Ok(value),
+    }
+}
+
+fn traps() -> std::sync::MutexGuard<'static, Option<HashMap<usize, Option<XError>>>> {
+    TRAPS.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+unsafe extern "C" fn on_error(display: *mut Display, event: *mut XErrorEvent) -> c_int {
+    let event = &*event;
+    let mut buffer = [0 as c_char; 256];
+    XGetErrorText(
+        display,
+        event.error_code as c_int,
+        buffer.as_mut_ptr(),
+        buffer.len() as c_int,
+    );
+    let error = XError {
+        error_code: event.error_code,
+        request_code: event.request_code,
+        resource: event.resourceid,
+        text: CStr::from_ptr(buffer.as_ptr())
+            .to_string_lossy()
+            .into_owned(),
+    };
+    match traps()
+        .as_mut()
+        .and_then(|traps| traps.get_mut(&(display as usize)))
+    {
+        Some(slot) => {
+            slot.get_or_insert(error);
+        }
+        // Outside a trap: log it, but keep running
+        None => eprintln!("X11 error: {}", error),
+    }
+    0
+}
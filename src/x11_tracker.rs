@@ -0,0 +1,430 @@
+// Event-driven model of the X11 client windows. A thread on its own connection
+// listens for PropertyNotify on the root and on every managed client, so a
+// hotkey can be answered from memory instead of with a round trip per window.
+use crate::common_backend::WindowInfo;
+use crate::matching::{matches_app, Candidate};
+use crate::x11_backend::Connection;
+use crate::x11_errors::trap;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{mpsc, Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+
+use core::ffi::c_int;
+use x11::xlib::*;
+
+// _NET_WM_DESKTOP of a window shown on all desktops
+const ALL_DESKTOPS: u64 = 0xFFFF_FFFF;
+
+// XEventsQueued modes, from Xlib.h
+const QUEUED_ALREADY: c_int = 0;
+const QUEUED_AFTER_READING: c_int = 1;
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct TrackedWindow {
+    pub class: Option<String>,
+    pub title: Option<String>,
+    pub desktop: Option<u64>,
+    // WM_STATE is IconicState (minimized)
+    pub iconic: bool,
+    pub mapped: bool,
+    // _NET_WM_PID
+    pub pid: Option<u32>,
+}
+
+// The windows the window manager lists in _NET_CLIENT_LIST, in that order.
+#[derive(Debug, Default)]
+pub struct WindowModel {
+    order: Vec<u64>,
+    windows: HashMap<u64, TrackedWindow>,
+    current_desktop: u64,
+    active: Option<u64>,
+}
+
+impl WindowModel {
+    // Replaces the client list. Returns the windows not seen before, which
+    // still need to be read and inserted.
+    pub fn set_clients(&mut self, clients: Vec<u64>) -> Vec<u64> {
+        self.windows.retain(|id, _| clients.contains(id));
+        let new = clients
+            .iter()
+            .copied()
+            .filter(|id| !self.windows.contains_key(id))
+            .collect();
+        self.order = clients;
+        new
+    }
+
+    // Ignored for windows that have left the client list in the meantime.
+    pub fn insert(&mut self, id: u64, window: TrackedWindow) {
+        if self.order.contains(&id) {
+            self.windows.insert(id, window);
+        }
+    }
+
+    pub fn window_mut(&mut self, id: u64) -> Option<&mut TrackedWindow> {
+        self.windows.get_mut(&id)
+    }
+
+    pub fn remove(&mut self, id: u64) {
+        self.windows.remove(&id);
+        self.order.retain(|&w| w != id);
+    }
+
+    pub fn set_current_desktop(&mut self, desktop: u64) {
+        self.current_desktop = desktop;
+    }
+
+    pub fn set_active(&mut self, window: Option<u64>) {
+        self.active = window.filter(|&w| w != 0);
+    }
+
+    // None if the window is not tracked. Same rules as the direct lookup: no
+    // _NET_WM_DESKTOP means desktop 0, and sticky windows are on every desktop.
+    pub fn is_on_current_desktop(&self, id: u64) -> Option<bool> {
+        let window = self.windows.get(&id)?;
+        let desktop = window.desktop.unwrap_or(0);
+        Some(desktop == self.current_desktop || desktop == ALL_DESKTOPS)
+    }
+
+    pub fn is_visible(&self, id: u64) -> Option<bool> {
+        let window = self.windows.get(&id)?;
+        Some(window.mapped && !window.iconic && self.is_on_current_desktop(id)?)
+    }
+
+    pub fn candidates(&self, target: &str) -> Vec<Candidate> {
+        self.matching(target)
+            .map(|(id, _)| Candidate {
+                window: id,
+                on_current_ws: self.is_on_current_desktop(id) == Some(true),
+                visible: self.is_visible(id) == Some(true),
+            })
+            .collect()
+    }
+
+    pub fn list(&self, target: &str) -> Vec<WindowInfo> {
+        self.matching(target)
+            .map(|(id, w)| WindowInfo {
+                id,
+                class: w.class.clone(),
+                title: w.title.clone(),
+                workspace: w.desktop.map(|d| match d {
+                    ALL_DESKTOPS => "all".to_string(),
+                    d => d.to_string(),
+                }),
+                on_current_ws: self.is_on_current_desktop(id) == Some(true),
+                visible: self.is_visible(id) == Some(true),
+                focused: self.active == Some(id),
+                pid: w.pid,
+            })
+            .collect()
+    }
+
+    fn matching<'a>(&'a self, target: &'a str) -> impl Iterator<Item = (u64, &'a TrackedWindow)> {
+        self.order.iter().filter_map(move |id| {
+            let w = self.windows.get(id)?;
+            matches_app(target, w.title.as_deref(), w.class.as_deref()).then_some((*id, w))
+        })
+    }
+}
+
+// Handle to the tracking thread. The model is only trustworthy while alive.
+pub struct Tracker {
+    model: Arc<Mutex<WindowModel>>,
+    alive: Arc<AtomicBool>,
+}
+
+impl Tracker {
+    // Opens a second connection and waits for the initial sync. None if the
+    // display cannot be opened or the window manager keeps no client list.
+    pub fn start() -> Option<Self> {
+        let model = Arc::new(Mutex::new(WindowModel::default()));
+        let alive = Arc::new(AtomicBool::new(false));
+        let (ready_tx, ready_rx) = mpsc::channel();
+        let (thread_model, thread_alive) = (model.clone(), alive.clone());
+        thread::spawn(move || {
+            let Some(conn) = Connection::open() else {
+                let _ = ready_tx.send(false);
+                return;
+            };
+            let mut watcher = Watcher {
+                conn,
+                model: thread_model,
+            };
+            if !watcher.sync() {
+                let _ = ready_tx.send(false);
+                return;
+            }
+            thread_alive.store(true, Ordering::SeqCst);
+            let _ = ready_tx.send(true);
+            watcher.run();
+            thread_alive.store(false, Ordering::SeqCst);
+            eprintln!("X11 window tracker stopped.");
+            // The server is gone; closing the display would only run into I/O errors
+            watcher.conn.abandon();
+        });
+        match ready_rx.recv_timeout(Duration::from_secs(2)) {
+            Ok(true) => Some(Self { model, alive }),
+            _ => None,
+        }
+    }
+
+    pub fn is_alive(&self) -> bool {
+        self.alive.load(Ordering::SeqCst)
+    }
+
+    // Runs f on the model, or returns None if the tracker has stopped.
+    pub fn with_model<T>(&self, f: impl FnOnce(&WindowModel) -> T) -> Option<T> {
+        if !self.is_alive() {
+            return None;
+        }
+        let model = self.model.lock().unwrap_or_else(|e| e.into_inner());
+        Some(f(&model))
+    }
+}
+
+struct Watcher {
+    conn: Connection,
+    model: Arc<Mutex<WindowModel>>,
+}
+
+impl Watcher {
+    fn model(&self) -> std::sync::MutexGuard<'_, WindowModel> {
+        self.model.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn sync(&mut self) -> bool {
+        let root = self.conn.root();
+        unsafe { XSelectInput(self.conn.display, root, PropertyChangeMask) };
+        let Some(clients) = self.conn.client_list() else {
+            return false;
+        };
+        let current = self.conn.current_desktop();
+        let active = self.read_active();
+        {
+            let mut model = self.model();
+            model.set_current_desktop(current);
+            model.set_active(active);
+        }
+        self.update_clients(clients);
+        true
+    }
+
+    // Selects events on new clients before reading them, so no change between
+    // the read and the selection can be missed. A client destroyed before it
+    // could be read is left out; its DestroyNotify may never come.
+    fn update_clients(&mut self, clients: Vec<u64>) {
+        let new = self.model().set_clients(clients);
+        for id in new {
+            let conn = &self.conn;
+            let read = trap(conn.display, || {
+                unsafe { XSelectInput(conn.display, id, PropertyChangeMask | StructureNotifyMask) };
+                TrackedWindow {
+                    class: conn.get_wm_class(id),
+                    title: conn.get_window_title(id),
+                    desktop: conn.window_desktop(id),
+                    iconic: conn.is_iconic(id),
+                    mapped: conn.map_state(id) != IsUnmapped,
+                    pid: conn.window_pid(id),
+                }
+            });
+            match read {
+                Ok(window) => self.model().insert(id, window),
+                Err(_) => self.model().remove(id),
+            }
+        }
+    }
+
+    fn read_active(&self) -> Option<u64> {
+        self.conn
+            .get_property(
+                self.conn.root(),
+                self.conn.atoms.net_active_window,
+                AnyPropertyType as Atom,
+                1,
+            )
+            .and_then(|p| p.first_long())
+    }
+
+    // Until the server goes away. Events Xlib queued while waiting for replies
+    // are handled first; the socket is only read once it is known to be open.
+    fn run(&mut self) {
+        let display = self.conn.display;
+        let fd = unsafe { XConnectionNumber(display) };
+        loop {
+            while unsafe { XEventsQueued(display, QUEUED_ALREADY) } > 0 {
+                let mut event: XEvent = unsafe { std::mem::zeroed() };
+                unsafe { XNextEvent(display, &mut event) };
+                self.handle(&event);
+            }
+            unsafe { XFlush(display) };
+            let mut pfd = libc::pollfd {
+                fd,
+                events: libc::POLLIN,
+                revents: 0,
+            };
+            if unsafe { libc::poll(&mut pfd, 1, -1) } < 0 {
+                continue;
+            }
+            if self.conn.is_lost() {
+                return;
+            }
+            unsafe { XEventsQueued(display, QUEUED_AFTER_READING) };
+        }
+    }
+
+    // Errors here mean the window went away meanwhile; the DestroyNotify or
+    // client list change that follows takes care of the model.
+    fn handle(&mut self, event: &XEvent) {
+        let display = self.conn.display;
+        let _ = trap(display, || self.dispatch(event));
+    }
+
+    fn dispatch(&mut self, event: &XEvent) {
+        let kind = event.get_type();
+        if kind == PropertyNotify {
+            self.on_property(From::from(event));
+        } else if kind == MapNotify {
+            let e: XMapEvent = From::from(event);
+            self.update(e.window, |w| w.mapped = true);
+        } else if kind == UnmapNotify {
+            let e: XUnmapEvent = From::from(event);
+            self.update(e.window, |w| w.mapped = false);
+        } else if kind == DestroyNotify {
+            let e: XDestroyWindowEvent = From::from(event);
+            self.model().remove(e.window);
+        }
+    }
+
+    fn on_property(&mut self, e: XPropertyEvent) {
+        let atoms = &self.conn.atoms;
+        if e.window == self.conn.root() {
+            if e.atom == atoms.net_client_list || e.atom == atoms.net_client_list_stacking {
+                if let Some(clients) = self.conn.client_list() {
+                    self.update_clients(clients);
+                }
+            } else if e.atom == atoms.net_current_desktop {
+                let current = self.conn.current_desktop();
+                self.model().set_current_desktop(current);
+            } else if e.atom == atoms.net_active_window {
+                let active = self.read_active();
+                self.model().set_active(active);
+            }
+        } else if e.atom == atoms.net_wm_name || e.atom == XA_WM_NAME {
+            let title = self.conn.get_window_title(e.window);
+            self.update(e.window, |w| w.title = title);
+        } else if e.atom == XA_WM_CLASS {
+            let class = self.conn.get_wm_class(e.window);
+            self.update(e.window, |w| w.class = class);
+        } else if e.atom == atoms.net_wm_desktop {
+            let desktop = self.conn.window_desktop(e.window);
+            self.update(e.window, |w| w.desktop = desktop);
+        } else if e.atom == atoms.wm_state {
+            let iconic = self.conn.is_iconic(e.window);
+            self.update(e.window, |w| w.iconic = iconic);
+        } else if e.atom == atoms.net_wm_pid {
+            let pid = self.conn.window_pid(e.window);
+            self.update(e.window, |w| w.pid = pid);
+        }
+    }
+
+    fn update(&self, id: u64, f: impl FnOnce(&mut TrackedWindow)) {
+        if let Some(window) = self.model().window_mut(id) {
+            f(window);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn window(class: &str, desktop: u64) -> TrackedWindow {
+        TrackedWindow {
+            class: Some(class.to_string()),
+            title: Some(format!("{class} window")),
+            desktop: Some(desktop),
+            iconic: false,
+            mapped: true,
+            pid: None,
+        }
+    }
+
+    #[test]
+    fn client_list_changes_add_and_drop_windows() {
+        let mut model = WindowModel::default();
+        assert_eq!(model.set_clients(vec![1, 2]), vec![1, 2]);
+        model.insert(1, window("Alacritty", 0));
+        model.insert(2, window("Firefox", 0));
+
+        // Only the new window needs reading; the closed one is forgotten
+        assert_eq!(model.set_clients(vec![2, 3]), vec![3]);
+        assert_eq!(model.is_visible(1), None);
+        assert_eq!(model.is_visible(2), Some(true));
+
+        // A window that left the list before it was read is not inserted
+        model.set_clients(vec![2]);
+        model.insert(3, window("Alacritty", 0));
+        assert!(model.candidates("Alacritty").is_empty());
+
+        model.remove(2);
+        assert!(model.list("Firefox").is_empty());
+    }
+
+    #[test]
+    fn workspace_and_visibility_follow_events() {
+        let mut model = WindowModel::default();
+        model.set_clients(vec![10, 11, 12]);
+        model.insert(10, window("Alacritty", 1));
+        model.insert(11, window("Alacritty", 0));
+        model.insert(
+            12,
+            TrackedWindow {
+                desktop: None,
+                ..window("Alacritty", 0)
+            },
+        );
+
+        let states = |m: &WindowModel| -> Vec<_> {
+            m.candidates("alacritty")
+                .iter()
+                .map(|c| (c.window, c.on_current_ws, c.visible))
+                .collect()
+        };
+        assert_eq!(
+            states(&model),
+            vec![(10, false, false), (11, true, true), (12, true, true)]
+        );
+
+        model.set_current_desktop(1);
+        model.window_mut(10).unwrap().iconic = true;
+        model.window_mut(11).unwrap().desktop = Some(ALL_DESKTOPS);
+        assert_eq!(
+            states(&model),
+            vec![(10, true, false), (11, true, true), (12, false, false)]
+        );
+
+        model.window_mut(11).unwrap().mapped = false;
+        assert_eq!(model.is_visible(11), Some(false));
+    }
+
+    #[test]
+    fn lists_matching_windows_in_client_order() {
+        let mut model = WindowModel::default();
+        model.set_clients(vec![5, 4]);
+        model.insert(4, window("Alacritty", ALL_DESKTOPS));
+        model.insert(5, window("Alacritty", 2));
+        model.set_active(Some(4));
+
+        let listed = model.list("class=alacritty");
+        assert_eq!(listed.iter().map(|w| w.id).collect::<Vec<_>>(), vec![5, 4]);
+        assert_eq!(listed[0].workspace.as_deref(), Some("2"));
+        assert_eq!(listed[1].workspace.as_deref(), Some("all"));
+        assert_eq!(listed[1].title.as_deref(), Some("Alacritty window"));
+        assert!(!listed[0].focused && listed[1].focused);
+        // _NET_ACTIVE_WINDOW is 0 when nothing has focus
+        model.set_active(Some(0));
+        assert!(model.list("Alacritty").iter().all(|w| !w.focused));
+    }
+}
@@ -0,0 +1,640 @@
+use crate::common_backend::{
+    spawn_app, BackendError, BackendResult, Probe, WindowBackend, WindowInfo,
+};
+use crate::matching::{matches_app, select_preferred_window, Candidate};
+use crate::x11_ewmh::{
+    build_net_active_window_message, build_net_wm_desktop_message, have_atoms, ClientMessageSpec,
+};
+use std::env;
+use x11rb::connection::Connection as _;
+use x11rb::cookie::Cookie;
+use x11rb::errors::{ConnectionError, ReplyError};
+use x11rb::properties::{WmClass, WmClassCookie};
+use x11rb::protocol::xproto::{
+    AtomEnum, ClientMessageEvent, ConnectionExt as _, EventMask, GetPropertyReply,
+    GetWindowAttributesReply, MapState, PropMode, Window,
+};
+use x11rb::protocol::ErrorKind;
+use x11rb::rust_connection::RustConnection;
+use x11rb::wrapper::ConnectionExt as _;
+use x11rb::x11_utils::X11Error;
+
+// ICCCM WM_STATE value of a minimized window
+const ICONIC_STATE: u32 = 3;
+// _NET_WM_DESKTOP of a window shown on every desktop
+const ALL_DESKTOPS: u32 = 0xFFFF_FFFF;
+
+x11rb::atom_manager! {
+    Atoms: AtomsCookie {
+        _NET_SUPPORTED,
+        _NET_CLIENT_LIST,
+        _NET_CLIENT_LIST_STACKING,
+        _NET_CURRENT_DESKTOP,
+        _NET_WM_DESKTOP,
+        _NET_ACTIVE_WINDOW,
+        _NET_WM_NAME,
+        _NET_WM_PID,
+        _NET_SUPPORTING_WM_CHECK,
+        UTF8_STRING,
+        WM_STATE,
+        WM_CHANGE_STATE,
+    }
+}
+
+// The X11 backend on x11rb, a pure-Rust X client, as an alternative to the
+// Xlib one (cargo feature "x11rb", `--backend x11rb`). Replies are typed and
+// X errors come back with the request that caused them. A lookup sends the
+// requests for every client window before reading any reply, so it takes one
+// round trip for the root window and one for all clients.
+pub struct X11rbBackend {
+    // None means $DISPLAY
+    display: Option<String>,
+    session: Option<Session>,
+}
+
+struct Session {
+    conn: RustConnection,
+    root: Window,
+    atoms: Atoms,
+}
+
+// What a lookup needs to know about one window.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct WindowProps {
+    window: Window,
+    title: Option<String>,
+    class: Option<String>,
+    desktop: Option<u32>,
+    iconic: bool,
+    viewable: bool,
+    pid: Option<u32>,
+}
+
+impl WindowProps {
+    fn visible(&self) -> bool {
+        !self.iconic && self.viewable
+    }
+
+    // Sticky windows are on every desktop
+    fn on_desktop(&self, current: u32) -> bool {
+        let desktop = self.desktop.unwrap_or(0);
+        desktop == current || desktop == ALL_DESKTOPS
+    }
+}
+
+// Requests sent for one window, answered later.
+struct WindowCookies<'c> {
+    window: Window,
+    net_wm_name: Cookie<'c, RustConnection, GetPropertyReply>,
+    wm_name: Cookie<'c, RustConnection, GetPropertyReply>,
+    wm_class: WmClassCookie<'c, RustConnection>,
+    desktop: Cookie<'c, RustConnection, GetPropertyReply>,
+    wm_state: Cookie<'c, RustConnection, GetPropertyReply>,
+    pid: Cookie<'c, RustConnection, GetPropertyReply>,
+    attributes: Cookie<'c, RustConnection, GetWindowAttributesReply>,
+}
+
+impl WindowCookies<'_> {
+    fn reply(self) -> Result<WindowProps, ReplyError> {
+        let text = |reply: GetPropertyReply| {
+            (reply.format == 8 && !reply.value.is_empty())
+                .then(|| String::from_utf8_lossy(&reply.value).into_owned())
+        };
+        let net_wm_name = text(self.net_wm_name.reply()?);
+        let wm_name = text(self.wm_name.reply()?);
+        // A malformed WM_CLASS is as good as none
+        let class = match self.wm_class.reply() {
+            Err(ReplyError::X11Error(e)) => return Err(ReplyError::X11Error(e)),
+            reply => reply.ok().flatten(),
+        };
+        Ok(WindowProps {
+            window: self.window,
+            title: net_wm_name.or(wm_name),
+            class: class.map(|c| String::from_utf8_lossy(c.class()).into_owned()),
+            desktop: first32(&self.desktop.reply()?),
+            iconic: first32(&self.wm_state.reply()?) == Some(ICONIC_STATE),
+            viewable: self.attributes.reply()?.map_state == MapState::VIEWABLE,
+            pid: first32(&self.pid.reply()?).filter(|&pid| pid > 0),
+        })
+    }
+}
+
+fn first32(reply: &GetPropertyReply) -> Option<u32> {
+    reply.value32()?.next()
+}
+
+impl Session {
+    fn connect(display: Option<&str>) -> Option<Self> {
+        let (conn, screen) = RustConnection::connect(display).ok()?;
+        let root = conn.setup().roots.get(screen)?.root;
+        let atoms = Atoms::new(&conn).ok()?.reply().ok()?;
+        Some(Self { conn, root, atoms })
+    }
+
+    fn get_property(
+        &self,
+        window: Window,
+        property: u32,
+        type_: impl Into<u32>,
+        length: u32,
+    ) -> Result<Cookie<'_, RustConnection, GetPropertyReply>, ConnectionError> {
+        self.conn
+            .get_property(false, window, property, type_.into(), 0, length)
+    }
+
+    fn root_property(&self, property: u32, length: u32) -> Result<GetPropertyReply, ReplyError> {
+        self.get_property(self.root, property, AtomEnum::ANY, length)?
+            .reply()
+    }
+
+    fn request_window(&self, window: Window) -> Result<WindowCookies<'_>, ConnectionError> {
+        Ok(WindowCookies {
+            window,
+            net_wm_name: self.get_property(
+                window,
+                self.atoms._NET_WM_NAME,
+                self.atoms.UTF8_STRING,
+                1024,
+            )?,
+            wm_name: self.get_property(window, AtomEnum::WM_NAME.into(), AtomEnum::ANY, 1024)?,
+            wm_class: WmClass::get(&self.conn, window)?,
+            desktop: self.get_property(
+                window,
+                self.atoms._NET_WM_DESKTOP,
+                AtomEnum::CARDINAL,
+                1,
+            )?,
+            wm_state: self.get_property(window, self.atoms.WM_STATE, self.atoms.WM_STATE, 2)?,
+            pid: self.get_property(window, self.atoms._NET_WM_PID, AtomEnum::CARDINAL, 1)?,
+            attributes: self.conn.get_window_attributes(window)?,
+        })
+    }
+
+    // Managed clients per EWMH, else the root's children. The requests go out
+    // together and the replies are read afterwards.
+    fn client_windows(&self) -> Result<Vec<Window>, ReplyError> {
+        let stacking = self.get_property(
+            self.root,
+            self.atoms._NET_CLIENT_LIST_STACKING,
+            AtomEnum::WINDOW,
+            4096,
+        )?;
+        let list = self.get_property(
+            self.root,
+            self.atoms._NET_CLIENT_LIST,
+            AtomEnum::WINDOW,
+            4096,
+        )?;
+        let tree = self.conn.query_tree(self.root)?;
+        for reply in [stacking.reply()?, list.reply()?] {
+            if let Some(windows) = reply.value32().filter(|_| reply.value_len > 0) {
+                return Ok(windows.collect());
+            }
+        }
+        Ok(tree.reply()?.children)
+    }
+
+    // Every client window with its properties. Windows destroyed while being
+    // read are skipped.
+    fn windows(&self) -> Result<Vec<WindowProps>, ReplyError> {
+        let windows = self.client_windows()?;
+        let cookies = windows
+            .iter()
+            .map(|&w| self.request_window(w))
+            .collect::<Result<Vec<_>, _>>()?;
+        let mut props = Vec::new();
+        for cookie in cookies {
+            match cookie.reply() {
+                Ok(p) => props.push(p),
+                Err(ReplyError::X11Error(e)) if e.error_kind == ErrorKind::Window => {}
+                Err(e) => return Err(e),
+            }
+        }
+        Ok(props)
+    }
+
+    fn window(&self, window: Window) -> Result<WindowProps, ReplyError> {
+        self.request_window(window)?.reply()
+    }
+
+    fn current_desktop(&self) -> Result<u32, ReplyError> {
+        Ok(first32(&self.root_property(self.atoms._NET_CURRENT_DESKTOP, 1)?).unwrap_or(0))
+    }
+
+    fn active_window(&self) -> Result<Option<Window>, ReplyError> {
+        Ok(first32(&self.root_property(self.atoms._NET_ACTIVE_WINDOW, 1)?).filter(|&w| w != 0))
+    }
+
+    // Read on every use: the window manager may be replaced while we run.
+    fn ewmh_supported(&self) -> Result<bool, ReplyError> {
+        let supported = self.root_property(self.atoms._NET_SUPPORTED, 4096)?;
+        let supported: Vec<u64> = supported
+            .value32()
+            .into_iter()
+            .flatten()
+            .map(u64::from)
+            .collect();
+        let required = [self.atoms._NET_WM_DESKTOP, self.atoms._NET_ACTIVE_WINDOW];
+        Ok(have_atoms(&supported, &required.map(u64::from)))
+    }
+
+    // The name on the window manager's _NET_SUPPORTING_WM_CHECK window; None
+    // without an EWMH window manager, or one that has exited.
+    fn wm_name(&self) -> Option<String> {
+        let check = self
+            .get_property(
+                self.root,
+                self.atoms._NET_SUPPORTING_WM_CHECK,
+                AtomEnum::WINDOW,
+                1,
+            )
+            .ok()?
+            .reply()
+            .ok()?;
+        self.window(first32(&check)?).ok()?.title
+    }
+
+    // To the root window, for the window manager; waits for the server to
+    // accept it.
+    fn send_client_message(&self, spec: ClientMessageSpec) -> Result<(), ReplyError> {
+        let event = ClientMessageEvent::new(
+            32,
+            spec.window as Window,
+            spec.message_type_atom as u32,
+            spec.data.map(|l| l as u32),
+        );
+        self.conn
+            .send_event(
+                false,
+                self.root,
+                EventMask::SUBSTRUCTURE_REDIRECT | EventMask::SUBSTRUCTURE_NOTIFY,
+                event,
+            )?
+            .check()
+    }
+
+    fn move_to_current_desktop(&self, window: Window) -> Result<(), ReplyError> {
+        let current = self.current_desktop()?;
+        if self.ewmh_supported()? {
+            let atom = self.atoms._NET_WM_DESKTOP;
+            let spec = build_net_wm_desktop_message(window.into(), current.into(), atom.into());
+            self.send_client_message(spec)
+        } else {
+            self.conn
+                .change_property32(
+                    PropMode::REPLACE,
+                    window,
+                    self.atoms._NET_WM_DESKTOP,
+                    AtomEnum::CARDINAL,
+                    &[current],
+                )?
+                .check()
+        }
+    }
+
+    fn show(&self, window: Window) -> Result<(), ReplyError> {
+        if self.ewmh_supported()? {
+            let atom = self.atoms._NET_ACTIVE_WINDOW;
+            self.send_client_message(build_net_active_window_message(window.into(), atom.into()))
+        } else {
+            self.conn.map_window(window)?.check()
+        }
+    }
+
+    // What XIconifyWindow sends: an ICCCM WM_CHANGE_STATE request
+    fn hide(&self, window: Window) -> Result<(), ReplyError> {
+        if self.ewmh_supported()? {
+            self.send_client_message(ClientMessageSpec {
+                message_type_atom: self.atoms.WM_CHANGE_STATE.into(),
+                window: window.into(),
+                data: [ICONIC_STATE.into(), 0, 0, 0, 0],
+            })
+        } else {
+            self.conn.unmap_window(window)?.check()
+        }
+    }
+}
+
+impl X11rbBackend {
+    pub fn new() -> Self {
+        Self {
+            display: None,
+            session: None,
+        }
+    }
+
+    // Scored like the Xlib backend; on a tie that one is preferred.
+    pub fn probe() -> Probe {
+        let Some(name) = env::var_os("DISPLAY") else {
+            return Probe::none("DISPLAY is not set");
+        };
+        let name = name.to_string_lossy();
+        let Some(session) = Session::connect(None) else {
+            return Probe::none(format!("cannot open display {name}"));
+        };
+        if env::var_os("WAYLAND_DISPLAY").is_some() {
+            return Probe::new(
+                30,
+                format!("{name} is Xwayland; only X11 clients are visible"),
+            );
+        }
+        match session.wm_name() {
+            Some(wm) => Probe::new(70, format!("window manager {wm:?} on {name}")),
+            None => Probe::new(40, format!("no EWMH window manager on {name}")),
+        }
+    }
+
+    // Connects on first use. A lost connection is dropped and the next call
+    // opens a new one, e.g. after the server restarted.
+    fn with_session<T>(
+        &mut self,
+        window: Option<Window>,
+        f: impl FnOnce(&Session) -> Result<T, ReplyError>,
+    ) -> BackendResult<T> {
+        if self.session.is_none() {
+            self.session = Session::connect(self.display.as_deref());
+        }
+        let session = self
+            .session
+            .as_ref()
+            .ok_or(BackendError::DisplayUnavailable)?;
+        match f(session) {
+            Ok(value) => Ok(value),
+            Err(ReplyError::ConnectionError(error)) => {
+                eprintln!("X11 connection lost: {error}");
+                self.session = None;
+                Err(BackendError::DisplayUnavailable)
+            }
+            Err(ReplyError::X11Error(error)) => Err(to_backend_error(window, error)),
+        }
+    }
+
+    fn matching(&mut self, app_name: &str) -> BackendResult<Vec<WindowProps>> {
+        self.with_session(None, |s| {
+            Ok(s.windows()?
+                .into_iter()
+                .filter(|p| matches_app(app_name, p.title.as_deref(), p.class.as_deref()))
+                .collect())
+        })
+    }
+
+    fn candidates(&mut self, app_name: &str) -> BackendResult<Vec<Candidate>> {
+        let matching = self.matching(app_name)?;
+        let current = self.with_session(None, Session::current_desktop)?;
+        Ok(matching
+            .into_iter()
+            .map(|p| Candidate {
+                window: p.window.into(),
+                on_current_ws: p.on_desktop(current),
+                visible: p.visible(),
+            })
+            .collect())
+    }
+
+    fn on_window<T>(
+        &mut self,
+        window: u64,
+        f: impl FnOnce(&Session, Window) -> Result<T, ReplyError>,
+    ) -> BackendResult<T> {
+        // X11 window ids are 29 bits
+        let id = Window::try_from(window).map_err(|_| BackendError::WindowGone(window))?;
+        self.with_session(Some(id), |s| f(s, id))
+    }
+}
+
+// BadWindow about the window being acted on means it has closed.
+fn to_backend_error(window: Option<Window>, error: X11Error) -> BackendError {
+    match window {
+        Some(window) if error.error_kind == ErrorKind::Window && error.bad_value == window => {
+            BackendError::WindowGone(window.into())
+        }
+        _ => BackendError::Protocol(format!(
+            "{:?} error in {} (resource 0x{:08x})",
+            error.error_kind,
+            error.request_name.unwrap_or("a request"),
+            error.bad_value
+        )),
+    }
+}
+
+impl WindowBackend for X11rbBackend {
+    fn find_window(&mut self, app_name: &str) -> BackendResult<Option<u64>> {
+        Ok(select_preferred_window(&self.candidates(app_name)?))
+    }
+
+    fn find_windows(&mut self, app_name: &str) -> BackendResult<Vec<u64>> {
+        Ok(self
+            .candidates(app_name)?
+            .into_iter()
+            .map(|c| c.window)
+            .collect())
+    }
+
+    fn list_windows(&mut self, app_name: &str) -> BackendResult<Vec<WindowInfo>> {
+        let matching = self.matching(app_name)?;
+        let (current, active) =
+            self.with_session(None, |s| Ok((s.current_desktop()?, s.active_window()?)))?;
+        Ok(matching
+            .into_iter()
+            .map(|p| WindowInfo {
+                id: p.window.into(),
+                on_current_ws: p.on_desktop(current),
+                visible: p.visible(),
+                focused: active == Some(p.window),
+                workspace: p.desktop.map(|d| {
+                    if d == ALL_DESKTOPS {
+                        "all".to_string()
+                    } else {
+                        d.to_string()
+                    }
+                }),
+                class: p.class,
+                title: p.title,
+                pid: p.pid,
+            })
+            .collect())
+    }
+
+    fn is_on_current_workspace(&mut self, window: u64) -> BackendResult<bool> {
+        self.on_window(window, |s, w| {
+            let props = s.window(w)?;
+            Ok(props.on_desktop(s.current_desktop()?))
+        })
+    }
+
+    fn is_visible(&mut self, window: u64) -> BackendResult<bool> {
+        self.on_window(window, |s, w| Ok(s.window(w)?.visible()))
+    }
+
+    // Client messages go to the root window, so a closed window has to be
+    // noticed beforehand.
+    fn move_to_current_workspace(&mut self, window: u64) -> BackendResult<()> {
+        self.on_window(window, |s, w| {
+            s.conn.get_window_attributes(w)?.reply()?;
+            s.move_to_current_desktop(w)
+        })
+    }
+
+    fn show(&mut self, window: u64) -> BackendResult<()> {
+        self.on_window(window, |s, w| {
+            s.conn.get_window_attributes(w)?.reply()?;
+            s.show(w)
+        })
+    }
+
+    fn hide(&mut self, window: u64) -> BackendResult<()> {
+        self.on_window(window, |s, w| {
+            s.conn.get_window_attributes(w)?.reply()?;
+            s.hide(w)
+        })
+    }
+
+    fn launch_app(&mut self, app_path: &str) -> BackendResult<u32> {
+        spawn_app(app_path)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{BufRead, BufReader};
+    use std::process::{Child, Command, Stdio};
+    use x11rb::protocol::xproto::{CreateWindowAux, WindowClass};
+
+    // A private Xvfb. Tests that need one are #[ignore]d so a missing Xvfb
+    // shows up as ignored, not passed; run them with `cargo test -- --ignored`.
+    struct Xvfb(Child, String);
+
+    impl Xvfb {
+        fn start() -> Self {
+            Self::spawn().expect("Xvfb not found; install it to run this test")
+        }
+
+        fn spawn() -> Option<Self> {
+            let mut child = Command::new("Xvfb")
+                .args(["-displayfd", "1", "-nolisten", "tcp"])
+                .stdout(Stdio::piped())
+                .stderr(Stdio::null())
+                .spawn()
+                .ok()?;
+            let mut line = String::new();
+            BufReader::new(child.stdout.take()?)
+                .read_line(&mut line)
+                .ok()?;
+            Some(Self(child, format!(":{}", line.trim())))
+        }
+    }
+
+    impl Drop for Xvfb {
+        fn drop(&mut self) {
+            let _ = self.0.kill();
+            let _ = self.0.wait();
+        }
+    }
+
+    // A top-level window with WM_CLASS and _NET_WM_NAME, as a client sets them.
+    fn create_window(s: &Session, class: &str, title: &str) -> Window {
+        let window = s.conn.generate_id().unwrap();
+        s.conn
+            .create_window(
+                0,
+                window,
+                s.root,
+                0,
+                0,
+                10,
+                10,
+                0,
+                WindowClass::INPUT_OUTPUT,
+                0,
+                &CreateWindowAux::new(),
+            )
+            .unwrap();
+        let wm_class = format!("{}\0{class}\0", class.to_lowercase());
+        s.conn
+            .change_property8(
+                PropMode::REPLACE,
+                window,
+                AtomEnum::WM_CLASS,
+                AtomEnum::STRING,
+                wm_class.as_bytes(),
+            )
+            .unwrap();
+        s.conn
+            .change_property8(
+                PropMode::REPLACE,
+                window,
+                s.atoms._NET_WM_NAME,
+                s.atoms.UTF8_STRING,
+                title.as_bytes(),
+            )
+            .unwrap();
+        s.conn.map_window(window).unwrap();
+        s.conn.sync().unwrap();
+        window
+    }
+
+    #[test]
+    #[ignore = "needs Xvfb"]
+    fn finds_and_toggles_windows_without_a_window_manager() {
+        let xvfb = Xvfb::start();
+        let client = Session::connect(Some(&xvfb.1)).unwrap();
+        let term = create_window(&client, "Alacritty", "~");
+        let notes = create_window(&client, "TextEditor", "notes");
+
+        let mut backend = X11rbBackend::new();
+        backend.display = Some(xvfb.1.clone());
+        assert_eq!(
+            backend.find_window("class=Alacritty").unwrap(),
+            Some(term.into())
+        );
+        let listed = backend.list_windows("title=notes").unwrap();
+        assert_eq!(listed.len(), 1);
+        assert_eq!(listed[0].id, u64::from(notes));
+        assert_eq!(listed[0].class.as_deref(), Some("TextEditor"));
+        assert!(listed[0].on_current_ws && listed[0].visible);
+
+        // Without EWMH, hide and show unmap and map
+        backend.hide(term.into()).unwrap();
+        assert!(!backend.is_visible(term.into()).unwrap());
+        backend.show(term.into()).unwrap();
+        assert!(backend.is_visible(term.into()).unwrap());
+
+        client.conn.destroy_window(notes).unwrap();
+        client.conn.sync().unwrap();
+        assert!(matches!(
+            backend.hide(notes.into()),
+            Err(BackendError::WindowGone(w)) if w == u64::from(notes)
+        ));
+        assert_eq!(
+            backend.find_windows("title=notes").unwrap(),
+            Vec::<u64>::new()
+        );
+        assert_eq!(
+            backend.with_session(None, |s| Ok(s.wm_name())).unwrap(),
+            None
+        );
+    }
+
+    #[test]
+    fn bad_window_errors_name_the_window() {
+        let error = X11Error {
+            error_kind: ErrorKind::Window,
+            error_code: 3,
+            sequence: 1,
+            bad_value: 0x0040_0001,
+            minor_opcode: 0,
+            major_opcode: 20,
+            extension_name: None,
+            request_name: Some("GetProperty"),
+        };
+        assert!(matches!(
+            to_backend_error(Some(0x0040_0001), error.clone()),
+            BackendError::WindowGone(0x0040_0001)
+        ));
+        assert!(matches!(
+            to_backend_error(Some(0x0040_0002), error),
+            BackendError::Protocol(e) if e == "Window error in GetProperty (resource 0x00400001)"
+        ));
+    }
+}